@@ -14,13 +14,17 @@ fi
 
 # Save the input to a variable
 SAVE_DATA=$1
+SAVE_FLAG=""
+if [[ "$SAVE_DATA" == "1" ]]; then
+    SAVE_FLAG="--save"
+fi
 
-cargo run --release -p bicycle_corr_exp -- rrrlc static $SAVE_DATA
-cargo run --release -p bicycle_corr_exp -- rrrlc dynamic $SAVE_DATA
-cargo run --release -p quadcopter_corr_exp -- rrrlc static $SAVE_DATA
-cargo run --release -p quadcopter_corr_exp -- rrrlc dynamic $SAVE_DATA
+cargo run --release -p rustreach -- run --env corr --vehicle bicycle --algorithm rrrlc --obstacles static $SAVE_FLAG
+cargo run --release -p rustreach -- run --env corr --vehicle bicycle --algorithm rrrlc --obstacles dynamic $SAVE_FLAG
+cargo run --release -p rustreach -- run --env corr --vehicle quadcopter --algorithm rrrlc --obstacles static $SAVE_FLAG
+cargo run --release -p rustreach -- run --env corr --vehicle quadcopter --algorithm rrrlc --obstacles dynamic $SAVE_FLAG
 
-cargo run --release -p bicycle_nbd_exp -- rrrlc astar static $SAVE_DATA
-cargo run --release -p bicycle_nbd_exp -- rrrlc astar dynamic $SAVE_DATA
-cargo run --release -p quadcopter_nbd_exp -- rrrlc astar static $SAVE_DATA
-cargo run --release -p quadcopter_nbd_exp -- rrrlc astar dynamic $SAVE_DATA
\ No newline at end of file
+cargo run --release -p rustreach -- run --env nbd --vehicle bicycle --algorithm rrrlc --waypoints astar --obstacles static $SAVE_FLAG
+cargo run --release -p rustreach -- run --env nbd --vehicle bicycle --algorithm rrrlc --waypoints astar --obstacles dynamic $SAVE_FLAG
+cargo run --release -p rustreach -- run --env nbd --vehicle quadcopter --algorithm rrrlc --waypoints astar --obstacles static $SAVE_FLAG
+cargo run --release -p rustreach -- run --env nbd --vehicle quadcopter --algorithm rrrlc --waypoints astar --obstacles dynamic $SAVE_FLAG
\ No newline at end of file
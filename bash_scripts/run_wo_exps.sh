@@ -14,18 +14,22 @@ fi
 
 # Save the input to a variable
 SAVE_DATA=$1
+SAVE_FLAG=""
+if [[ "$SAVE_DATA" == "1" ]]; then
+    SAVE_FLAG="--save"
+fi
 
-cargo run --release -p bicycle_corr_exp -- wo static $SAVE_DATA
-cargo run --release -p bicycle_corr_exp -- wo dynamic $SAVE_DATA
-cargo run --release -p quadcopter_corr_exp -- wo static $SAVE_DATA
-cargo run --release -p quadcopter_corr_exp -- wo dynamic $SAVE_DATA
+cargo run --release -p rustreach -- run --env corr --vehicle bicycle --algorithm wo --obstacles static $SAVE_FLAG
+cargo run --release -p rustreach -- run --env corr --vehicle bicycle --algorithm wo --obstacles dynamic $SAVE_FLAG
+cargo run --release -p rustreach -- run --env corr --vehicle quadcopter --algorithm wo --obstacles static $SAVE_FLAG
+cargo run --release -p rustreach -- run --env corr --vehicle quadcopter --algorithm wo --obstacles dynamic $SAVE_FLAG
 
-cargo run --release -p bicycle_nbd_exp -- wo astar static $SAVE_DATA
-cargo run --release -p bicycle_nbd_exp -- wo astar dynamic $SAVE_DATA
-cargo run --release -p quadcopter_nbd_exp -- wo astar static $SAVE_DATA
-cargo run --release -p quadcopter_nbd_exp -- wo astar dynamic $SAVE_DATA
+cargo run --release -p rustreach -- run --env nbd --vehicle bicycle --algorithm wo --waypoints astar --obstacles static $SAVE_FLAG
+cargo run --release -p rustreach -- run --env nbd --vehicle bicycle --algorithm wo --waypoints astar --obstacles dynamic $SAVE_FLAG
+cargo run --release -p rustreach -- run --env nbd --vehicle quadcopter --algorithm wo --waypoints astar --obstacles static $SAVE_FLAG
+cargo run --release -p rustreach -- run --env nbd --vehicle quadcopter --algorithm wo --waypoints astar --obstacles dynamic $SAVE_FLAG
 
-cargo run --release -p bicycle_nbd_exp -- wo rrt static $SAVE_DATA
-cargo run --release -p bicycle_nbd_exp -- wo rrt dynamic $SAVE_DATA
-cargo run --release -p quadcopter_nbd_exp -- wo rrt static $SAVE_DATA
-cargo run --release -p quadcopter_nbd_exp -- wo rrt dynamic $SAVE_DATA
\ No newline at end of file
+cargo run --release -p rustreach -- run --env nbd --vehicle bicycle --algorithm wo --waypoints rrt --obstacles static $SAVE_FLAG
+cargo run --release -p rustreach -- run --env nbd --vehicle bicycle --algorithm wo --waypoints rrt --obstacles dynamic $SAVE_FLAG
+cargo run --release -p rustreach -- run --env nbd --vehicle quadcopter --algorithm wo --waypoints rrt --obstacles static $SAVE_FLAG
+cargo run --release -p rustreach -- run --env nbd --vehicle quadcopter --algorithm wo --waypoints rrt --obstacles dynamic $SAVE_FLAG
\ No newline at end of file
@@ -1,12 +1,34 @@
-use super::dynamics_bicycle::{BicycleModel, BICYCLE_NUM_DIMS as NUM_DIMS};
+use super::dynamics_bicycle::{BicycleModel, BICYCLE_NUM_DIMS as NUM_DIMS, BICYCLE_NUM_CTRLS as NUM_CTRLS};
 use super::simulate_bicycle::simulate_bicycle;
 use rtreach::geometry::HyperRectangle;
-use rtreach::obstacle_safety::{check_safety_obstacles, check_safety_wall, OBSTACLES, DYNAMIC_OBSTACLE_COUNT, OBSTACLE_COUNT};
-use rtreach::face_lift::{LiftingSettings, face_lifting_iterative_improvement};
+use rtreach::obstacle::Obstacle;
+use rtreach::footprint::Footprint;
+use rtreach::obstacle_safety::{check_safety_obstacles, check_safety_obstacles_indexed, check_safety_wall, obstacles_clearance, wall_clearance, OBSTACLES, DYNAMIC_OBSTACLE_COUNT, OBSTACLE_COUNT};
+use rtreach::face_lift::{LiftingOptions, LiftingResult, LiftingSettings, face_lifting_iterative_improvement, face_lifting_with};
+use rtreach::initial_split::{InitialSetSplit, SubBoxRun, face_lifting_split};
+use rtreach::safe_zones::{Grid, label_grid};
+use rtreach::stl::{stl_monitor_check, stl_monitor_enabled, stl_monitor_record, stl_monitor_restarted};
+use lazy_static::lazy_static;
+use std::sync::Mutex;
+
 // a note from the f1tenth simulator 
 // the car is 0.5 m long in the x direction 
 // 0.3 long in the y direction
 
+// footprint used by the safety checks, theta is the heading
+pub const HEADING_DIM: usize = 3;
+lazy_static! {
+    pub static ref BICYCLE_FOOTPRINT: Mutex<Footprint> = Mutex::new(Footprint::Rectangle { length: 0.5, width: 0.3 });
+}
+
+// obstacles and walls further away than this do not count for the clearance
+pub const CLEARANCE_RANGE: f64 = 2.0;
+
+pub fn set_footprint(footprint: Footprint) {
+    let mut footprint_lock = BICYCLE_FOOTPRINT.lock().unwrap();
+    *footprint_lock = footprint;
+}
+
 // function that stops simulation after two seconds
 pub fn should_stop(_: [f64; NUM_DIMS], sim_time: f64, stop_time: &mut f64) -> bool {
     let mut rv = false;
@@ -30,21 +52,22 @@ pub fn get_simulated_safe_time(system_model: &BicycleModel, start: [f64; NUM_DIM
 }
 
 // called on states reached during the computation
-pub fn intermediate_state(r: &mut HyperRectangle<NUM_DIMS>, time: f64, obstacle_sim_fn: fn(t: f64, obs: &mut Vec<Vec<Vec<f64>>>), store_rect: bool, storage_vec: &mut Vec<(f64, HyperRectangle<NUM_DIMS>)>) -> bool {
+pub fn intermediate_state(r: &mut HyperRectangle<NUM_DIMS>, time: f64, obstacle_sim_fn: fn(t: f64, obs: &mut Vec<Obstacle>), store_rect: bool, storage_vec: &mut Vec<(f64, HyperRectangle<NUM_DIMS>)>) -> bool {
     if store_rect {
         storage_vec.push((time, *r));
     }
+    if stl_monitor_enabled() {
+        stl_monitor_record(r, time);
+    }
     
     let mut allowed: bool;
     //const REAL FIFTEEN_DEGREES_IN_RADIANS = 0.2618;
 
-    // bloat the box for the width of the car
-    r.dims[0].min = r.dims[0].min - 0.25;
-    r.dims[0].max = r.dims[0].max + 0.25;
-    r.dims[1].min = r.dims[1].min - 0.15;
-    r.dims[1].max = r.dims[1].max + 0.15;
+    // bloat the box for the footprint of the car over the headings in the box
+    let footprint: Footprint = *BICYCLE_FOOTPRINT.lock().unwrap();
+    let b = footprint.bloat(r, Some(HEADING_DIM));
 
-    let obstacles: &Option<Vec<Vec<Vec<f64>>>> = &*OBSTACLES.lock().unwrap();
+    let obstacles: &Option<Vec<Obstacle>> = &*OBSTACLES.lock().unwrap();
     match obstacles {
         Some(obst) => {
             let dyn_obs_ct = *DYNAMIC_OBSTACLE_COUNT.lock().unwrap();
@@ -52,8 +75,8 @@ pub fn intermediate_state(r: &mut HyperRectangle<NUM_DIMS>, time: f64, obstacle_
             let mut dyn_obs_vec = obst[0..dyn_obs_ct as usize].to_vec();
             obstacle_sim_fn(time, &mut dyn_obs_vec);
             allowed = 
-            check_safety_obstacles(r, &dyn_obs_vec, dyn_obs_ct) &&
-            check_safety_obstacles(r, &obst[(dyn_obs_ct as usize)..], tot_obs_ct - dyn_obs_ct);
+            check_safety_obstacles(&b, &dyn_obs_vec, dyn_obs_ct) &&
+            check_safety_obstacles_indexed(&b, obst, dyn_obs_ct, tot_obs_ct - dyn_obs_ct);
         },
         None => {
             allowed = true;
@@ -61,15 +84,9 @@ pub fn intermediate_state(r: &mut HyperRectangle<NUM_DIMS>, time: f64, obstacle_
     }
 
     if allowed {
-        allowed = check_safety_wall(r);
+        allowed = check_safety_wall(&b);
     }
 
-    // reset it
-    r.dims[0].min = r.dims[0].min + 0.25;
-    r.dims[0].max = r.dims[0].max - 0.25;
-    r.dims[1].min = r.dims[1].min + 0.15;
-    r.dims[1].max = r.dims[1].max - 0.15;
-
     // if !allowed {
     //    println!("unsafe....");
     // }
@@ -80,13 +97,44 @@ pub fn intermediate_state(r: &mut HyperRectangle<NUM_DIMS>, time: f64, obstacle_
 
 // This function enumerates all of the corners of the current HyperRectangle and 
 // returns whether or not any of the points lies outside of the ellipsoid
-pub fn final_state(r: &mut HyperRectangle<NUM_DIMS>, time: f64, obstacle_sim_fn: fn(t: f64, obs: &mut Vec<Vec<Vec<f64>>>), store_rect: bool, storage_vec: &mut Vec<(f64, HyperRectangle<NUM_DIMS>)>) -> bool {
-    intermediate_state(r, time, obstacle_sim_fn, store_rect, storage_vec)
+pub fn final_state(r: &mut HyperRectangle<NUM_DIMS>, time: f64, obstacle_sim_fn: fn(t: f64, obs: &mut Vec<Obstacle>), store_rect: bool, storage_vec: &mut Vec<(f64, HyperRectangle<NUM_DIMS>)>) -> bool {
+    // the stl monitor, if set, is evaluated on the whole tube at the final time
+    intermediate_state(r, time, obstacle_sim_fn, store_rect, storage_vec) && stl_monitor_check()
 }
 
 // Clear all but the first rectangle (initial state) in the storage vector
 pub fn restarted_computation(_: bool, storage_vec: &mut Vec<(f64, HyperRectangle<NUM_DIMS>)>) {
     storage_vec.truncate(1);
+    stl_monitor_restarted();
+}
+
+// Signed distance from the footprint over the box to the nearest obstacle or wall at the
+// given time, negative if it overlaps an obstacle and 0 if it touches a wall.
+// Capped at CLEARANCE_RANGE.
+pub fn clearance(r: &HyperRectangle<NUM_DIMS>, time: f64, obstacle_sim_fn: fn(t: f64, obs: &mut Vec<Obstacle>)) -> f64 {
+    let footprint: Footprint = *BICYCLE_FOOTPRINT.lock().unwrap();
+    let b = footprint.bloat(r, Some(HEADING_DIM));
+
+    let mut rv = wall_clearance(b.dims[0], b.dims[1], CLEARANCE_RANGE).min;
+    let obstacles: &Option<Vec<Obstacle>> = &*OBSTACLES.lock().unwrap();
+    if let Some(obst) = obstacles {
+        let dyn_obs_ct = *DYNAMIC_OBSTACLE_COUNT.lock().unwrap();
+        let tot_obs_ct = *OBSTACLE_COUNT.lock().unwrap();
+        let mut dyn_obs_vec = obst[0..dyn_obs_ct as usize].to_vec();
+        obstacle_sim_fn(time, &mut dyn_obs_vec);
+        rv = rv
+            .min(obstacles_clearance(&b, &dyn_obs_vec, dyn_obs_ct))
+            .min(obstacles_clearance(&b, &obst[dyn_obs_ct as usize..], tot_obs_ct - dyn_obs_ct));
+    }
+    rv
+}
+
+// Obstacle covered by the footprint of the car over the box, for other agents to avoid, see
+// rtreach::multi_agent
+pub fn footprint_obstacle(r: &HyperRectangle<NUM_DIMS>) -> Obstacle {
+    let footprint: Footprint = *BICYCLE_FOOTPRINT.lock().unwrap();
+    let b = footprint.bloat(r, Some(HEADING_DIM));
+    Obstacle::Rect { x: b.dims[0], y: b.dims[1] }
 }
 
 pub fn has_collided(state: &[f64; NUM_DIMS]) -> bool {
@@ -96,17 +144,18 @@ pub fn has_collided(state: &[f64; NUM_DIMS]) -> bool {
         r.dims[d].min = state[d];
         r.dims[d].max = state[d];
     }
-    r.dims[0].min = r.dims[0].min - 0.25;
-    r.dims[0].max = r.dims[0].max + 0.25;
-    r.dims[1].min = r.dims[1].min - 0.15;
-    r.dims[1].max = r.dims[1].max + 0.15;
+    let footprint: Footprint = *BICYCLE_FOOTPRINT.lock().unwrap();
+    let r = footprint.bloat(&r, Some(HEADING_DIM));
 
     let mut allowed: bool;
-    let obstacles: &Option<Vec<Vec<Vec<f64>>>> = &*OBSTACLES.lock().unwrap();
+    let obstacles: &Option<Vec<Obstacle>> = &*OBSTACLES.lock().unwrap();
     match obstacles {
         Some(obst) => {
+            let dyn_obs_ct = *DYNAMIC_OBSTACLE_COUNT.lock().unwrap();
             let tot_obs_ct = *OBSTACLE_COUNT.lock().unwrap();
-            allowed = check_safety_obstacles(&r, obst, tot_obs_ct);
+            allowed = 
+            check_safety_obstacles(&r, obst, dyn_obs_ct) &&
+            check_safety_obstacles_indexed(&r, obst, dyn_obs_ct, tot_obs_ct - dyn_obs_ct);
         },
         None => {
             allowed = true;
@@ -128,20 +177,94 @@ pub fn run_reachability_bicycle(system_model: &BicycleModel,
                                 start: [f64; NUM_DIMS], 
                                 sim_time: f64,
                                 init_step_size: f64, 
-                                wall_time_ms: u64, 
+                                max_runtime_us: u64, 
                                 start_ms: u64, 
                                 heading_input: f64, 
                                 throttle: f64, 
                                 store_rect: bool,
                                 fixed_step: bool,
                                 dynamic_control: bool,
-                                obstacle_sim_fn: fn(t: f64, obs: &mut Vec<Vec<Vec<f64>>>)) -> (bool, Vec<(f64, HyperRectangle<NUM_DIMS>)>) {
+                                obstacle_sim_fn: fn(t: f64, obs: &mut Vec<Obstacle>)) -> (bool, Vec<(f64, HyperRectangle<NUM_DIMS>)>) {
+    let mut set = lifting_settings(start, sim_time, init_step_size, max_runtime_us, obstacle_sim_fn);
+    let mut storage_vec: Vec<(f64, HyperRectangle<NUM_DIMS>)> = Vec::new();
+    storage_vec.push((0.0, set.init));
+    let safe = face_lifting_iterative_improvement(system_model, 
+                                                        start_ms, 
+                                                        &mut set, 
+                                                        &[heading_input, throttle], 
+                                                        store_rect, 
+                                                        &mut storage_vec,
+                                                        fixed_step,
+                                                        dynamic_control);
+    (safe, storage_vec)
+}
+
+
+// Safe zones of the initial states in region on a grid of cells[d] cells along dimension d,
+// see label_grid. The reach set of each cell is computed for sim_time within max_runtime_us,
+// under the fixed control or, with dynamic_control, the policy of the model.
+pub fn label_safe_zones_bicycle(system_model: &BicycleModel, 
+                                region: HyperRectangle<NUM_DIMS>, 
+                                cells: [usize; NUM_DIMS], 
+                                sim_time: f64,
+                                init_step_size: f64, 
+                                max_runtime_us: u64, 
+                                start_ms: u64, 
+                                heading_input: f64, 
+                                throttle: f64, 
+                                fixed_step: bool,
+                                dynamic_control: bool,
+                                obstacle_sim_fn: fn(t: f64, obs: &mut Vec<Obstacle>)) -> Vec<(HyperRectangle<NUM_DIMS>, bool)> {
+    let set = lifting_settings([0.0; NUM_DIMS], sim_time, init_step_size, max_runtime_us, obstacle_sim_fn);
+    label_grid(system_model, 
+               start_ms, 
+               &set, 
+               &Grid { region, cells }, 
+               &[heading_input, throttle], 
+               fixed_step, 
+               dynamic_control)
+}
+
+// run_reachability_bicycle from settings made by lifting_settings with what options adds,
+// see face_lifting_with. Returns the result and the stored rects, which start with the
+// initial state.
+pub fn run_reachability_bicycle_with(system_model: &BicycleModel, 
+                                     start_ms: u64, 
+                                     set: &mut LiftingSettings<NUM_DIMS>, 
+                                     ctrl_input: &[f64; NUM_CTRLS], 
+                                     options: LiftingOptions<NUM_DIMS>) -> (LiftingResult, Vec<(f64, HyperRectangle<NUM_DIMS>)>) {
+    let mut storage_vec: Vec<(f64, HyperRectangle<NUM_DIMS>)> = Vec::new();
+    storage_vec.push((0.0, set.init));
+    let result = face_lifting_with(system_model, start_ms, set, ctrl_input, &mut storage_vec, options);
+    (result, storage_vec)
+}
+
+// face_lifting_split from set.init, for uncertain initial states. The rects of each sub-box
+// follow set.init in the stored rects. The sub-boxes cannot run in parallel while an STL
+// monitor is set, the callbacks record into its one trace.
+pub fn run_reachability_bicycle_split(system_model: &BicycleModel, 
+                                      start_ms: u64, 
+                                      set: &LiftingSettings<NUM_DIMS>, 
+                                      ctrl_input: &[f64; NUM_CTRLS], 
+                                      split: &InitialSetSplit, 
+                                      run: &SubBoxRun) -> Result<(bool, Vec<(f64, HyperRectangle<NUM_DIMS>)>), String> {
+    if split.parallel && stl_monitor_enabled() {
+        return Err("the sub-boxes cannot run in parallel while an STL monitor is set".to_string());
+    }
+    let mut storage_vec: Vec<(f64, HyperRectangle<NUM_DIMS>)> = Vec::new();
+    storage_vec.push((0.0, set.init));
+    let safe = face_lifting_split(system_model, start_ms, set, ctrl_input, split, run, &mut storage_vec);
+    Ok((safe, storage_vec))
+}
+
+// settings of a reach computation from start, checked against the obstacles and walls
+pub fn lifting_settings(start: [f64; NUM_DIMS], sim_time: f64, init_step_size: f64, max_runtime_us: u64, obstacle_sim_fn: fn(t: f64, obs: &mut Vec<Obstacle>)) -> LiftingSettings<NUM_DIMS> {
     let mut set: LiftingSettings<NUM_DIMS> = LiftingSettings::<NUM_DIMS> {
         init: HyperRectangle::default(),
         reach_time: sim_time,
         initial_step_size: init_step_size,
         max_rect_width_before_error: 100.0,
-        max_runtime_milliseconds: wall_time_ms,
+        max_runtime_us,
         obstacle_sim_fn: obstacle_sim_fn,
         reached_at_intermediate_time: Some(intermediate_state),
         reached_at_final_time: Some(final_state),
@@ -151,15 +274,5 @@ pub fn run_reachability_bicycle(system_model: &BicycleModel,
         set.init.dims[d].min = start[d];
         set.init.dims[d].max = start[d];
     }
-    let mut storage_vec: Vec<(f64, HyperRectangle<NUM_DIMS>)> = Vec::new();
-    storage_vec.push((0.0, set.init));
-    let safe = face_lifting_iterative_improvement(system_model, 
-                                                        start_ms, 
-                                                        &mut set, 
-                                                        &vec![heading_input, throttle], 
-                                                        store_rect, 
-                                                        &mut storage_vec,
-                                                        fixed_step,
-                                                        dynamic_control);
-    (safe, storage_vec)
+    set
 }
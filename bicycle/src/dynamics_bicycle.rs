@@ -3,8 +3,10 @@ use tract_onnx::prelude::*;
 use rtreach::geometry::*;
 use rtreach::interval::*;
 use rtreach::system_model::SystemModel;
+use rtreach::face_lift::make_neighborhood_rect;
 
 pub const BICYCLE_NUM_DIMS: usize = 4;
+pub const BICYCLE_NUM_CTRLS: usize = 2;
 
 // a bicycle model to model the car's dynamics. The bicycle model is a standard model for cars with front steering. 
 // This model tracks well for slow speeds
@@ -44,22 +46,25 @@ pub const BICYCLE_NUM_DIMS: usize = 4;
 
 pub struct BicycleModel<'a> {
     pub goal: [f64; 2],
-    pub ctrl_fn: fn(&[f64; BICYCLE_NUM_DIMS], &[f64; 2], Option<&'a SimplePlan<TypedFact, Box<dyn TypedOp>, Graph<TypedFact, Box<dyn TypedOp>>>>) -> [f64; 2],
-    pub model: Option<&'a SimplePlan<TypedFact, Box<dyn TypedOp>, Graph<TypedFact, Box<dyn TypedOp>>>>
+    pub ctrl_fn: fn(&[f64; BICYCLE_NUM_DIMS], &[f64; 2], Option<&'a SimplePlan<TypedFact, Box<dyn TypedOp>, Graph<TypedFact, Box<dyn TypedOp>>>>, f64) -> [f64; 2],
+    pub model: Option<&'a SimplePlan<TypedFact, Box<dyn TypedOp>, Graph<TypedFact, Box<dyn TypedOp>>>>,
+    // multiplies the velocity command of ctrl_fn
+    pub speed_scale: f64,
 }
 
 impl Default for BicycleModel<'_> {
     fn default() -> Self {
         BicycleModel {
             goal: [0.0; 2],
-            ctrl_fn: |_, _, _| [0.0; 2],
-            model: None
+            ctrl_fn: |_, _, _, _| [0.0; 2],
+            model: None,
+            speed_scale: 1.0,
         }
     }
 }
 
 impl<'a> BicycleModel<'a> {
-    pub fn set_ctrl_fn(&mut self, ctrl_fn: fn(&[f64; BICYCLE_NUM_DIMS], &[f64; 2], Option<&SimplePlan<TypedFact, Box<dyn TypedOp>, Graph<TypedFact, Box<dyn TypedOp>>>>) -> [f64; 2]) {
+    pub fn set_ctrl_fn(&mut self, ctrl_fn: fn(&[f64; BICYCLE_NUM_DIMS], &[f64; 2], Option<&SimplePlan<TypedFact, Box<dyn TypedOp>, Graph<TypedFact, Box<dyn TypedOp>>>>, f64) -> [f64; 2]) {
         self.ctrl_fn = ctrl_fn;
     }
 
@@ -67,40 +72,85 @@ impl<'a> BicycleModel<'a> {
         self.goal = goal;
     }
 
+    pub fn set_speed_scale(&mut self, speed_scale: f64) {
+        self.speed_scale = speed_scale;
+    }
+
     pub fn set_model(&mut self, model: &'a SimplePlan<TypedFact, Box<dyn TypedOp>, Graph<TypedFact, Box<dyn TypedOp>>>) {
         self.model = Some(model);
     }
 
     pub fn sample_state_action(&self, state: &[f64; BICYCLE_NUM_DIMS]) -> [f64; 2] {
-        (self.ctrl_fn)(state, &self.goal, self.model)
+        (self.ctrl_fn)(state, &self.goal, self.model, self.speed_scale)
     }
 }
 
-impl SystemModel<BICYCLE_NUM_DIMS> for BicycleModel<'_> {
+impl SystemModel<BICYCLE_NUM_DIMS, BICYCLE_NUM_CTRLS> for BicycleModel<'_> {
     fn get_derivative_bounds(
         &self,
         rect: &HyperRectangle<BICYCLE_NUM_DIMS>,
         face_index: usize,
-        ctrl_inputs: &Vec<f64>,
+        ctrl_inputs: &[f64; BICYCLE_NUM_CTRLS],
     ) -> f64 {
         _get_derivative_bounds_bicycle(rect, face_index, ctrl_inputs[0], ctrl_inputs[1])
     }
 
+    // x', y' and theta' do not depend on x, y and theta, so both face neighborhoods of these
+    // dimensions give the bounds on bloated_rect and each is evaluated once. Only v' is
+    // evaluated per face.
+    fn get_face_derivative_bounds(
+        &self,
+        rect: &HyperRectangle<BICYCLE_NUM_DIMS>,
+        bloated_rect: &HyperRectangle<BICYCLE_NUM_DIMS>,
+        neb_width: &[[f64; 2]; BICYCLE_NUM_DIMS],
+        ctrl_inputs: &[f64; BICYCLE_NUM_CTRLS],
+    ) -> [Interval; BICYCLE_NUM_DIMS] {
+        let mut rv: [Interval; BICYCLE_NUM_DIMS] = [Interval::default(); BICYCLE_NUM_DIMS];
+        for d in [0, 1, 3] {
+            rv[d] = _get_derivative_interval_bicycle(bloated_rect, d, ctrl_inputs[0], ctrl_inputs[1]);
+        }
+
+        let mut face_neb_rect: HyperRectangle<BICYCLE_NUM_DIMS> = HyperRectangle::default();
+        make_neighborhood_rect(&mut face_neb_rect, 4, bloated_rect, rect, neb_width[2][0]);
+        rv[2].min = _get_derivative_interval_bicycle(&face_neb_rect, 2, ctrl_inputs[0], ctrl_inputs[1]).min;
+        make_neighborhood_rect(&mut face_neb_rect, 5, bloated_rect, rect, neb_width[2][1]);
+        rv[2].max = _get_derivative_interval_bicycle(&face_neb_rect, 2, ctrl_inputs[0], ctrl_inputs[1]).max;
+        rv
+    }
+
     fn sample_control(
         &self,
         rect: &HyperRectangle<BICYCLE_NUM_DIMS>,
-    ) -> Vec<f64> {
-        (self.ctrl_fn)(&rect.mean_point().dims, &self.goal, self.model).to_vec()
+    ) -> [f64; BICYCLE_NUM_CTRLS] {
+        (self.ctrl_fn)(&rect.mean_point().dims, &self.goal, self.model, self.speed_scale)
     }
 }
 
-// implement the derivative using interval arithmetic
 fn _get_derivative_bounds_bicycle(
     rect: &HyperRectangle<BICYCLE_NUM_DIMS>,
     face_index: usize,
     heading_input: f64,
     throttle: f64,
 ) -> f64 {
+    let dim: usize = face_index / 2;
+    let is_min: bool = (face_index % 2) == 0;
+
+    let rv: Interval = _get_derivative_interval_bicycle(rect, dim, heading_input, throttle);
+
+    if is_min {
+        rv.min
+    } else {
+        rv.max
+    }
+}
+
+// implement the derivative using interval arithmetic
+fn _get_derivative_interval_bicycle(
+    rect: &HyperRectangle<BICYCLE_NUM_DIMS>,
+    dim: usize,
+    heading_input: f64,
+    throttle: f64,
+) -> Interval {
     let u: f64 = throttle;
     let delta: f64 = heading_input;
     let ca: f64 = 1.9569;      // 1.633
@@ -109,9 +159,6 @@ fn _get_derivative_bounds_bicycle(
     let lf: f64 = 0.225;
     let lr: f64 = 0.225;
 
-    let dim: usize = face_index / 2;
-    let is_min: bool = (face_index % 2) == 0;
-
     // Interval rv.min = rv.max = 0
     let rv: Interval;
     let v: Interval = rect.dims[2];
@@ -150,9 +197,5 @@ fn _get_derivative_bounds_bicycle(
         }
     }
 
-    if is_min {
-        rv.min
-    } else {
-        rv.max
-    }
+    rv
 }
\ No newline at end of file
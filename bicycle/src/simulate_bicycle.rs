@@ -56,7 +56,7 @@ pub fn step_bicycle(
 
     // euler's method
     for d in 0..NUM_DIMS {
-        let der: f64 = system_model.get_derivative_bounds(&rect, 2*d, &vec![heading_input, throttle]);
+        let der: f64 = system_model.get_derivative_bounds(&rect, 2*d, &[heading_input, throttle]);
 
         next_point[d] += step_size * der;
     }
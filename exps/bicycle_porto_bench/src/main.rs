@@ -0,0 +1,127 @@
+use std::env;
+use std::time::Instant;
+
+use rtreach::obstacle::Obstacle;
+use rtreach::face_lift::{LiftingOptions, ReachHint, ITERATIONS_AT_QUIT};
+use rtreach::obstacle_safety::{load_occupancy_grid, load_wallpoints, set_wall_segments, OCCUPANCY_GRID, SPATIAL_INDEX_ENABLED, WALL_COORDS};
+use rtreach::wall_segments::load_wall_segments_from_csv;
+use bicycle::dynamics_bicycle::BicycleModel;
+use bicycle::bicycle_model::{lifting_settings, run_reachability_bicycle, run_reachability_bicycle_with};
+
+const WALL_FILE_PATH: &str = "data/porto_obstacles.txt";
+const SEGMENTS_FILE_PATH: &str = "data/porto_segments.csv";
+const MAP_FILE_PATH: &str = "data/porto_map.yaml";
+
+// Time of one fixed step size iteration without walls, the cost of the lifting loop itself.
+// Then the face lifting iterations completed within the deadline, first without walls, then
+// on the Porto map with the linear wall scan, with the spatial index, with the wall segments
+// and with the occupancy grid. Then the finest step size reached within the deadline on the grid,
+// and the time it took, starting every run from the initial step size and warm starting each
+// run from the last, with how much of the deadline was left over.
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let runs: u32 = if args.len() > 1 { args[1].parse().unwrap() } else { 20 };
+    let runtime_ms: u64 = if args.len() > 2 { args[2].parse().unwrap() } else { 100 };
+
+    let current_dir = env::current_dir().expect("Failed to get current directory");
+
+    // top straight of the track, driving along it at the steady state speed
+    let start_state: [f64; 4] = [-2.0, 6.4, 1.0, 0.0];
+    let reach_time: f64 = 2.0;
+    let init_step_size: f64 = 0.1;
+    let bicycle_model = BicycleModel::default();
+
+    // fine enough that the iteration takes about a millisecond
+    let fixed_step_size = init_step_size / 64.0;
+    let start_time = Instant::now();
+    for _ in 0..runs {
+        run_reachability_bicycle(&bicycle_model, 
+                                 start_state, 
+                                 reach_time, 
+                                 fixed_step_size, 
+                                 0, 
+                                 0, 
+                                 0.0, 
+                                 0.0, 
+                                 false, 
+                                 true, 
+                                 false, 
+                                 obstacle_sim_fn_static);
+    }
+    println!("fixed step: {:.3} ms per iteration at step size {} on average", 
+             start_time.elapsed().as_micros() as f64 / 1000.0 / runs as f64, fixed_step_size);
+
+    for name in ["free", "linear", "indexed", "segments", "grid"] {
+        {
+            let mut index_enabled = SPATIAL_INDEX_ENABLED.lock().unwrap();
+            *index_enabled = name != "linear";
+        }
+        if name == "linear" {
+            let wall_path = current_dir.join(WALL_FILE_PATH);
+            load_wallpoints(wall_path.to_str().unwrap(), true);
+        }
+        if name == "segments" {
+            // replace the wall points with the segments
+            *WALL_COORDS.lock().unwrap() = None;
+            set_wall_segments(load_wall_segments_from_csv(&current_dir.join(SEGMENTS_FILE_PATH)));
+        }
+        if name == "grid" {
+            let map_path = current_dir.join(MAP_FILE_PATH);
+            load_occupancy_grid(map_path.to_str().unwrap(), true);
+            if OCCUPANCY_GRID.lock().unwrap().is_none() {
+                std::process::exit(1);
+            }
+        }
+
+        let mut total_iterations: u64 = 0;
+        let mut safe_runs: u32 = 0;
+        for _ in 0..runs {
+            let (safe, _) = run_reachability_bicycle(&bicycle_model, 
+                                                     start_state, 
+                                                     reach_time, 
+                                                     init_step_size, 
+                                                     runtime_ms * 1000, 
+                                                     0, 
+                                                     0.0, 
+                                                     0.0, 
+                                                     false, 
+                                                     false, 
+                                                     false, 
+                                                     obstacle_sim_fn_static);
+            total_iterations += *ITERATIONS_AT_QUIT.lock().unwrap();
+            if safe {
+                safe_runs += 1;
+            }
+        }
+        println!("{}: {:.1} iterations per {} ms deadline on average, {}/{} runs safe", 
+                 name, total_iterations as f64 / runs as f64, runtime_ms, safe_runs, runs);
+    }
+
+    let mut hint = ReachHint::default();
+    for warm in [false, true] {
+        let mut total_step_size: f64 = 0.0;
+        let mut total_slack_us: i64 = 0;
+        let start_time = Instant::now();
+        for _ in 0..runs {
+            if !warm {
+                hint = ReachHint::default();
+            }
+            let mut set = lifting_settings(start_state, reach_time, init_step_size, runtime_ms * 1000, obstacle_sim_fn_static);
+            let (result, _) = run_reachability_bicycle_with(&bicycle_model, 
+                                                            0, 
+                                                            &mut set, 
+                                                            &[0.0, 0.0], 
+                                                            LiftingOptions::default().warm_start(&mut hint));
+            total_step_size += hint.step_size;
+            total_slack_us += result.report.slack_us;
+        }
+        println!("{}: {:.6} step size per {} ms deadline in {:.1} ms on average, {:.1} ms slack", 
+                 if warm { "warm start" } else { "cold start" }, total_step_size / runs as f64, runtime_ms, 
+                 start_time.elapsed().as_micros() as f64 / 1000.0 / runs as f64, 
+                 total_slack_us as f64 / 1000.0 / runs as f64);
+    }
+}
+
+fn obstacle_sim_fn_static(_: f64, _: &mut Vec<Obstacle>) {
+    // Do nothing
+}
@@ -0,0 +1,69 @@
+// Flags of the subcommands: --name value, --name=value, switches like --save and positionals
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+pub struct Args {
+    pub positionals: Vec<String>,
+    values: HashMap<String, String>,
+    switches: Vec<String>,
+}
+
+// args after the subcommand, with the names of the flags that take a value and of the switches
+pub fn parse_args(args: &[String], value_flags: &[&str], switch_flags: &[&str]) -> Result<Args, String> {
+    let mut parsed = Args { positionals: Vec::new(), values: HashMap::new(), switches: Vec::new() };
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let Some(flag) = arg.strip_prefix("--") else {
+            parsed.positionals.push(arg.clone());
+            continue;
+        };
+        let (name, inline_value) = match flag.split_once('=') {
+            Some((name, value)) => (name, Some(value.to_string())),
+            None => (flag, None),
+        };
+        if value_flags.contains(&name) {
+            let value = match inline_value {
+                Some(value) => value,
+                None => iter.next().cloned().ok_or_else(|| format!("--{} needs a value", name))?,
+            };
+            parsed.values.insert(name.to_string(), value);
+        }
+        else if switch_flags.contains(&name) && inline_value.is_none() {
+            parsed.switches.push(name.to_string());
+        }
+        else {
+            return Err(format!("unknown flag {:?}", arg));
+        }
+    }
+    Ok(parsed)
+}
+
+impl Args {
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(String::as_str)
+    }
+
+    pub fn switch(&self, name: &str) -> bool {
+        self.switches.iter().any(|s| s == name)
+    }
+
+    // the value of --name parsed as T, default if the flag is not given
+    pub fn parse_value<T: FromStr>(&self, name: &str, default: T) -> Result<T, String> {
+        match self.value(name) {
+            Some(value) => value.parse().map_err(|_| format!("invalid value {:?} for --{}", value, name)),
+            None => Ok(default),
+        }
+    }
+
+    // the value of --name looked up with from_name, one of the names listed in the error
+    pub fn choice<T>(&self, name: &str, from_name: fn(&str) -> Option<T>, names: &str) -> Result<Option<T>, String> {
+        match self.value(name) {
+            Some(value) => from_name(value)
+                .map(Some)
+                .ok_or_else(|| format!("invalid value {:?} for --{}, must be one of: {}", value, name, names)),
+            None => Ok(None),
+        }
+    }
+}
+
@@ -0,0 +1,272 @@
+// The evaluation environments of the run command. corr: the vehicle crosses a corridor of four
+// obstacles whose inner two close the gap when dynamic. nbd: the vehicle follows an A* or RRT
+// path past obstacles near it, with an obstacle moving across each long segment when dynamic.
+
+use std::io;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use csv::ReaderBuilder;
+use lazy_static::lazy_static;
+
+use rtreach::obstacle::Obstacle;
+use rtreach::interval::new_interval;
+use rtreach::obstacle_safety::{allocate_obstacles, allocate_obstacles_3d, load_obstacles_from_csv, DYNAMIC_OBSTACLE_COUNT, OBSTACLES};
+use rtreach::scenario::{install_obstacles, scenario_obstacle_sim_fn, ObstacleSpec, ReachParams, ShapeSpec, SimParams};
+use rtreach::util::load_paths_from_csv;
+
+use crate::episode::SegmentHook;
+
+const OBSTACLE_SPEED: f64 = 0.5; // m/s
+const NBD_ROOF_CLEARANCE: f64 = 1.0; // m
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Env {
+    Corr,
+    Nbd,
+}
+
+impl Env {
+    pub fn from_name(name: &str) -> Option<Env> {
+        match name {
+            "corr" => Some(Env::Corr),
+            "nbd" => Some(Env::Nbd),
+            _ => None,
+        }
+    }
+}
+
+// Planner of the nbd paths
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PathPlanner {
+    Astar,
+    Rrt,
+}
+
+impl PathPlanner {
+    pub fn from_name(name: &str) -> Option<PathPlanner> {
+        match name {
+            "astar" => Some(PathPlanner::Astar),
+            "rrt" => Some(PathPlanner::Rrt),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PathPlanner::Astar => "astar",
+            PathPlanner::Rrt => "rrt",
+        }
+    }
+}
+
+// Start position and waypoints of an episode, the first waypoint is where the first segment
+// starts
+pub struct Episode<const GOAL_DIMS: usize> {
+    pub position: [f64; GOAL_DIMS],
+    pub waypoints: Vec<[f64; GOAL_DIMS]>,
+    obstacles: Vec<[f64; 2]>,   // centers of the nbd obstacles
+}
+
+pub struct Experiment<const GOAL_DIMS: usize> {
+    pub env: Env,
+    pub dynamic: bool,
+    pub episodes: Vec<Episode<GOAL_DIMS>>,
+    pub reach: ReachParams,
+    pub sim: SimParams,
+    pub sliding_window: bool,
+    pub obstacle_sim_fn: fn(t: f64, obs: &mut Vec<Obstacle>),
+    pub on_new_segment: Option<SegmentHook>,
+    // directory under eval_output_data/<vehicle> and name of the output file after the algorithm
+    pub output_dir: &'static str,
+    pub output_name: String,
+}
+
+impl<const GOAL_DIMS: usize> Experiment<GOAL_DIMS> {
+    // Put the obstacles of an episode in their initial place
+    pub fn install_episode(&self, index: usize) {
+        match self.env {
+            Env::Corr => install_obstacles(&corr_obstacles(self.dynamic)),
+            // the moving obstacle is placed by update_obstacle_pos
+            Env::Nbd => self.install_nbd_episode(index, self.dynamic as usize),
+        }
+    }
+
+    // The obstacles of an nbd episode behind `moving` obstacles that the obstacle sim fn of the
+    // run places and moves
+    pub fn install_nbd_episode(&self, index: usize, moving: usize) {
+        let mut obstacle_set = vec![[0.0, 0.0]; moving];
+        obstacle_set.extend(self.episodes[index].obstacles.iter());
+        match self.episodes[index].position.get(2) {
+            // for the quadcopter the houses end NBD_ROOF_CLEARANCE past the altitude
+            // of the path, a vertical detour can pass over them
+            Some(&altitude) => {
+                let roof = altitude + NBD_ROOF_CLEARANCE.copysign(altitude);
+                allocate_obstacles_3d(obstacle_set.len() as u32, &obstacle_set, new_interval(roof.min(0.0), roof.max(0.0)));
+            }
+            None => allocate_obstacles(obstacle_set.len() as u32, &obstacle_set),
+        }
+        *DYNAMIC_OBSTACLE_COUNT.lock().unwrap() = moving as u32;
+    }
+}
+
+// The corr episodes of the vehicle from eval_input_data/<vehicle>/corr_dataset.csv
+pub fn corr_experiment<const GOAL_DIMS: usize>(vehicle: &str, dynamic: bool) -> io::Result<Experiment<GOAL_DIMS>> {
+    let mut rdr = ReaderBuilder::new()
+        .has_headers(true)
+        .from_path(format!("eval_input_data/{}/corr_dataset.csv", vehicle))?;
+    let mut episodes = Vec::new();
+    for record in rdr.records() {
+        let record = record?;
+        let mut values = [0.0; 6];
+        for (i, v) in values.iter_mut().enumerate() {
+            *v = record.get(i).and_then(|s| s.parse().ok()).ok_or_else(|| invalid_data(&format!("invalid corr dataset record {:?}", record)))?;
+        }
+        // vehicle, start and goal x y, at altitude 0 for the quadcopter
+        let mut points = [[0.0; GOAL_DIMS]; 3];
+        for (point, xy) in points.iter_mut().zip(values.chunks(2)) {
+            point[..2].copy_from_slice(xy);
+        }
+        episodes.push(Episode { position: points[0], waypoints: vec![points[1], points[2]], obstacles: Vec::new() });
+    }
+
+    Ok(Experiment {
+        env: Env::Corr,
+        dynamic,
+        episodes,
+        reach: ReachParams { sim_time: 2.0, ..ReachParams::default() },
+        sim: SimParams { total_steps: 200, goal_threshold: 0.2, ..SimParams::default() },
+        sliding_window: false,
+        obstacle_sim_fn: scenario_obstacle_sim_fn,
+        on_new_segment: None,
+        output_dir: "corr_exp",
+        output_name: format!("{}_corr_exp.csv", obstacle_type_name(dynamic)),
+    })
+}
+
+// the inner two obstacles close the gap at OBSTACLE_SPEED when dynamic
+fn corr_obstacles(dynamic: bool) -> Vec<ObstacleSpec> {
+    let limits = [[1.75, 2.25], [-0.95, 0.95]];
+    [([2.0, 0.7], -OBSTACLE_SPEED), ([2.0, -0.7], OBSTACLE_SPEED), ([2.0, 1.4], 0.0), ([2.0, -1.4], 0.0)]
+        .into_iter()
+        .map(|(center, speed)| {
+            let moving = dynamic && speed != 0.0;
+            ObstacleSpec {
+                shape: ShapeSpec::Rect { center, size: [0.5, 0.5] },
+                velocity: if moving { [0.0, speed] } else { [0.0, 0.0] },
+                limits: if moving { Some(limits) } else { None },
+            }
+        })
+        .collect()
+}
+
+// The nbd episodes along the paths of the planner, eval_input_data/<planner>_rustreach_paths.csv
+// with the obstacles near each path
+pub fn nbd_experiment<const GOAL_DIMS: usize>(planner: PathPlanner, dynamic: bool) -> io::Result<Experiment<GOAL_DIMS>> {
+    let paths_vec = load_paths_from_csv(&PathBuf::from(format!("eval_input_data/{}_rustreach_paths.csv", planner.name())));
+    let obstacles_vec = load_obstacles_from_csv(&PathBuf::from(format!("eval_input_data/{}_rr_nbd_obstacles_near_path.csv", planner.name())));
+    if obstacles_vec.len() < paths_vec.len() {
+        return Err(invalid_data(&format!("{} paths but obstacles for {}", paths_vec.len(), obstacles_vec.len())));
+    }
+
+    let episodes = paths_vec
+        .iter()
+        .zip(obstacles_vec)
+        .map(|(pth, obstacles)| {
+            let waypoints: Vec<[f64; GOAL_DIMS]> = pth.iter().map(|p| std::array::from_fn(|i| p[i])).collect();
+            Episode { position: waypoints[0], waypoints, obstacles }
+        })
+        .collect();
+
+    Ok(Experiment {
+        env: Env::Nbd,
+        dynamic,
+        episodes,
+        reach: ReachParams::default(),
+        sim: SimParams::default(),
+        sliding_window: true,
+        obstacle_sim_fn: if dynamic { nbd_obstacle_sim_fn } else { obstacle_sim_fn_static },
+        on_new_segment: if dynamic { Some(update_obstacle_pos) } else { None },
+        output_dir: "nbd_exp",
+        output_name: format!("{}_{}_nbd_exp.csv", planner.name(), obstacle_type_name(dynamic)),
+    })
+}
+
+fn obstacle_type_name(dynamic: bool) -> &'static str {
+    if dynamic { "dynamic" } else { "static" }
+}
+
+// Define global variables using lazy_static
+lazy_static! {
+    static ref PERP_SLOPE_DX: Mutex<f64> = Mutex::new(0.0);
+    static ref PERP_SLOPE_DY: Mutex<f64> = Mutex::new(0.0);
+}
+
+fn obstacle_sim_fn_static(_: f64, _: &mut Vec<Obstacle>) {
+    // Do nothing
+}
+
+fn nbd_obstacle_sim_fn(t: f64, obstacles: &mut Vec<Obstacle>) {
+    let offset = OBSTACLE_SPEED * t;
+    let perp_slope_dx = *PERP_SLOPE_DX.lock().unwrap();
+    let perp_slope_dy = *PERP_SLOPE_DY.lock().unwrap();
+    obstacles[0].translate(-perp_slope_dx*offset, -perp_slope_dy*offset);
+}
+
+// Put the moving obstacle beside the middle of a long segment, it moves across the segment
+fn update_obstacle_pos(prev_goal_waypoint: &[f64], cur_goal_waypoint: &[f64], first: bool) {
+    let dx = cur_goal_waypoint[0] - prev_goal_waypoint[0];
+    let dy = cur_goal_waypoint[1] - prev_goal_waypoint[1];
+    let segment_length = prev_goal_waypoint.iter().zip(cur_goal_waypoint).map(|(p, c)| (c - p) * (c - p)).sum::<f64>().sqrt();
+    let min_length = if first { 2.9 } else { 4.0 };
+    if segment_length <= min_length {
+        return;
+    }
+
+    let w = 0.5;
+    let h = 0.5;
+
+    let mid_x = (prev_goal_waypoint[0] + cur_goal_waypoint[0]) / 2.0;
+    let mid_y = (prev_goal_waypoint[1] + cur_goal_waypoint[1]) / 2.0;
+
+    let new_x: f64;
+    let new_y: f64;
+    let offset = 2.0;
+    let mut perp_slope_dx = PERP_SLOPE_DX.lock().unwrap();
+    let mut perp_slope_dy = PERP_SLOPE_DY.lock().unwrap();
+    if dx == 0.0 {
+        new_x = mid_x + offset;
+        new_y = mid_y;
+        *perp_slope_dx = 1.0;
+        *perp_slope_dy = 0.0;
+    }
+    else if dy == 0.0 {
+        new_x = mid_x;
+        new_y = mid_y + offset;
+        *perp_slope_dx = 0.0;
+        *perp_slope_dy = 1.0;
+    }
+    else {
+        // General case: Compute perpendicular offset using a normal vector
+        let length = (dx * dx + dy * dy).sqrt();
+
+        // Perpendicular unit vector (-dy/length, dx/length)
+        let nx = -dy / length;
+        let ny = dx / length;
+        *perp_slope_dx = nx;
+        *perp_slope_dy = ny;
+
+        // Apply offset in the normal direction
+        new_x = mid_x + offset * nx;
+        new_y = mid_y + offset * ny;
+    }
+
+    let mut obstacles_lock = OBSTACLES.lock().unwrap();
+    if let Some(obstacles) = obstacles_lock.as_mut() {
+        obstacles[0] = Obstacle::rect_centered([new_x, new_y], w, h);
+    }
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
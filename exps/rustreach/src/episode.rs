@@ -0,0 +1,205 @@
+// The episode loop shared by the subcommands: the vehicle follows the waypoints, each step
+// selecting its control with the algorithm, until it reaches the last waypoint, collides, finds
+// no safe control or runs out of steps.
+
+use std::time::Instant;
+
+use rtreach::geometry::HyperRectangle;
+use rtreach::obstacle::Obstacle;
+use rtreach::obstacle_safety::OBSTACLES;
+use rtreach::scenario::{AlgorithmParameters, ReachParams, SimParams};
+use rtreach::controller::{select_safe_control_direct, select_safe_subgoal_circle, select_safe_subgoal_rtreach_scaled};
+
+use crate::vehicle::{SimVehicle, START_MS};
+
+// called with the previous and the current waypoint when the vehicle heads for a new one, the
+// flag is set for the first segment
+pub type SegmentHook = fn(prev: &[f64], cur: &[f64], first: bool);
+
+pub struct EpisodeSettings<'a> {
+    pub algorithm: AlgorithmParameters,
+    pub reach: &'a ReachParams,
+    pub sim: &'a SimParams,
+    // subgoals between the last waypoint and the current one instead of from the segment start
+    pub sliding_window: bool,
+    pub obstacle_sim_fn: fn(t: f64, obs: &mut Vec<Obstacle>),
+    pub on_new_segment: Option<SegmentHook>,
+    // keep the states, subgoals and reach tubes of the episode
+    pub record: bool,
+}
+
+// The control chosen for one step
+pub struct Selection<const NUM_DIMS: usize, const GOAL_DIMS: usize, const NUM_CTRLS: usize> {
+    pub safe: bool,                                     // a safe subgoal, control or fallback was found
+    pub ctrl_input: [f64; NUM_CTRLS],
+    pub subgoal: Option<[f64; GOAL_DIMS]>,              // None without subgoal control
+    pub rects: Vec<(f64, HyperRectangle<NUM_DIMS>)>,
+    pub speed_scale: Option<f64>,                       // scale of the safe subgoal
+    pub fallback: bool,                                 // ctrl_input is the fallback control
+    pub compute_time_us: Option<f64>,                   // time of the subgoal or control search
+}
+
+#[derive(Default)]
+pub struct EpisodeResult<const NUM_DIMS: usize, const GOAL_DIMS: usize> {
+    pub time: f64,
+    pub reached: bool,
+    pub collision: bool,
+    pub no_subgoal: bool,
+    pub compute_times_us: Vec<f64>,
+    pub deadline_violations: u32,
+    pub fallback_engagements: u32,
+    pub fallback_time: f64,
+    pub speed_scales: Vec<f64>,
+    // only with record
+    pub states: Vec<[f64; NUM_DIMS]>,
+    pub subgoals: Vec<[f64; GOAL_DIMS]>,
+    pub reachtubes: Vec<Vec<(f64, HyperRectangle<NUM_DIMS>)>>,
+}
+
+// Euclidean distance of the position of state to the goal
+pub fn goal_distance<const GOAL_DIMS: usize>(state: &[f64], goal: &[f64; GOAL_DIMS]) -> f64 {
+    goal.iter().zip(state.iter()).map(|(g, s)| (g - s) * (g - s)).sum::<f64>().sqrt()
+}
+
+// Control of the algorithm for heading from state to goal along the segment from start
+pub fn select_control<V, const NUM_DIMS: usize, const GOAL_DIMS: usize, const NUM_CTRLS: usize>(
+    model: &mut V,
+    state: [f64; NUM_DIMS],
+    start: [f64; GOAL_DIMS],
+    goal: [f64; GOAL_DIMS],
+    settings: &EpisodeSettings,
+) -> Selection<NUM_DIMS, GOAL_DIMS, NUM_CTRLS>
+where
+    V: SimVehicle<NUM_DIMS, GOAL_DIMS, NUM_CTRLS>,
+{
+    let algorithm = &settings.algorithm;
+    let reach = settings.reach;
+    if algorithm.use_safety_filter {
+        let start_time = Instant::now();
+        let (safe, ctrl_input, rects) = select_safe_control_direct(model, state, goal, reach.control_grid_steps, reach.sim_time, reach.step_size, reach.max_runtime_us(), START_MS, reach.store_rect, reach.fixed_step, settings.obstacle_sim_fn);
+        let duration = start_time.elapsed().as_micros() as f64;
+        Selection { safe, ctrl_input, subgoal: None, rects, speed_scale: None, fallback: false, compute_time_us: Some(duration) }
+    }
+    else if algorithm.use_subgoal_ctrl {
+        let start_time = Instant::now();
+        let (safe, subgoal, rects, speed_scale) = if algorithm.use_rtreach {
+            select_safe_subgoal_rtreach_scaled(model, state, start, goal, reach.num_subgoal_cands, &reach.scorer, &reach.speed_scales, reach.sim_time, reach.step_size, reach.max_runtime_us(), START_MS, reach.store_rect, reach.fixed_step, algorithm.use_rtreach_dynamic_control, settings.sliding_window, reach.subgoal_pattern, settings.obstacle_sim_fn)
+        }
+        else {
+            let (safe, subgoal, rects) = select_safe_subgoal_circle(model, &state, start, goal, reach.num_subgoal_cands * 10, settings.sliding_window, reach.subgoal_pattern);
+            (safe, subgoal, rects, 1.0)
+        };
+
+        // verified braking/hover fallback instead of ending the episode when no subgoal is safe,
+        // its reach set counts towards the compute time of the step
+        if !safe && algorithm.use_fallback {
+            let (fallback_safe, fallback_ctrl, _) = model.select_fallback_control(state, reach, settings.obstacle_sim_fn);
+            if fallback_safe {
+                let duration = start_time.elapsed().as_micros() as f64;
+                return Selection { safe: true, ctrl_input: fallback_ctrl, subgoal: Some(subgoal), rects, speed_scale: None, fallback: true, compute_time_us: Some(duration) };
+            }
+        }
+        let duration = start_time.elapsed().as_micros() as f64;
+        model.set_goal(subgoal);
+        let ctrl_input = model.sample_state_action(&state);
+        Selection { safe, ctrl_input, subgoal: Some(subgoal), rects, speed_scale: safe.then_some(speed_scale), fallback: false, compute_time_us: Some(duration) }
+    }
+    else {
+        let ctrl_input = model.sample_state_action(&state);
+        Selection { safe: true, ctrl_input, subgoal: None, rects: Vec::new(), speed_scale: None, fallback: false, compute_time_us: None }
+    }
+}
+
+// Run the vehicle from start_state along the segments between the waypoints, waypoints[0] is
+// where the first segment starts. The obstacles of the environment must be installed.
+pub fn run_episode<V, const NUM_DIMS: usize, const GOAL_DIMS: usize, const NUM_CTRLS: usize>(
+    model: &mut V,
+    start_state: [f64; NUM_DIMS],
+    waypoints: &[[f64; GOAL_DIMS]],
+    settings: &EpisodeSettings,
+) -> EpisodeResult<NUM_DIMS, GOAL_DIMS>
+where
+    V: SimVehicle<NUM_DIMS, GOAL_DIMS, NUM_CTRLS>,
+{
+    let sim = settings.sim;
+    let deadline_us = settings.reach.max_runtime_us() as f64;
+    let final_goal = waypoints[waypoints.len() - 1];
+    let mut goal_idx = 1.min(waypoints.len() - 1);
+    let mut prev_goal = waypoints[0];
+    let mut cur_goal = waypoints[goal_idx];
+
+    let mut result = EpisodeResult::default();
+    let mut state = start_state;
+    let mut step = 0;
+    let mut fallback_active = false;
+    if settings.record {
+        result.states.push(state);
+    }
+
+    model.set_goal(cur_goal);
+    if let Some(on_new_segment) = settings.on_new_segment {
+        on_new_segment(&prev_goal, &cur_goal, true);
+    }
+
+    while !result.no_subgoal && step < sim.total_steps && goal_distance(&state, &final_goal) > sim.goal_threshold {
+        if goal_idx < waypoints.len() - 1 && goal_distance(&state, &cur_goal) < sim.goal_threshold {
+            goal_idx += 1;
+            prev_goal = cur_goal;
+            cur_goal = waypoints[goal_idx];
+            model.set_goal(cur_goal);
+            if let Some(on_new_segment) = settings.on_new_segment {
+                on_new_segment(&prev_goal, &cur_goal, false);
+            }
+        }
+
+        let selection = select_control(model, state, prev_goal, cur_goal, settings);
+        if let Some(duration) = selection.compute_time_us {
+            result.compute_times_us.push(duration);
+            if duration > deadline_us {
+                result.deadline_violations += 1;
+            }
+        }
+        if let Some(speed_scale) = selection.speed_scale {
+            result.speed_scales.push(speed_scale);
+        }
+        if selection.fallback {
+            if !fallback_active {
+                result.fallback_engagements += 1;
+            }
+            fallback_active = true;
+            result.fallback_time += sim.step_size;
+        }
+        else {
+            fallback_active = false;
+        }
+        if settings.record {
+            if let Some(subgoal) = selection.subgoal {
+                result.subgoals.push(subgoal);
+                result.reachtubes.push(selection.rects);
+            }
+        }
+        // the episode ends after this step
+        if !selection.safe {
+            result.no_subgoal = true;
+        }
+
+        state = model.step(&state, &selection.ctrl_input, sim.step_size, sim.euler_step_size);
+        {
+            let mut obstacles_lock = OBSTACLES.lock().unwrap();
+            if let Some(obstacles) = obstacles_lock.as_mut() {
+                (settings.obstacle_sim_fn)(sim.step_size, obstacles);
+            }
+        }
+        result.time += sim.step_size;
+        if settings.record {
+            result.states.push(state);
+        }
+        if model.has_collided(&state) {
+            result.collision = true;
+            break;
+        }
+        step += 1;
+    }
+    result.reached = !result.collision && !result.no_subgoal && goal_distance(&state, &final_goal) <= sim.goal_threshold;
+    result
+}
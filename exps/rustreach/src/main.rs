@@ -0,0 +1,578 @@
+// rustreach: the experiments on the bicycle and the quadcopter behind one command line
+//
+//   run          the corr and nbd evaluations, one line of metrics per episode
+//   simulate     a closed loop run of a scenario file
+//   reach        reach sets of the policy from the initial state of a scenario file
+//   plan         a single subgoal or control selection from the initial state of a scenario file
+//   multi-agent  several bicycles on the nbd maps, each avoiding the reach tubes of the others
+//   safe-zones   grid of the positions on the Porto map the bicycle can drive on from safely
+//   stl          an STL formula checked on saved reach sets
+//   stats        the summary of a saved run
+
+mod args;
+mod datasets;
+mod episode;
+mod metrics;
+mod multi_agent;
+mod vehicle;
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use pbr::ProgressBar;
+use tract_onnx::prelude::*;
+
+use rtreach::face_lift::LiftingOptions;
+use rtreach::geometry::{println, HyperRectangle};
+use rtreach::initial_split::{InitialSetSplit, SplitDimension, SubBoxRun};
+use rtreach::interval::new_interval;
+use rtreach::obstacle::Obstacle;
+use rtreach::obstacle_safety::load_wallpoints;
+use rtreach::reach_avoid::ReachAvoidSpec;
+use rtreach::scenario::{load_scenario, scenario_obstacle_sim_fn, Algorithm, ReachParams, Scenario, VehicleKind};
+use rtreach::subgoal::{SubgoalPattern, SubgoalScore};
+use rtreach::stl::{load_reachtubes_from_csv, load_trace_from_csv, parse_formula, robustness, verdict_of};
+use rtreach::util::{save_grid_labels_to_csv, save_reachtubes_to_csv, save_rects_to_csv, save_states_to_csv};
+
+use bicycle::dynamics_bicycle::{BicycleModel, BICYCLE_NUM_CTRLS, BICYCLE_NUM_DIMS};
+use quadcopter::dynamics_quadcopter::{QuadcopterModel, QUAD_NUM_CTRLS, QUAD_NUM_DIMS};
+
+use args::{parse_args, Args};
+use datasets::{corr_experiment, nbd_experiment, Env, PathPlanner};
+use episode::{run_episode, select_control, EpisodeSettings};
+use metrics::{load_metrics_from_csv, print_summary, save_metrics_to_csv, EpisodeMetrics};
+use multi_agent::{print_multi_agent_summary, run_multi_agent, save_multi_agent_metrics_to_csv};
+use vehicle::{load_actor, SimVehicle, START_MS};
+
+const USAGE: &str = "Usage: rustreach <command> [options]
+
+Commands:
+  run --vehicle <bicycle|quadcopter> --algorithm <algorithm> --env <corr|nbd>
+      [--obstacles <static|dynamic>] [--waypoints <astar|rrt>] [--subgoal-pattern <pattern>]
+      [--scorer <furthest|weighted>] [--episodes <n>] [--save]
+  simulate [--scenario <file> | --vehicle <vehicle>] [--algorithm <algorithm>] [--save]
+  reach [--scenario <file> | --vehicle <vehicle>] [--goal-radius <m>]
+      [--split-depth <n> [--split-dimension <widest|sensitive>] [--parallel]] [--save]
+  plan [--scenario <file> | --vehicle <vehicle>] [--algorithm <algorithm>] [--save]
+  multi-agent --algorithm <rrfc|rrrlc> --agents <n> [--waypoints <astar|rrt>] [--episodes <n>] [--save]
+  safe-zones <output_csv> [--cell-size <m>] [--heading <rad>] [--speed <m/s>]
+  stl <reach_csv> --formula <formula> [--walls <wall_points_file>]
+  stats <file> [--max-time <s>]
+
+Algorithms: wo, rrfc, rrrlc, rrfc_fb, rrrlc_fb, rrsf
+Subgoal patterns: line, lateral, arcs, grid, vertical (quadcopter)
+Scorers: furthest (first safe subgoal along the path), weighted (progress against clearance)
+Without --scenario, simulate and plan read scenarios/<vehicle>_simple_ctrl.toml and reach
+reads scenarios/<vehicle>_simple_exp.toml. With --split-depth, reach also bisects the box of
+uncertain initial states n times and checks the sub-boxes, on one thread per core with
+--parallel. multi-agent drives the bicycle on the nbd maps, safe-zones labels the Porto map
+from data/porto_obstacles.txt and stl reads files of reach sets or reach tubes saved by the
+other commands. --save writes the results under data/ or eval_output_data/.";
+
+const ALGORITHM_NAMES: &str = "wo, rrfc, rrrlc, rrfc_fb, rrrlc_fb, rrsf";
+const VEHICLE_NAMES: &str = "bicycle, quadcopter";
+
+// A command that runs a vehicle, with its options
+enum Command {
+    Run { algorithm: Algorithm, env: Env, dynamic: bool, planner: PathPlanner, pattern: Option<SubgoalPattern>, scorer: Option<SubgoalScore>, episodes: Option<usize>, save: bool },
+    Simulate { scenario: Scenario, save: bool },
+    Reach { scenario: Scenario, goal_radius: f64, save: bool },
+    Plan { scenario: Scenario, save: bool },
+}
+
+fn main() -> TractResult<()> {
+    let args: Vec<String> = env::args().collect();
+    let Some(command_name) = args.get(1) else {
+        eprintln!("{}", USAGE);
+        std::process::exit(1); // Exit with a non-zero status code
+    };
+    if command_name == "help" || command_name == "--help" {
+        println!("{}", USAGE);
+        return Ok(());
+    }
+    // the commands that do not run a single vehicle
+    match command_name.as_str() {
+        "multi-agent" => return multi_agent(&args[2..]),
+        "safe-zones" => return safe_zones(&args[2..]),
+        "stl" => return stl(&args[2..]),
+        "stats" => return stats(&args[2..]),
+        _ => {}
+    }
+
+    let (command, vehicle, onnx_path, learning_enabled, footprint) = match parse_command(command_name, &args[2..]) {
+        Ok(parsed) => parsed,
+        Err(msg) => usage_error(&msg),
+    };
+
+    match vehicle {
+        VehicleKind::Bicycle => {
+            let model = load_actor::<BICYCLE_NUM_DIMS>(&onnx_path)?;
+            let mut bicycle_model = BicycleModel::default();
+            bicycle_model.set_ctrl_fn(bicycle::controller::model_sample_action);
+            if learning_enabled {
+                bicycle_model.set_model(&model);
+            }
+            if let Some(footprint) = footprint {
+                bicycle::bicycle_model::set_footprint(footprint);
+            }
+            execute::<_, BICYCLE_NUM_DIMS, 2, BICYCLE_NUM_CTRLS>(&mut bicycle_model, &command)
+        }
+        VehicleKind::Quadcopter => {
+            let model = load_actor::<QUAD_NUM_DIMS>(&onnx_path)?;
+            let mut quadcopter_model = QuadcopterModel::default();
+            quadcopter_model.set_ctrl_fn(quadcopter::controller::model_sample_action);
+            if learning_enabled {
+                quadcopter_model.set_model(&model);
+            }
+            if let Some(footprint) = footprint {
+                quadcopter::quadcopter_model::set_footprint(footprint);
+            }
+            execute::<_, QUAD_NUM_DIMS, 3, QUAD_NUM_CTRLS>(&mut quadcopter_model, &command)
+        }
+    }
+}
+
+type ParsedCommand = (Command, VehicleKind, String, bool, Option<rtreach::footprint::Footprint>);
+
+// The command with the vehicle, its actor network, whether the algorithm uses it and the
+// footprint of the scenario
+fn parse_command(name: &str, args: &[String]) -> Result<ParsedCommand, String> {
+    let (value_flags, switch_flags): (&[&str], &[&str]) = match name {
+        "run" => (&["vehicle", "algorithm", "env", "obstacles", "waypoints", "subgoal-pattern", "scorer", "episodes"], &["save"]),
+        "simulate" | "plan" => (&["scenario", "vehicle", "algorithm"], &["save"]),
+        "reach" => (&["scenario", "vehicle", "goal-radius", "split-depth", "split-dimension"], &["parallel", "save"]),
+        _ => return Err(format!("unknown command {:?}", name)),
+    };
+    let args = parse_args(args, value_flags, switch_flags)?;
+    if !args.positionals.is_empty() {
+        return Err(format!("unexpected argument {:?}", args.positionals[0]));
+    }
+    let save = args.switch("save");
+
+    if name == "run" {
+        let vehicle = required(args.choice("vehicle", VehicleKind::from_name, VEHICLE_NAMES)?, "vehicle")?;
+        let algorithm = required(args.choice("algorithm", Algorithm::from_name, ALGORITHM_NAMES)?, "algorithm")?;
+        let env = required(args.choice("env", Env::from_name, "corr, nbd")?, "env")?;
+        let dynamic = args.choice("obstacles", |o| match o { "static" => Some(false), "dynamic" => Some(true), _ => None }, "static, dynamic")?.unwrap_or(false);
+        let planner = args.choice("waypoints", PathPlanner::from_name, "astar, rrt")?.unwrap_or(PathPlanner::Astar);
+        let pattern = args.choice("subgoal-pattern", SubgoalPattern::from_name, "line, lateral, arcs, grid, vertical")?;
+        let scorer = args.choice("scorer", SubgoalScore::from_name, "furthest, weighted")?;
+        let episodes = args.value("episodes").map(|_| args.parse_value("episodes", 0)).transpose()?;
+        let onnx_path = format!("models/{}_model_actor.onnx", vehicle.name());
+        let command = Command::Run { algorithm, env, dynamic, planner, pattern, scorer, episodes, save };
+        return Ok((command, vehicle, onnx_path, algorithm.parameters().learning_enabled, None));
+    }
+
+    let default_file = if name == "reach" { "simple_exp" } else { "simple_ctrl" };
+    let scenario_path = match (args.value("scenario"), args.choice("vehicle", VehicleKind::from_name, VEHICLE_NAMES)?) {
+        (Some(path), _) => PathBuf::from(path),
+        (None, Some(vehicle)) => PathBuf::from(format!("scenarios/{}_{}.toml", vehicle.name(), default_file)),
+        (None, None) => return Err("--scenario or --vehicle is required".to_string()),
+    };
+    let mut scenario = load_scenario(&scenario_path).map_err(|e| format!("cannot load {:?}: {}", scenario_path, e))?;
+    println!("Scenario: {}", scenario_path.display());
+    if let Some(algorithm) = args.choice("algorithm", Algorithm::from_name, ALGORITHM_NAMES)? {
+        scenario.algorithm = algorithm;
+    }
+    if let Some(depth) = args.value("split-depth").map(|_| args.parse_value("split-depth", 0)).transpose()? {
+        let dimension = args.choice("split-dimension", SplitDimension::from_name, "widest, sensitive")?;
+        scenario.reach.split = Some(InitialSetSplit { dimension: dimension.unwrap_or(SplitDimension::Widest), depth, parallel: args.switch("parallel") });
+    }
+    let vehicle = scenario.vehicle;
+    let onnx_path = scenario.onnx_path();
+    // the reach sets are always of the policy
+    let learning_enabled = name == "reach" || scenario.algorithm.parameters().learning_enabled;
+    let footprint = scenario.model.footprint;
+    let command = match name {
+        "simulate" => Command::Simulate { scenario, save },
+        "plan" => Command::Plan { scenario, save },
+        _ => Command::Reach { scenario, goal_radius: args.parse_value("goal-radius", 0.5)?, save },
+    };
+    Ok((command, vehicle, onnx_path, learning_enabled, footprint))
+}
+
+fn required<T>(value: Option<T>, name: &str) -> Result<T, String> {
+    value.ok_or_else(|| format!("--{} is required", name))
+}
+
+fn usage_error(msg: &str) -> ! {
+    eprintln!("Error: {}", msg);
+    eprintln!("{}", USAGE);
+    std::process::exit(1); // Exit with a non-zero status code
+}
+
+fn execute<V, const NUM_DIMS: usize, const GOAL_DIMS: usize, const NUM_CTRLS: usize>(model: &mut V, command: &Command) -> TractResult<()>
+where
+    V: SimVehicle<NUM_DIMS, GOAL_DIMS, NUM_CTRLS>,
+{
+    match command {
+        Command::Run { algorithm, env, dynamic, planner, pattern, scorer, episodes, save } => run(model, *algorithm, *env, *dynamic, *planner, *pattern, *scorer, *episodes, *save),
+        Command::Simulate { scenario, save } => simulate(model, scenario, *save),
+        Command::Reach { scenario, goal_radius, save } => reach(model, scenario, *goal_radius, *save),
+        Command::Plan { scenario, save } => plan(model, scenario, *save),
+    }
+}
+
+// create the parent directory of the files saved under dir
+fn output_dir(dir: &str, save: bool) -> TractResult<PathBuf> {
+    let dir = env::current_dir()?.join(dir);
+    if save {
+        println!("Saving data to: {}", dir.display());
+        fs::create_dir_all(&dir)?; // Creates parent directories if they don't exist
+    }
+    Ok(dir)
+}
+
+fn position<const NUM_DIMS: usize, const GOAL_DIMS: usize>(state: &[f64; NUM_DIMS]) -> [f64; GOAL_DIMS] {
+    std::array::from_fn(|i| state[i])
+}
+
+#[allow(clippy::too_many_arguments)]
+fn run<V, const NUM_DIMS: usize, const GOAL_DIMS: usize, const NUM_CTRLS: usize>(
+    model: &mut V,
+    algorithm: Algorithm,
+    env: Env,
+    dynamic: bool,
+    planner: PathPlanner,
+    pattern: Option<SubgoalPattern>,
+    scorer: Option<SubgoalScore>,
+    episodes: Option<usize>,
+    save: bool,
+) -> TractResult<()>
+where
+    V: SimVehicle<NUM_DIMS, GOAL_DIMS, NUM_CTRLS>,
+{
+    let mut experiment = match env {
+        Env::Corr => corr_experiment::<GOAL_DIMS>(V::NAME, dynamic)?,
+        Env::Nbd => nbd_experiment::<GOAL_DIMS>(planner, dynamic)?,
+    };
+    if let Some(pattern) = pattern {
+        experiment.reach.subgoal_pattern = pattern;
+    }
+    if let Some(scorer) = scorer {
+        experiment.reach.scorer = scorer;
+    }
+    if let Some(episodes) = episodes {
+        experiment.episodes.truncate(episodes);
+    }
+    println!("Algorithm: {}", algorithm.name());
+
+    let eval_output_parent = output_dir(&format!("eval_output_data/{}/{}", V::NAME, experiment.output_dir), save)?;
+    let eval_output_path = eval_output_parent.join(format!("{}_{}", algorithm.name(), experiment.output_name));
+
+    let settings = EpisodeSettings {
+        algorithm: algorithm.parameters(),
+        reach: &experiment.reach,
+        sim: &experiment.sim,
+        sliding_window: experiment.sliding_window,
+        obstacle_sim_fn: experiment.obstacle_sim_fn,
+        on_new_segment: experiment.on_new_segment,
+        record: false,
+    };
+
+    let mut rows = Vec::new();
+    let mut pb = ProgressBar::new(experiment.episodes.len() as u64);
+    for (i, episode) in experiment.episodes.iter().enumerate() {
+        pb.inc();
+        experiment.install_episode(i);
+        let mut start_state = [0.0; NUM_DIMS];
+        start_state[..GOAL_DIMS].copy_from_slice(&episode.position);
+        let result = run_episode(model, start_state, &episode.waypoints, &settings);
+        rows.push(EpisodeMetrics::from_result(&result));
+    }
+    pb.finish_print("");
+
+    print_summary(&rows, experiment.sim.total_steps as f64 * experiment.sim.step_size);
+    if save {
+        save_metrics_to_csv(&eval_output_path, &rows)?;
+    }
+    Ok(())
+}
+
+fn simulate<V, const NUM_DIMS: usize, const GOAL_DIMS: usize, const NUM_CTRLS: usize>(model: &mut V, scenario: &Scenario, save: bool) -> TractResult<()>
+where
+    V: SimVehicle<NUM_DIMS, GOAL_DIMS, NUM_CTRLS>,
+{
+    scenario.install_environment();
+    let start_state: [f64; NUM_DIMS] = scenario.initial_state()?;
+    let goal_list: Vec<[f64; GOAL_DIMS]> = scenario.waypoints()?;
+    for (i, goal) in goal_list.iter().enumerate() {
+        println!("Goal {}: {:?}", i, goal);
+    }
+    println!("The state at time 0 s is: \n {:?} \n", start_state);
+
+    // the first segment starts at the initial position
+    let mut waypoints = vec![position(&start_state)];
+    waypoints.extend(goal_list);
+    let algorithm = scenario.algorithm.parameters();
+    let settings = EpisodeSettings {
+        algorithm,
+        reach: &scenario.reach,
+        sim: &scenario.sim,
+        sliding_window: false,
+        obstacle_sim_fn: scenario_obstacle_sim_fn,
+        on_new_segment: None,
+        record: true,
+    };
+    let result = run_episode(model, start_state, &waypoints, &settings);
+
+    if result.collision {
+        println!("Collision Detected");
+    }
+    else if result.no_subgoal {
+        println!("No safe subgoal found");
+    }
+    else if result.reached {
+        println!("Goal Reached {:?}", waypoints[waypoints.len() - 1]);
+    }
+    else {
+        println!("Max Steps Reached");
+    }
+    println!("The state after {} s is: \n {:?} \n", result.time, result.states[result.states.len() - 1]);
+
+    let dir = output_dir(&format!("data/{}/simple_ctrl", V::NAME), save)?;
+    if save {
+        save_states_to_csv(dir.join("ctrl_states.csv").to_str().unwrap(), &result.states);
+        if algorithm.use_subgoal_ctrl {
+            save_states_to_csv(dir.join("subgoals.csv").to_str().unwrap(), &result.subgoals);
+            save_reachtubes_to_csv(dir.join("reachtubes.csv").to_str().unwrap(), &result.reachtubes);
+        }
+    }
+    Ok(())
+}
+
+fn reach<V, const NUM_DIMS: usize, const GOAL_DIMS: usize, const NUM_CTRLS: usize>(model: &mut V, scenario: &Scenario, goal_radius: f64, save: bool) -> TractResult<()>
+where
+    V: SimVehicle<NUM_DIMS, GOAL_DIMS, NUM_CTRLS>,
+{
+    scenario.install_environment();
+    let start_state: [f64; NUM_DIMS] = scenario.initial_state()?;
+    let goal: [f64; GOAL_DIMS] = scenario.waypoints()?[0];
+    let sim = &scenario.sim;
+    let reach = ReachParams { store_rect: true, ..scenario.reach.clone() };
+    let dir = output_dir(&format!("data/{}/simple_exp", V::NAME), save)?;
+
+    // ground truth of the policy without safety checks
+    model.set_goal(goal);
+    let mut state = start_state;
+    let mut states_vec: Vec<[f64; NUM_DIMS]> = Vec::new();
+    for _ in 0..sim.total_steps {
+        let ctrl_input = model.sample_state_action(&state);
+        state = model.step(&state, &ctrl_input, sim.step_size, sim.euler_step_size);
+        states_vec.push(state);
+    }
+    println!("Final ground truth state: {:?}\n", state);
+
+    let ctrl_input = model.sample_state_action(&start_state);
+    let options = || LiftingOptions::default().store_rect(reach.store_rect).fixed_step(reach.fixed_step);
+    let (_, storage_rects_fc) = model.run_reachability(start_state, &ctrl_input, reach.sim_time, reach.step_size, reach.max_runtime_us(), START_MS, scenario_obstacle_sim_fn, options());
+    let (_, storage_rects_dc) = model.run_reachability(start_state, &ctrl_input, reach.sim_time, reach.step_size, reach.max_runtime_us(), START_MS, scenario_obstacle_sim_fn, options().dynamic_control(true));
+    if save {
+        save_states_to_csv(dir.join("gt_ctrl_states.csv").to_str().unwrap(), &states_vec);
+        save_rects_to_csv(dir.join("rects_fc.csv").to_str().unwrap(), &storage_rects_fc);
+        save_rects_to_csv(dir.join("rects_rlc.csv").to_str().unwrap(), &storage_rects_dc);
+    }
+    // the last rect is the hull of the states at the reach time, the one before is the final step
+    if let [.., (_, final_fc), _] = storage_rects_fc.as_slice() {
+        println!("Final Hyperrectangle for Fixed Control: ");
+        println(final_fc);
+    }
+    if let [.., (_, final_dc), _] = storage_rects_dc.as_slice() {
+        println!("Final Hyperrectangle for Dynamic RL Control: ");
+        println(final_dc);
+    }
+
+    // does the RL controller provably get within goal_radius of the goal?
+    let spec = ReachAvoidSpec::position_goal(new_interval(goal[0] - goal_radius, goal[0] + goal_radius),
+                                             new_interval(goal[1] - goal_radius, goal[1] + goal_radius),
+                                             new_interval(0.0, reach.sim_time));
+    let options = LiftingOptions::default().fixed_step(reach.fixed_step).dynamic_control(true).reach_avoid(&spec);
+    let (result, _) = model.run_reachability(start_state, &ctrl_input, reach.sim_time, reach.step_size, reach.max_runtime_us(), START_MS, scenario_obstacle_sim_fn, options);
+    println!("Reach-avoid for Dynamic RL Control: safe = {}, verdict = {:?}", result.safe, result.verdict);
+
+    // the uncertain initial states, split into sub-boxes
+    if let Some(split) = &reach.split {
+        let init = scenario.initial_box()?;
+        let run = SubBoxRun { store_rect: reach.store_rect, fixed_step: reach.fixed_step, dynamic_control: true };
+        let (safe, storage_rects_split) = model
+            .run_reachability_split(init, &ctrl_input, reach.sim_time, reach.step_size, reach.max_runtime_us(), START_MS, scenario_obstacle_sim_fn, split, &run)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        println!("Split initial set for Dynamic RL Control: safe = {}, up to {} sub-boxes", safe, 1u64 << split.depth);
+        if save {
+            save_rects_to_csv(dir.join("rects_split.csv").to_str().unwrap(), &storage_rects_split);
+        }
+    }
+    Ok(())
+}
+
+fn plan<V, const NUM_DIMS: usize, const GOAL_DIMS: usize, const NUM_CTRLS: usize>(model: &mut V, scenario: &Scenario, save: bool) -> TractResult<()>
+where
+    V: SimVehicle<NUM_DIMS, GOAL_DIMS, NUM_CTRLS>,
+{
+    scenario.install_environment();
+    let start_state: [f64; NUM_DIMS] = scenario.initial_state()?;
+    let goal: [f64; GOAL_DIMS] = scenario.waypoints()?[0];
+    let reach = ReachParams { store_rect: save || scenario.reach.store_rect, ..scenario.reach.clone() };
+    let settings = EpisodeSettings {
+        algorithm: scenario.algorithm.parameters(),
+        reach: &reach,
+        sim: &scenario.sim,
+        sliding_window: false,
+        obstacle_sim_fn: scenario_obstacle_sim_fn,
+        on_new_segment: None,
+        record: false,
+    };
+    model.set_goal(goal);
+    let selection = select_control(model, start_state, position(&start_state), goal, &settings);
+
+    println!("Safe: {}", selection.safe);
+    if let Some(subgoal) = selection.subgoal {
+        println!("Subgoal: {:?}", subgoal);
+    }
+    if let Some(speed_scale) = selection.speed_scale {
+        println!("Speed scale: {}", speed_scale);
+    }
+    if selection.fallback {
+        println!("Fallback control engaged");
+    }
+    println!("Control input: {:?}", selection.ctrl_input);
+    if let Some(duration) = selection.compute_time_us {
+        println!("Computation time: {}us", duration);
+    }
+
+    let dir = output_dir(&format!("data/{}/plan", V::NAME), save)?;
+    if save {
+        save_rects_to_csv(dir.join("rects.csv").to_str().unwrap(), &selection.rects);
+    }
+    Ok(())
+}
+
+fn multi_agent(args: &[String]) -> TractResult<()> {
+    let args = parse_args(args, &["algorithm", "agents", "waypoints", "episodes"], &["save"]).unwrap_or_else(|msg| usage_error(&msg));
+    if let Some(arg) = args.positionals.first() {
+        usage_error(&format!("unexpected argument {:?}", arg));
+    }
+    let algorithm = args.choice("algorithm", Algorithm::from_name, "rrfc, rrrlc").and_then(|a| required(a, "algorithm")).unwrap_or_else(|msg| usage_error(&msg));
+    // the tube of an agent is the reach set of its subgoal
+    let parameters = algorithm.parameters();
+    if !parameters.use_subgoal_ctrl || !parameters.use_rtreach || parameters.use_fallback {
+        usage_error(&format!("multi-agent needs an rtreach subgoal algorithm, rrfc or rrrlc, not {}", algorithm.name()));
+    }
+    let num_agents = args.value("agents").map(|_| args.parse_value("agents", 0)).transpose().and_then(|n| required(n, "agents")).unwrap_or_else(|msg| usage_error(&msg));
+    let planner = args.choice("waypoints", PathPlanner::from_name, "astar, rrt").unwrap_or_else(|msg| usage_error(&msg)).unwrap_or(PathPlanner::Astar);
+    let episodes = args.value("episodes").map(|_| args.parse_value("episodes", 0)).transpose().unwrap_or_else(|msg| usage_error(&msg));
+    let save = args.switch("save");
+
+    let mut experiment = nbd_experiment::<2>(planner, false)?;
+    if let Some(episodes) = episodes {
+        experiment.episodes.truncate(episodes);
+    }
+    println!("Algorithm: {}", algorithm.name());
+    let eval_output_parent = output_dir("eval_output_data/bicycle/multi_agent_exp", save)?;
+    let eval_output_path = eval_output_parent.join(format!("{}_{}_{}_agents_multi_agent_exp.csv", algorithm.name(), planner.name(), num_agents));
+
+    let model = load_actor::<BICYCLE_NUM_DIMS>("models/bicycle_model_actor.onnx")?;
+    let rows = run_multi_agent(&model, algorithm, &experiment, num_agents);
+    print_multi_agent_summary(&rows);
+    if save {
+        save_multi_agent_metrics_to_csv(&eval_output_path, &rows)?;
+    }
+    Ok(())
+}
+
+// Label the positions on the Porto map as safe or unsafe for driving straight on from them at
+// the given heading and speed, on a grid with cells of cell_size meters
+fn safe_zones(args: &[String]) -> TractResult<()> {
+    let args = parse_args(args, &["cell-size", "heading", "speed"], &[]).unwrap_or_else(|msg| usage_error(&msg));
+    let [file] = args.positionals.as_slice() else {
+        usage_error("safe-zones takes one output file");
+    };
+    let cell_size: f64 = args.parse_value("cell-size", 0.25).unwrap_or_else(|msg| usage_error(&msg));
+    let heading: f64 = args.parse_value("heading", 0.0).unwrap_or_else(|msg| usage_error(&msg));
+    let speed: f64 = args.parse_value("speed", 1.0).unwrap_or_else(|msg| usage_error(&msg));
+
+    let wall_path = env::current_dir()?.join("data/porto_obstacles.txt");
+    load_wallpoints(wall_path.to_str().unwrap(), true);
+
+    // bounds of the walls of the track
+    let region = HyperRectangle { dims: [new_interval(-11.25, 13.75),
+                                         new_interval(-3.8, 7.8),
+                                         new_interval(speed, speed),
+                                         new_interval(heading, heading)] };
+    let cells = [((region.dims[0].max - region.dims[0].min) / cell_size).ceil() as usize,
+                 ((region.dims[1].max - region.dims[1].min) / cell_size).ceil() as usize,
+                 1,
+                 1];
+
+    // throttle holding the speed, v' = 0 in v' = -ca * v + ca * cm * (u - ch)
+    let c_h = -37.1967;
+    let c_m: f64 = 0.0342;
+    let throttle = speed / c_m + c_h;
+
+    let bicycle_model = BicycleModel::default();
+    let labels = bicycle::bicycle_model::label_safe_zones_bicycle(&bicycle_model,
+                                                                   region,
+                                                                   cells,
+                                                                   1.0,
+                                                                   0.1,
+                                                                   10_000,
+                                                                   START_MS,
+                                                                   0.0,
+                                                                   throttle,
+                                                                   false,
+                                                                   false,
+                                                                   obstacle_sim_fn_static);
+    let safe_cells = labels.iter().filter(|(_, safe)| *safe).count();
+    println!("{}/{} cells safe", safe_cells, labels.len());
+    save_grid_labels_to_csv(file, &labels);
+    Ok(())
+}
+
+fn obstacle_sim_fn_static(_: f64, _: &mut Vec<Obstacle>) {
+    // Do nothing
+}
+
+// Check an STL formula offline on reach sets saved by save_rects_to_csv or reach tubes saved
+// by save_reachtubes_to_csv
+fn stl(args: &[String]) -> TractResult<()> {
+    let args = parse_args(args, &["formula", "walls"], &[]).unwrap_or_else(|msg| usage_error(&msg));
+    let [file] = args.positionals.as_slice() else {
+        usage_error("stl takes one file of reach sets");
+    };
+    let formula = required(args.value("formula"), "formula")
+        .and_then(|f| parse_formula(f).map_err(|e| format!("cannot parse formula: {}", e)))
+        .unwrap_or_else(|msg| usage_error(&msg));
+    if let Some(walls) = args.value("walls") {
+        load_wallpoints(walls, true);
+    }
+
+    // reach tube files have an extra leading time column
+    let path = PathBuf::from(file);
+    let header = fs::read_to_string(&path)?;
+    let traces = if header.starts_with("time,") {
+        load_reachtubes_from_csv(&path)
+    } else {
+        vec![load_trace_from_csv(&path)]
+    };
+
+    for (i, trace) in traces.iter().enumerate() {
+        let r = robustness(&formula, trace);
+        println!("tube {}: robustness [{}, {}] verdict {:?}", i, r.min, r.max, verdict_of(r));
+    }
+    Ok(())
+}
+
+fn stats(args: &[String]) -> TractResult<()> {
+    let args: Args = parse_args(args, &["max-time"], &[]).unwrap_or_else(|msg| usage_error(&msg));
+    let [file] = args.positionals.as_slice() else {
+        usage_error("stats takes one file");
+    };
+    // 20 s for corr, 100 s for nbd
+    let max_time = args.parse_value("max-time", 20.0).unwrap_or_else(|msg| usage_error(&msg));
+    let rows = load_metrics_from_csv(Path::new(file))?;
+    println!("Episodes: {}", rows.len());
+    print_summary(&rows, max_time);
+    Ok(())
+}
@@ -0,0 +1,105 @@
+// Per-episode metrics of the experiments, their summary and the CSV files they are saved to
+
+use std::io;
+use std::path::Path;
+
+use crate::episode::EpisodeResult;
+
+const HEADER: [&str; 9] = ["TTG", "Collision", "No Subgoal", "Avg Subgoal Compute Time", "Max Subgoal Compute Time", "Deadline Violations", "Fallback Engagements", "Fallback Time", "Avg Speed Scale"];
+
+// One row of the CSV files, the flags and counts are stored as numbers like the times
+#[derive(Clone, Copy, Debug)]
+pub struct EpisodeMetrics {
+    pub ttg: f64,                   // time to goal, -1 after a collision or without a safe subgoal
+    pub collision: f64,
+    pub no_subgoal: f64,
+    pub avg_compute_time: f64,      // of the subgoal or control search, in us
+    pub max_compute_time: f64,
+    pub deadline_violations: f64,
+    pub fallback_engagements: f64,
+    pub fallback_time: f64,
+    pub avg_speed_scale: f64,
+}
+
+impl EpisodeMetrics {
+    pub fn from_result<const NUM_DIMS: usize, const GOAL_DIMS: usize>(result: &EpisodeResult<NUM_DIMS, GOAL_DIMS>) -> EpisodeMetrics {
+        let times = &result.compute_times_us;
+        let scales = &result.speed_scales;
+        EpisodeMetrics {
+            ttg: if result.collision || result.no_subgoal { -1.0 } else { result.time },
+            collision: result.collision as u8 as f64,
+            no_subgoal: result.no_subgoal as u8 as f64,
+            avg_compute_time: if times.is_empty() { 0.0 } else { times.iter().sum::<f64>() / times.len() as f64 },
+            max_compute_time: times.iter().cloned().fold(0.0, f64::max),
+            deadline_violations: result.deadline_violations as f64,
+            fallback_engagements: result.fallback_engagements as f64,
+            fallback_time: result.fallback_time,
+            avg_speed_scale: if scales.is_empty() { 1.0 } else { scales.iter().sum::<f64>() / scales.len() as f64 },
+        }
+    }
+
+    fn to_record(self) -> [String; 9] {
+        [self.ttg, self.collision, self.no_subgoal, self.avg_compute_time, self.max_compute_time, self.deadline_violations, self.fallback_engagements, self.fallback_time, self.avg_speed_scale].map(|v| v.to_string())
+    }
+}
+
+// Print the summary of the episodes, the ones taking max_time or longer are timeouts
+pub fn print_summary(rows: &[EpisodeMetrics], max_time: f64) {
+    // the time of an episode adds up the steps, allow for the rounding
+    let timeout = max_time - 0.1;
+    let valid: Vec<f64> = rows.iter().map(|r| r.ttg).filter(|&t| t != -1.0 && t < timeout).collect();
+    let avg_time = valid.iter().sum::<f64>() / valid.len() as f64;
+    let timeouts = rows.iter().filter(|r| r.ttg >= timeout).count();
+    let n = rows.len() as f64;
+
+    println!("Average time to reach goal (TTG) without safety violation and timeouts: {}s", avg_time);
+    println!("Timeouts: {}", timeouts);
+    println!("Total collisions: {}", rows.iter().map(|r| r.collision).sum::<f64>());
+    println!("Total no subgoal ctrl: {}", rows.iter().map(|r| r.no_subgoal).sum::<f64>());
+    println!("Average subgoal computation time: {}us", rows.iter().map(|r| r.avg_compute_time).sum::<f64>() / n);
+    println!("Max subgoal computation time: {}us", rows.iter().map(|r| r.max_compute_time).fold(0.0, f64::max));
+    println!("Total deadline violations: {}", rows.iter().map(|r| r.deadline_violations).sum::<f64>());
+    println!("Total fallback engagements: {}", rows.iter().map(|r| r.fallback_engagements).sum::<f64>());
+    println!("Total fallback time: {}s", rows.iter().map(|r| r.fallback_time).sum::<f64>());
+    println!("Average speed scale: {}", rows.iter().map(|r| r.avg_speed_scale).sum::<f64>() / n);
+}
+
+pub fn save_metrics_to_csv(filename: &Path, rows: &[EpisodeMetrics]) -> io::Result<()> {
+    let mut wtr = csv::Writer::from_path(filename)?;
+    wtr.write_record(HEADER)?;
+    for row in rows {
+        wtr.write_record(row.to_record())?;
+    }
+    wtr.flush()
+}
+
+pub fn load_metrics_from_csv(filename: &Path) -> io::Result<Vec<EpisodeMetrics>> {
+    let mut rdr = csv::Reader::from_path(filename)?;
+    if rdr.headers()?.iter().ne(HEADER) {
+        return Err(invalid_data(&format!("{:?} is not a file of experiment metrics", filename)));
+    }
+    let mut rows = Vec::new();
+    for record in rdr.records() {
+        let record = record?;
+        let values: Vec<f64> = record
+            .iter()
+            .map(|v| v.parse::<f64>().map_err(|_| invalid_data(&format!("invalid number {:?} in {:?}", v, filename))))
+            .collect::<io::Result<_>>()?;
+        rows.push(EpisodeMetrics {
+            ttg: values[0],
+            collision: values[1],
+            no_subgoal: values[2],
+            avg_compute_time: values[3],
+            max_compute_time: values[4],
+            deadline_violations: values[5],
+            fallback_engagements: values[6],
+            fallback_time: values[7],
+            avg_speed_scale: values[8],
+        });
+    }
+    Ok(rows)
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
@@ -0,0 +1,220 @@
+// The multi-agent command: N bicycles driving the nbd maps together. Each selects its subgoals
+// with rtreach, treating the latest reach tubes of the other cars as moving obstacles. Car k
+// follows the waypoint path of the map forwards if k is even and backwards if it is odd,
+// starting k / 2 waypoints along it, so that cars meet head on. A car that collides or finds no
+// safe subgoal stops where it is, one that reaches its goal leaves the map.
+
+use std::io;
+use std::path::Path;
+
+use pbr::ProgressBar;
+
+use rtreach::geometry::{hyperrectangle_bloat, HyperRectangle};
+use rtreach::multi_agent::{agent_tubes_sim_fn, set_agent_tubes, AgentTube, AgentTubes};
+use rtreach::obstacle::Obstacle;
+use rtreach::scenario::{Algorithm, ReachParams};
+use rtreach::vehicle::Vehicle;
+
+use bicycle::bicycle_model::footprint_obstacle;
+use bicycle::dynamics_bicycle::{BicycleModel, BICYCLE_NUM_DIMS as NUM_DIMS};
+
+use crate::datasets::Experiment;
+use crate::episode::{goal_distance, select_control, EpisodeSettings};
+use crate::vehicle::{ActorPlan, SimVehicle};
+
+#[derive(Clone, Copy, PartialEq)]
+enum AgentStatus {
+    Driving,
+    Reached,
+    ObstacleCollision,
+    AgentCollision,
+    NoSubgoal,
+}
+
+struct Agent<'a> {
+    model: BicycleModel<'a>,
+    waypoints: Vec<[f64; 2]>,
+    goal_idx: usize,
+    state: [f64; NUM_DIMS],
+    tube: AgentTube,
+    status: AgentStatus,
+}
+
+// The outcome of the agents of one map
+pub struct MultiAgentMetrics {
+    pub agents: usize,
+    pub reached: usize,
+    pub avg_time: f64,            // -1 if no agent reached its goal
+    pub agent_collisions: usize,
+    pub obstacle_collisions: usize,
+    pub no_subgoal: usize,
+}
+
+// The episodes of an nbd experiment with num_agents cars each, the algorithm must select its
+// subgoals with rtreach
+pub fn run_multi_agent(model: &ActorPlan, algorithm: Algorithm, experiment: &Experiment<2>, num_agents: usize) -> Vec<MultiAgentMetrics> {
+    let sim = &experiment.sim;
+    // the reach tubes go one step past the horizon, the agents after one in the turns still
+    // have its tube over the whole horizon at the next step
+    let reach = ReachParams { sim_time: experiment.reach.sim_time + sim.step_size, store_rect: true, ..experiment.reach.clone() };
+    let settings = EpisodeSettings {
+        algorithm: algorithm.parameters(),
+        reach: &reach,
+        sim,
+        sliding_window: true,
+        obstacle_sim_fn: agent_tubes_sim_fn,
+        on_new_segment: None,
+        record: false,
+    };
+
+    let mut rows = Vec::new();
+    let mut pb = ProgressBar::new(experiment.episodes.len() as u64);
+    for (i, episode) in experiment.episodes.iter().enumerate() {
+        pb.inc();
+        let mut agents: Vec<Agent> = (0..num_agents)
+            .filter_map(|k| {
+                let mut waypoints = episode.waypoints.clone();
+                if k % 2 == 1 {
+                    waypoints.reverse();
+                }
+                // at least one waypoint to drive to
+                if k / 2 + 1 >= waypoints.len() {
+                    return None;
+                }
+                waypoints.drain(..k / 2);
+                let mut state = [0.0; NUM_DIMS];
+                state[..2].copy_from_slice(&waypoints[0]);
+                let mut bicycle_model = BicycleModel::default();
+                bicycle_model.set_ctrl_fn(bicycle::controller::model_sample_action);
+                bicycle_model.set_model(model);
+                bicycle_model.set_goal(waypoints[1]);
+                Some(Agent { model: bicycle_model, waypoints, goal_idx: 1, state, tube: parked(0.0, &state), status: AgentStatus::Driving })
+            })
+            .collect();
+
+        // one moving obstacle per other agent in front of the obstacles of the map
+        experiment.install_nbd_episode(i, agents.len().saturating_sub(1));
+
+        let mut time = 0.0;
+        let mut step = 0;
+        let mut times = vec![];
+        while step < sim.total_steps && agents.iter().any(|a| a.status == AgentStatus::Driving) {
+            // the agents take turns, each against the latest tubes of the others
+            let mut ctrl_inputs = vec![[0.0; 2]; agents.len()];
+            for k in 0..agents.len() {
+                if agents[k].status != AgentStatus::Driving {
+                    continue;
+                }
+                set_agent_tubes(AgentTubes { now: time, step: sim.step_size, horizon: experiment.reach.sim_time, tubes: other_tubes(&agents, k) });
+                let agent = &mut agents[k];
+                if agent.goal_idx + 1 < agent.waypoints.len() && goal_distance(&agent.state, &agent.waypoints[agent.goal_idx]) < sim.goal_threshold {
+                    agent.goal_idx += 1;
+                }
+                let prev_goal = agent.waypoints[agent.goal_idx - 1];
+                let cur_goal = agent.waypoints[agent.goal_idx];
+                let selection = select_control(&mut agent.model, agent.state, prev_goal, cur_goal, &settings);
+                if selection.safe {
+                    agent.tube = AgentTube::from_rects(time, &selection.rects, footprint_obstacle);
+                    ctrl_inputs[k] = selection.ctrl_input;
+                }
+                else {
+                    agent.status = AgentStatus::NoSubgoal;
+                    agent.tube = parked(time, &agent.state);
+                }
+            }
+
+            time += sim.step_size;
+            for (agent, ctrl_input) in agents.iter_mut().zip(&ctrl_inputs) {
+                if agent.status == AgentStatus::Driving {
+                    agent.state = agent.model.step(&agent.state, ctrl_input, sim.step_size, sim.euler_step_size);
+                }
+            }
+
+            // collisions with the other agents where they are now
+            let footprints: Vec<Option<Obstacle>> = agents
+                .iter()
+                .map(|a| if a.status == AgentStatus::Reached { None } else { Some(footprint_obstacle(&point_rect(&a.state))) })
+                .collect();
+            for k in 0..agents.len() {
+                if agents[k].status != AgentStatus::Driving {
+                    continue;
+                }
+                let others: Vec<AgentTube> = footprints
+                    .iter()
+                    .enumerate()
+                    .filter(|(j, _)| *j != k)
+                    .map(|(_, o)| o.clone().map_or(AgentTube::default(), |o| AgentTube::parked(time, o)))
+                    .collect();
+                set_agent_tubes(AgentTubes { now: time, step: 0.0, horizon: 0.0, tubes: others });
+                let agent = &mut agents[k];
+                if agent.model.has_collided(&agent.state) {
+                    let [x, y] = footprint_obstacle(&point_rect(&agent.state)).bounding_box();
+                    let hit_agent = footprints
+                        .iter()
+                        .enumerate()
+                        .any(|(j, o)| j != k && o.as_ref().is_some_and(|o| o.intersects_box(x, y, None)));
+                    agent.status = if hit_agent { AgentStatus::AgentCollision } else { AgentStatus::ObstacleCollision };
+                    agent.tube = parked(time, &agent.state);
+                }
+                else if goal_distance(&agent.state, agent.waypoints.last().unwrap()) < sim.goal_threshold {
+                    agent.status = AgentStatus::Reached;
+                    agent.tube = AgentTube::default();
+                    times.push(time);
+                }
+            }
+            step += 1;
+        }
+
+        let count = |status: AgentStatus| agents.iter().filter(|a| a.status == status).count();
+        rows.push(MultiAgentMetrics {
+            agents: agents.len(),
+            reached: count(AgentStatus::Reached),
+            avg_time: if times.is_empty() { -1.0 } else { times.iter().sum::<f64>() / times.len() as f64 },
+            agent_collisions: count(AgentStatus::AgentCollision),
+            obstacle_collisions: count(AgentStatus::ObstacleCollision),
+            no_subgoal: count(AgentStatus::NoSubgoal),
+        });
+    }
+    pb.finish_print("");
+    rows
+}
+
+pub fn print_multi_agent_summary(rows: &[MultiAgentMetrics]) {
+    let total = |f: fn(&MultiAgentMetrics) -> usize| rows.iter().map(f).sum::<usize>();
+    let valid_times: Vec<f64> = rows.iter().map(|r| r.avg_time).filter(|&t| t != -1.0).collect();
+    println!("Agents reaching their goal: {}/{}", total(|r| r.reached), total(|r| r.agents));
+    println!("Average time to reach goal (TTG): {}s", valid_times.iter().sum::<f64>() / valid_times.len() as f64);
+    println!("Agents in inter-agent collisions: {}", total(|r| r.agent_collisions));
+    println!("Agents in obstacle collisions: {}", total(|r| r.obstacle_collisions));
+    println!("Agents without subgoal ctrl: {}", total(|r| r.no_subgoal));
+}
+
+pub fn save_multi_agent_metrics_to_csv(path: &Path, rows: &[MultiAgentMetrics]) -> io::Result<()> {
+    let mut wtr = csv::Writer::from_path(path)?;
+    wtr.write_record(["Agents", "Reached", "Avg TTG", "Agent Collisions", "Obstacle Collisions", "No Subgoal"])?;
+    for r in rows {
+        wtr.write_record([r.agents.to_string(), r.reached.to_string(), r.avg_time.to_string(), r.agent_collisions.to_string(), r.obstacle_collisions.to_string(), r.no_subgoal.to_string()])?;
+    }
+    wtr.flush()
+}
+
+// the latest tubes of all agents but agent k
+fn other_tubes(agents: &[Agent], k: usize) -> Vec<AgentTube> {
+    agents
+        .iter()
+        .enumerate()
+        .filter(|(j, _)| *j != k)
+        .map(|(_, a)| a.tube.clone())
+        .collect()
+}
+
+// the footprint of a car standing at state from time on
+fn parked(time: f64, state: &[f64; NUM_DIMS]) -> AgentTube {
+    AgentTube::parked(time, footprint_obstacle(&point_rect(state)))
+}
+
+fn point_rect(state: &[f64; NUM_DIMS]) -> HyperRectangle<NUM_DIMS> {
+    let mut rect = HyperRectangle::default();
+    hyperrectangle_bloat(&mut rect, *state, 0.0);
+    rect
+}
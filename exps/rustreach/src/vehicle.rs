@@ -0,0 +1,74 @@
+// The vehicles behind one interface so the episode loop and the commands are written once.
+// The subgoal and control selection come with the rtreach Vehicle trait, this adds the
+// simulation step and the fallback controller of each vehicle.
+
+use tract_onnx::prelude::*;
+
+use rtreach::geometry::HyperRectangle;
+use rtreach::obstacle::Obstacle;
+use rtreach::scenario::ReachParams;
+use rtreach::vehicle::Vehicle;
+
+use bicycle::dynamics_bicycle::{BicycleModel, BICYCLE_NUM_CTRLS, BICYCLE_NUM_DIMS};
+use bicycle::simulate_bicycle::step_bicycle;
+use quadcopter::dynamics_quadcopter::{QuadcopterModel, QUAD_NUM_CTRLS, QUAD_NUM_DIMS};
+use quadcopter::simulate_quadcopter::simulate_quadcopter;
+
+pub type ActorPlan = SimplePlan<TypedFact, Box<dyn TypedOp>, Graph<TypedFact, Box<dyn TypedOp>>>;
+
+// start of the reach computations, the experiments give each one its whole wall time
+pub const START_MS: u64 = 0;
+
+type Rects<const NUM_DIMS: usize> = Vec<(f64, HyperRectangle<NUM_DIMS>)>;
+
+// A vehicle the episodes simulate
+pub trait SimVehicle<const NUM_DIMS: usize, const GOAL_DIMS: usize, const NUM_CTRLS: usize>: Vehicle<NUM_DIMS, GOAL_DIMS, NUM_CTRLS> {
+    const NAME: &'static str;
+
+    // state after step_size under ctrl_input with the angles normalized, integrated with
+    // euler_step_size by the vehicles that use a finer step
+    fn step(&self, state: &[f64; NUM_DIMS], ctrl_input: &[f64; NUM_CTRLS], step_size: f64, euler_step_size: f64) -> [f64; NUM_DIMS];
+
+    // verified braking or hover control for when no subgoal is safe
+    fn select_fallback_control(&self, state: [f64; NUM_DIMS], reach: &ReachParams, obstacle_sim_fn: fn(t: f64, obs: &mut Vec<Obstacle>)) -> (bool, [f64; NUM_CTRLS], Rects<NUM_DIMS>);
+}
+
+impl SimVehicle<BICYCLE_NUM_DIMS, 2, BICYCLE_NUM_CTRLS> for BicycleModel<'_> {
+    const NAME: &'static str = "bicycle";
+
+    fn step(&self, state: &[f64; BICYCLE_NUM_DIMS], ctrl_input: &[f64; BICYCLE_NUM_CTRLS], step_size: f64, _: f64) -> [f64; BICYCLE_NUM_DIMS] {
+        let mut next_state = step_bicycle(self, state, ctrl_input[0], ctrl_input[1], step_size);
+        next_state[3] = bicycle::utils::normalize_angle(next_state[3]);
+        next_state
+    }
+
+    fn select_fallback_control(&self, state: [f64; BICYCLE_NUM_DIMS], reach: &ReachParams, obstacle_sim_fn: fn(t: f64, obs: &mut Vec<Obstacle>)) -> (bool, [f64; BICYCLE_NUM_CTRLS], Rects<BICYCLE_NUM_DIMS>) {
+        bicycle::controller::select_fallback_control(state, reach.sim_time, reach.step_size, reach.max_runtime_us(), START_MS, reach.store_rect, reach.fixed_step, obstacle_sim_fn)
+    }
+}
+
+impl SimVehicle<QUAD_NUM_DIMS, 3, QUAD_NUM_CTRLS> for QuadcopterModel<'_> {
+    const NAME: &'static str = "quadcopter";
+
+    fn step(&self, state: &[f64; QUAD_NUM_DIMS], ctrl_input: &[f64; QUAD_NUM_CTRLS], step_size: f64, euler_step_size: f64) -> [f64; QUAD_NUM_DIMS] {
+        let mut next_state = simulate_quadcopter(self, *state, ctrl_input, euler_step_size, step_size);
+        next_state[3] = quadcopter::utils::normalize_angle(next_state[3]); // phi
+        next_state[4] = quadcopter::utils::normalize_angle(next_state[4]); // theta
+        next_state[5] = quadcopter::utils::normalize_angle(next_state[5]); // psi
+        next_state
+    }
+
+    fn select_fallback_control(&self, state: [f64; QUAD_NUM_DIMS], reach: &ReachParams, obstacle_sim_fn: fn(t: f64, obs: &mut Vec<Obstacle>)) -> (bool, [f64; QUAD_NUM_CTRLS], Rects<QUAD_NUM_DIMS>) {
+        quadcopter::controller::select_fallback_control(state, reach.sim_time, reach.step_size, reach.max_runtime_us(), START_MS, reach.store_rect, reach.fixed_step, obstacle_sim_fn)
+    }
+}
+
+// The actor network for states of NUM_DIMS dimensions
+pub fn load_actor<const NUM_DIMS: usize>(path: &str) -> TractResult<ActorPlan> {
+    tract_onnx::onnx()
+        .model_for_path(path)?
+        // specify input type and shape
+        .with_input_fact(0, f64::fact([1, NUM_DIMS]).into())?
+        .into_optimized()?        // Optimize the model for performance
+        .into_runnable()          // Make it runnable
+}
@@ -0,0 +1,26 @@
+use std::env;
+
+use rtreach::obstacle_safety::{load_wallpoints, WALL_COORDS};
+use rtreach::wall_segments::{save_wall_segments_to_csv, wall_points_to_segments};
+
+// Convert a wall point file ("x, y" per line) into a wall segment list. Points are chained
+// when closer than max_gap and the dropped points lie within tolerance of the segments.
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 3 || args.len() > 5 {
+        eprintln!("Usage: {} <points_file> <segments_file> [max_gap] [tolerance]", args[0]);
+        std::process::exit(1);
+    }
+    let max_gap: f64 = if args.len() > 3 { args[3].parse().unwrap() } else { 0.1 };
+    let tolerance: f64 = if args.len() > 4 { args[4].parse().unwrap() } else { 0.03 };
+
+    load_wallpoints(&args[1], true);
+    let points: Vec<[f64; 2]> = match &*WALL_COORDS.lock().unwrap() {
+        Some(wall_coords) => wall_coords.iter().filter(|c| c.len() >= 2).map(|c| [c[0], c[1]]).collect(),
+        None => std::process::exit(1),
+    };
+
+    let walls = wall_points_to_segments(&points, max_gap, tolerance);
+    save_wall_segments_to_csv(&args[2], &walls);
+    println!("Converted {} points into {} segments", points.len(), walls.segments.len());
+}
@@ -1,12 +1,21 @@
 use tract_onnx::prelude::*;
 
-use rtreach::geometry::HyperRectangle;
-use rtreach::interval::{new_interval, new_interval_v};
-use rtreach::obstacle_safety::check_safety_obstacles_circumscribe;
-
-use super::quadcopter_model::run_reachability_quadcopter;
-use super::dynamics_quadcopter::{QuadcopterModel, QUAD_NUM_DIMS as NUM_DIMS};
-use super::utils::{distance, normalize_angle};
+use rtreach::footprint::Footprint;
+use rtreach::geometry::{HyperRectangle, Interval};
+use rtreach::obstacle::Obstacle;
+use rtreach::face_lift::{ClearanceFn, LiftingOptions, LiftingResult};
+use rtreach::initial_split::{InitialSetSplit, SubBoxRun};
+use rtreach::vehicle::Vehicle;
+
+// the subgoal selection is the same for all vehicles
+pub use rtreach::controller::{
+    select_safe_control_direct, select_safe_subgoal_circle, select_safe_subgoal_reach_avoid,
+    select_safe_subgoal_rtreach, select_safe_subgoal_rtreach_scaled,
+};
+
+use super::quadcopter_model::{has_collided, clearance, lifting_settings, run_reachability_quadcopter, run_reachability_quadcopter_split, run_reachability_quadcopter_with, HEADING_DIM, QUADCOPTER_FOOTPRINT};
+use super::dynamics_quadcopter::{QuadcopterModel, QUAD_NUM_CTRLS as NUM_CTRLS, QUAD_NUM_DIMS as NUM_DIMS};
+use super::utils::normalize_angle;
 
 const G: f64 = 9.81;
 const M: f64 = 1.2;
@@ -26,13 +35,22 @@ const K_P_P: f64 = 1.5;
 const K_P_Q: f64 = 1.5;
 const K_P_R: f64 = 1.0;
 
-pub fn goal_conditioned_sample_action(state: &[f64; NUM_DIMS], goal: &[f64; 3]) -> [f64; 4] {
-    let vx_des = goal[0] - state[0];
-    let vy_des = goal[1] - state[1];
+// raw controls searched by select_safe_control_direct, [f_t, tor_x, tor_y, tor_z] around
+// hover, about what xy_vel_z_pos_controller commands for 1 m/s and 1 m altitude errors
+pub const CONTROL_BOUNDS: [Interval; 4] = [
+    Interval { min: -1.5, max: 1.5 },
+    Interval { min: -0.02, max: 0.02 },
+    Interval { min: -0.02, max: 0.02 },
+    Interval { min: -0.02, max: 0.02 },
+];
+
+pub fn goal_conditioned_sample_action(state: &[f64; NUM_DIMS], goal: &[f64; 3], speed_scale: f64) -> [f64; 4] {
+    let vx_des = (goal[0] - state[0]) * speed_scale;
+    let vy_des = (goal[1] - state[1]) * speed_scale;
     xy_vel_z_pos_controller(vx_des, vy_des, goal[2], true, state)
 }
 
-pub fn model_sample_action(state: &[f64; NUM_DIMS], goal: &[f64; 3], model: Option<&SimplePlan<TypedFact, Box<dyn TypedOp>, Graph<TypedFact, Box<dyn TypedOp>>>>) -> [f64; 4] {
+pub fn model_sample_action(state: &[f64; NUM_DIMS], goal: &[f64; 3], model: Option<&SimplePlan<TypedFact, Box<dyn TypedOp>, Graph<TypedFact, Box<dyn TypedOp>>>>, speed_scale: f64) -> [f64; 4] {
     match model {
         Some(actor) => {
             let input = tract_ndarray::Array::from_shape_vec((1, 12), vec![goal[0] - state[0], 
@@ -53,13 +71,13 @@ pub fn model_sample_action(state: &[f64; NUM_DIMS], goal: &[f64; 3], model: Opti
             let model_output = output.iter().collect::<Vec<_>>();
 
             if model_output.len() == 2 {
-                let v_des =  [*model_output[0] as f64 * 5.0, *model_output[1] as f64 * 5.0];
+                let v_des =  [*model_output[0] as f64 * 5.0 * speed_scale, *model_output[1] as f64 * 5.0 * speed_scale];
                 return xy_vel_z_pos_controller(v_des[0], v_des[1], goal[2], true, state)
             }
             return [0.0, 0.0, 0.0, 0.0];
         }
         None => {
-            return goal_conditioned_sample_action(state, goal);
+            return goal_conditioned_sample_action(state, goal, speed_scale);
         }
     }
 }
@@ -104,193 +122,100 @@ pub fn xy_vel_z_pos_controller(
     [f_t, tor_x, tor_y, tor_z]
 }
 
-// Function to generate subgoal candiates evenly spaced along the path
-// Inputs: start and goal points, number of subgoals to generate
-// Output: Vector of subgoal candidates
-fn generate_linear_subgoals_simple(start: &[f64; 3], goal: &[f64; 3], num_subgoals: u32) -> Vec<[f64; 3]> {
-    let mut subgoals = Vec::new();
-    let dx = (goal[0] - start[0]) / num_subgoals as f64;
-    let dy = (goal[1] - start[1]) / num_subgoals as f64;
-    let dz = (goal[2] - start[2]) / num_subgoals as f64;
-    for i in 1..(num_subgoals+1) {
-        subgoals.push([start[0] + i as f64 * dx, start[1] + i as f64 * dy, start[2] + i as f64 * dz]);
-    }
-    subgoals
+// Safety controller of the simplex architecture: stop moving in x/y and hold the altitude
+// of the goal
+pub fn hover_sample_action(state: &[f64; NUM_DIMS], goal: &[f64; 3], _: Option<&SimplePlan<TypedFact, Box<dyn TypedOp>, Graph<TypedFact, Box<dyn TypedOp>>>>, _: f64) -> [f64; 4] {
+    xy_vel_z_pos_controller(0.0, 0.0, goal[2], false, state)
 }
 
-fn generate_linear_subgoals_sliding(
-    start: &[f64; 3],
-    goal: &[f64; 3],
-    robot_position: &[f64; 3],
-    num_subgoals: u32,
-    range_behind: f64,
-    range_ahead: f64,
-) -> Vec<[f64; 3]> {
-    let mut subgoals = Vec::new();
-    
-    // Vector from start to goal
-    let line_vec = [goal[0] - start[0], goal[1] - start[1]];
-    let line_length = (line_vec[0].powi(2) + line_vec[1].powi(2)).sqrt();
-    let unit_line_vec = [line_vec[0] / line_length, line_vec[1] / line_length];
-
-    // Vector from start to robot position
-    let to_robot_vec = [robot_position[0] - start[0], robot_position[1] - start[1]];
-    let projection_length = to_robot_vec[0] * unit_line_vec[0] + to_robot_vec[1] * unit_line_vec[1];
-    
-    // Projected point on the line
-    let projected_point = [
-        start[0] + projection_length * unit_line_vec[0],
-        start[1] + projection_length * unit_line_vec[1],
-    ];
+// Fallback for when no subgoal candidate is safe. The hover controller holds the current
+// position and is only safe to use if its own reach tube over sim_time is. The controller
+// is resampled over the reach set since a fixed hover input does not stabilize the attitude.
+pub fn select_fallback_control(
+    state: [f64; NUM_DIMS],
+    sim_time: f64,
+    init_step_size: f64, 
+    max_runtime_us: u64, 
+    start_ms: u64,  
+    store_rect: bool,
+    fixed_step: bool,
+    obstacle_sim_fn: fn(t: f64, obs: &mut Vec<Obstacle>),
+) -> (bool, [f64; 4], Vec<(f64, HyperRectangle<NUM_DIMS>)>) {
+    let mut fallback_model = QuadcopterModel::default();
+    fallback_model.set_ctrl_fn(hover_sample_action);
+    fallback_model.set_goal([state[0], state[1], state[2]]);
+    let ctrl_input = fallback_model.sample_state_action(&state);
+    let (safe, storage_vec) = run_reachability_quadcopter(&fallback_model, 
+                                                            state, 
+                                                            sim_time,
+                                                            init_step_size, 
+                                                            max_runtime_us, 
+                                                            start_ms, 
+                                                            &ctrl_input, 
+                                                            store_rect, 
+                                                            fixed_step,
+                                                            true,
+                                                            obstacle_sim_fn);
+    (safe, ctrl_input, storage_vec)
+}
 
-    // Define the segment start and end around the projected point
-    let mut segment_start = [
-        projected_point[0] - range_behind * unit_line_vec[0],
-        projected_point[1] - range_behind * unit_line_vec[1],
-    ];
-    let mut segment_end = [
-        projected_point[0] + range_ahead * unit_line_vec[0],
-        projected_point[1] + range_ahead * unit_line_vec[1],
-    ];
+impl Vehicle<NUM_DIMS, 3, NUM_CTRLS> for QuadcopterModel<'_> {
+    const HEADING_DIM: usize = HEADING_DIM;
+    const ROBOT_RADIUS: f64 = 0.16;
+    const CIRCLE_RANGE_BEHIND: f64 = 1.0;
+    const SUBGOALS_FROM_START: bool = false;
+    const CONTROL_BOUNDS: [Interval; NUM_CTRLS] = CONTROL_BOUNDS;
+    const CLEARANCE_FN: ClearanceFn<NUM_DIMS> = clearance;
 
-    // Trim segment start if it is behind the `start` point
-    if (segment_start[0] - start[0]) * unit_line_vec[0] + (segment_start[1] - start[1]) * unit_line_vec[1] < 0.0 {
-        segment_start = [start[0], start[1]];
+    fn set_goal(&mut self, goal: [f64; 3]) {
+        QuadcopterModel::set_goal(self, goal);
     }
 
-    // Trim segment end if it is ahead of the `goal` point
-    if (segment_end[0] - goal[0]) * unit_line_vec[0] + (segment_end[1] - goal[1]) * unit_line_vec[1] > 0.0 {
-        segment_end = [goal[0], goal[1]];
+    fn sample_state_action(&self, state: &[f64; NUM_DIMS]) -> [f64; NUM_CTRLS] {
+        QuadcopterModel::sample_state_action(self, state)
     }
 
-    // Divide the segment into `num_subgoals` evenly spaced points
-    let dx = (segment_end[0] - segment_start[0]) / num_subgoals as f64;
-    let dy = (segment_end[1] - segment_start[1]) / num_subgoals as f64;
-
-    for i in 0..=num_subgoals {
-        let subgoal = [
-            segment_start[0] + i as f64 * dx,
-            segment_start[1] + i as f64 * dy,
-            0.0,
-        ];
-        subgoals.push(subgoal);
+    fn set_speed_scale(&mut self, scale: f64) {
+        QuadcopterModel::set_speed_scale(self, scale);
     }
-    
-    subgoals
-}
 
-pub fn select_safe_subgoal_circle(
-    state: &[f64; NUM_DIMS],
-    start: [f64; 3], 
-    goal: [f64; 3],
-    num_subgoal_cands: u32,
-    sliding_window: bool,
-)-> (bool, [f64; 3], Vec<(f64, HyperRectangle<NUM_DIMS>)>){
-    let mut subgoals = 
-    if sliding_window{
-        generate_linear_subgoals_sliding(&start, &goal, &[state[0], state[1], state[2]], num_subgoal_cands, 1.0, 5.0)
-    } else {
-        generate_linear_subgoals_simple(&start, &goal, num_subgoal_cands)
-    };
-    subgoals.reverse(); // Reverse the order to prioritize subgoals closer to the goal
-    for subgoal in subgoals.iter() {
-        let rad_des = distance(state, subgoal);
-        let subgoal_rect = HyperRectangle::<NUM_DIMS> {
-            dims: [
-                new_interval(subgoal[0] - rad_des, subgoal[0] + rad_des),
-                new_interval(subgoal[1] - rad_des, subgoal[1] + rad_des),
-                new_interval(subgoal[2] - rad_des, subgoal[2] + rad_des),
-                new_interval_v(0.0),
-                new_interval_v(0.0),
-                new_interval_v(0.0),
-                new_interval_v(0.0),
-                new_interval_v(0.0),
-                new_interval_v(0.0),
-                new_interval_v(0.0),
-                new_interval_v(0.0),
-                new_interval_v(0.0),
-            ],
-        };
-        if check_safety_obstacles_circumscribe(subgoal, 0.16, rad_des){
-            return (true, *subgoal, vec![(0.0, subgoal_rect)]);
-        }
+    fn footprint(&self) -> Footprint {
+        *QUADCOPTER_FOOTPRINT.lock().unwrap()
     }
-    (false, [0.0, 0.0, 0.0], Vec::new())
-}
 
-// Given a list of control inputs in priority order and current state,
-// Return the first control input index rtreach determined to be safe
-// If none are determined to be safe boolean is false
-fn select_safe_control(
-    system_model: &mut QuadcopterModel, 
-    start_state: [f64; NUM_DIMS], 
-    sim_time: f64,
-    init_step_size: f64, 
-    wall_time_ms: u64, 
-    start_ms: u64, 
-    subgoals: &Vec<[f64; 3]>,
-    control_inputs: &Vec<[f64; 4]>, 
-    store_rect: bool,
-    fixed_step: bool,
-    rtreach_dynamic_control: bool,
-    obstacle_sim_fn: fn(t: f64, obs: &mut Vec<Vec<Vec<f64>>>),
-) -> (bool, usize, Vec<(f64, HyperRectangle<NUM_DIMS>)>) {
-    let wall_time_per_input = wall_time_ms / control_inputs.len() as u64;
-    for (idx, control_input) in control_inputs.iter().enumerate() {
-        system_model.set_goal(subgoals[idx]);
-        let (safe, storage_vec) = run_reachability_quadcopter(&system_model, 
-                                                                                        start_state, 
-                                                                                        sim_time,
-                                                                                        init_step_size, 
-                                                                                        wall_time_per_input, 
-                                                                                        start_ms, 
-                                                                                        &control_input.to_vec(), 
-                                                                                        store_rect, 
-                                                                                        fixed_step,
-                                                                                        rtreach_dynamic_control,
-                                                                                        obstacle_sim_fn);
-        if safe {
-            return (true, idx, storage_vec);
-        }
+    fn has_collided(&self, state: &[f64; NUM_DIMS]) -> bool {
+        has_collided(state)
     }
-    return (false, 0, Vec::new());
-}
 
-// Function to select subgoal based on if its associated control input is safe
-// Output none if no safe subgoal is found
-pub fn select_safe_subgoal_rtreach(
-    system_model: &mut QuadcopterModel, 
-    state: [f64; NUM_DIMS],
-    start: [f64; 3], 
-    goal: [f64; 3],
-    num_subgoal_cands: u32,
-    sim_time: f64,
-    init_step_size: f64, 
-    wall_time_ms: u64, 
-    start_ms: u64,  
-    store_rect: bool,
-    fixed_step: bool,
-    rtreach_dynamic_control: bool,
-    sliding_window: bool,
-    obstacle_sim_fn: fn(t: f64, obs: &mut Vec<Vec<Vec<f64>>>),
-) -> (bool, [f64; 3], Vec<(f64, HyperRectangle<NUM_DIMS>)>) {
-    let mut subgoals = 
-    if sliding_window{
-        generate_linear_subgoals_sliding(&start, &goal, &[state[0], state[1], state[2]], num_subgoal_cands, 5.0, 5.0)
-    } else {
-        generate_linear_subgoals_simple(&start, &goal, num_subgoal_cands)
-    };
-    subgoals.reverse(); // Reverse the order to prioritize subgoals closer to the goal
-    let mut control_inputs = Vec::new();
-    // Generate control input for each subgoal
-    for subgoal in subgoals.iter() {
-        system_model.set_goal(*subgoal);
-        let ctrl_input = system_model.sample_state_action(&state);
-        control_inputs.push(ctrl_input);
+    fn run_reachability(
+        &self,
+        state: [f64; NUM_DIMS],
+        ctrl_input: &[f64; NUM_CTRLS],
+        sim_time: f64,
+        init_step_size: f64,
+        max_runtime_us: u64,
+        start_ms: u64,
+        obstacle_sim_fn: fn(t: f64, obs: &mut Vec<Obstacle>),
+        options: LiftingOptions<NUM_DIMS>,
+    ) -> (LiftingResult, Vec<(f64, HyperRectangle<NUM_DIMS>)>) {
+        let mut set = lifting_settings(state, sim_time, init_step_size, max_runtime_us, obstacle_sim_fn);
+        run_reachability_quadcopter_with(self, start_ms, &mut set, ctrl_input, options)
     }
-    let (safe, idx, storage_vec) = select_safe_control(system_model, state, sim_time, init_step_size, wall_time_ms, start_ms, &subgoals, &control_inputs, store_rect, fixed_step, rtreach_dynamic_control, obstacle_sim_fn);
-    if safe {
-        return (true, subgoals[idx], storage_vec);
+
+    fn run_reachability_split(
+        &self,
+        init: HyperRectangle<NUM_DIMS>,
+        ctrl_input: &[f64; NUM_CTRLS],
+        sim_time: f64,
+        init_step_size: f64,
+        max_runtime_us: u64,
+        start_ms: u64,
+        obstacle_sim_fn: fn(t: f64, obs: &mut Vec<Obstacle>),
+        split: &InitialSetSplit,
+        run: &SubBoxRun,
+    ) -> Result<(bool, Vec<(f64, HyperRectangle<NUM_DIMS>)>), String> {
+        let mut set = lifting_settings([0.0; NUM_DIMS], sim_time, init_step_size, max_runtime_us, obstacle_sim_fn);
+        set.init = init;
+        run_reachability_quadcopter_split(self, start_ms, &set, ctrl_input, split, run)
     }
-    (false, [0.0, 0.0, 0.0], Vec::new())
-}
\ No newline at end of file
+}
@@ -5,6 +5,7 @@ use rtreach::interval::*;
 use rtreach::system_model::SystemModel;
 
 pub const QUAD_NUM_DIMS: usize = 12;
+pub const QUAD_NUM_CTRLS: usize = 4;
 
 // A linear model of a quadcopter. This model is a simplified version of the quadcopter dynamics.
 // This model is based on the paper
@@ -45,22 +46,25 @@ pub const QUAD_NUM_DIMS: usize = 12;
 
 pub struct QuadcopterModel<'a>{
     pub goal: [f64; 3],
-    pub ctrl_fn: fn(&[f64; QUAD_NUM_DIMS], &[f64; 3], Option<&'a SimplePlan<TypedFact, Box<dyn TypedOp>, Graph<TypedFact, Box<dyn TypedOp>>>>) -> [f64; 4],
-    pub model: Option<&'a SimplePlan<TypedFact, Box<dyn TypedOp>, Graph<TypedFact, Box<dyn TypedOp>>>>
+    pub ctrl_fn: fn(&[f64; QUAD_NUM_DIMS], &[f64; 3], Option<&'a SimplePlan<TypedFact, Box<dyn TypedOp>, Graph<TypedFact, Box<dyn TypedOp>>>>, f64) -> [f64; 4],
+    pub model: Option<&'a SimplePlan<TypedFact, Box<dyn TypedOp>, Graph<TypedFact, Box<dyn TypedOp>>>>,
+    // multiplies the velocity command of ctrl_fn
+    pub speed_scale: f64,
 }
 
 impl Default for QuadcopterModel<'_> {
     fn default() -> Self {
         QuadcopterModel {
             goal: [0.0; 3],
-            ctrl_fn: |_, _, _| [0.0; 4],
-            model: None
+            ctrl_fn: |_, _, _, _| [0.0; 4],
+            model: None,
+            speed_scale: 1.0,
         }
     }
 }
 
 impl<'a> QuadcopterModel<'a> {
-    pub fn set_ctrl_fn(&mut self, ctrl_fn: fn(&[f64; QUAD_NUM_DIMS], &[f64; 3], Option<&'a SimplePlan<TypedFact, Box<dyn TypedOp>, Graph<TypedFact, Box<dyn TypedOp>>>>) -> [f64; 4]) {
+    pub fn set_ctrl_fn(&mut self, ctrl_fn: fn(&[f64; QUAD_NUM_DIMS], &[f64; 3], Option<&'a SimplePlan<TypedFact, Box<dyn TypedOp>, Graph<TypedFact, Box<dyn TypedOp>>>>, f64) -> [f64; 4]) {
         self.ctrl_fn = ctrl_fn;
     }
 
@@ -68,35 +72,50 @@ impl<'a> QuadcopterModel<'a> {
         self.goal = goal;
     }
 
+    pub fn set_speed_scale(&mut self, speed_scale: f64) {
+        self.speed_scale = speed_scale;
+    }
+
     pub fn set_model(&mut self, model: &'a SimplePlan<TypedFact, Box<dyn TypedOp>, Graph<TypedFact, Box<dyn TypedOp>>>) {
         self.model = Some(model);
     }
 
     pub fn sample_state_action(&self, state: &[f64; QUAD_NUM_DIMS]) -> [f64; 4] {
-        (self.ctrl_fn)(state, &self.goal, self.model)
+        (self.ctrl_fn)(state, &self.goal, self.model, self.speed_scale)
     }
 }
 
 
-impl SystemModel<QUAD_NUM_DIMS> for QuadcopterModel<'_> {
+impl SystemModel<QUAD_NUM_DIMS, QUAD_NUM_CTRLS> for QuadcopterModel<'_> {
     fn get_derivative_bounds(
         &self,
         rect: &HyperRectangle<QUAD_NUM_DIMS>,
         face_index: usize,
-        ctrl_inputs: &Vec<f64>,
+        ctrl_inputs: &[f64; QUAD_NUM_CTRLS],
     ) -> f64 {
         _get_derivative_bounds_quadcopter(rect, face_index, ctrl_inputs[0], ctrl_inputs[1], ctrl_inputs[2], ctrl_inputs[3])
     }
 
+    // No derivative of the linear model depends on its own dimension, so both face
+    // neighborhoods of every dimension give the bounds on bloated_rect
+    fn get_face_derivative_bounds(
+        &self,
+        _rect: &HyperRectangle<QUAD_NUM_DIMS>,
+        bloated_rect: &HyperRectangle<QUAD_NUM_DIMS>,
+        _neb_width: &[[f64; 2]; QUAD_NUM_DIMS],
+        ctrl_inputs: &[f64; QUAD_NUM_CTRLS],
+    ) -> [Interval; QUAD_NUM_DIMS] {
+        std::array::from_fn(|d| _get_derivative_interval_quadcopter(bloated_rect, d, ctrl_inputs[0], ctrl_inputs[1], ctrl_inputs[2], ctrl_inputs[3]))
+    }
+
     fn sample_control(
             &self,
             rect: &HyperRectangle<QUAD_NUM_DIMS>,
-        ) -> Vec<f64> {
-        (self.ctrl_fn)(&rect.mean_point().dims, &self.goal, self.model).to_vec()
+        ) -> [f64; QUAD_NUM_CTRLS] {
+        (self.ctrl_fn)(&rect.mean_point().dims, &self.goal, self.model, self.speed_scale)
     }
 }
 
-// implement the derivative using interval arithmetic
 fn _get_derivative_bounds_quadcopter(
     rect: &HyperRectangle<QUAD_NUM_DIMS>,
     face_index: usize,
@@ -105,15 +124,33 @@ fn _get_derivative_bounds_quadcopter(
     tor_y: f64,
     tor_z: f64,
 ) -> f64 {
+    let dim: usize = face_index / 2;
+    let is_min: bool = (face_index % 2) == 0;
+
+    let rv: Interval = _get_derivative_interval_quadcopter(rect, dim, f_t, tor_x, tor_y, tor_z);
+
+    if is_min {
+        rv.min
+    } else {
+        rv.max
+    }
+}
+
+// implement the derivative using interval arithmetic
+fn _get_derivative_interval_quadcopter(
+    rect: &HyperRectangle<QUAD_NUM_DIMS>,
+    dim: usize,
+    f_t: f64,
+    tor_x: f64,
+    tor_y: f64,
+    tor_z: f64,
+) -> Interval {
     let g = 9.81;
     let m = 1.2;
     let i_x = 0.0123;
     let i_y = 0.0123;
     let i_z = 0.0224;
 
-    let dim: usize = face_index / 2;
-    let is_min: bool = (face_index % 2) == 0;
-
     // Interval rv.min = rv.max = 0
     let rv: Interval;
     // let x: Interval = rect.dims[0];
@@ -196,9 +233,5 @@ fn _get_derivative_bounds_quadcopter(
         }
     }
 
-    if is_min {
-        rv.min
-    } else {
-        rv.max
-    }
+    rv
 }
\ No newline at end of file
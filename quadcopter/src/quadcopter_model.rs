@@ -1,14 +1,41 @@
-use super::dynamics_quadcopter::{QuadcopterModel, QUAD_NUM_DIMS as NUM_DIMS};
+use super::dynamics_quadcopter::{QuadcopterModel, QUAD_NUM_DIMS as NUM_DIMS, QUAD_NUM_CTRLS as NUM_CTRLS};
 use super::simulate_quadcopter::simulate_quadcopter_exp;
 use rtreach::geometry::HyperRectangle;
-use rtreach::obstacle_safety::{check_safety_obstacles, check_safety_wall, OBSTACLES, DYNAMIC_OBSTACLE_COUNT, OBSTACLE_COUNT};
-use rtreach::face_lift::{LiftingSettings, face_lifting_iterative_improvement};
+use rtreach::interval::new_interval;
+use rtreach::obstacle::Obstacle;
+use rtreach::footprint::Footprint;
+use rtreach::obstacle_safety::{check_safety_obstacles_3d, check_safety_obstacles_indexed_3d, check_safety_wall, obstacles_clearance_3d, wall_clearance, OBSTACLES, DYNAMIC_OBSTACLE_COUNT, OBSTACLE_COUNT};
+use rtreach::face_lift::{LiftingOptions, LiftingResult, LiftingSettings, face_lifting_iterative_improvement, face_lifting_with};
+use rtreach::initial_split::{InitialSetSplit, SubBoxRun, face_lifting_split};
+use rtreach::safe_zones::{Grid, label_grid};
+use rtreach::stl::{stl_monitor_check, stl_monitor_enabled, stl_monitor_record, stl_monitor_restarted};
+use lazy_static::lazy_static;
+use std::sync::Mutex;
+
 // a note from the quadcopter simulator 
 // the arm length in x direction is 0.16 meters
 // the arm length in y direction is 0.16 meters
 // the motor height is 0.05 meters
 // the total width of the quadcopter is 0.32 meters 
 
+// footprint used by the safety checks, psi is the heading. The default circle of the arm
+// length bloats by 0.16 whatever the heading, like the original checks.
+pub const HEADING_DIM: usize = 5;
+// altitude checked against cuboid obstacles, bloated by half the motor height
+pub const Z_DIM: usize = 2;
+pub const HALF_HEIGHT: f64 = 0.025;
+lazy_static! {
+    pub static ref QUADCOPTER_FOOTPRINT: Mutex<Footprint> = Mutex::new(Footprint::Circle { radius: 0.16 });
+}
+
+// obstacles and walls further away than this do not count for the clearance
+pub const CLEARANCE_RANGE: f64 = 2.0;
+
+pub fn set_footprint(footprint: Footprint) {
+    let mut footprint_lock = QUADCOPTER_FOOTPRINT.lock().unwrap();
+    *footprint_lock = footprint;
+}
+
 // function that stops simulation after two seconds
 pub fn should_stop(_: [f64; NUM_DIMS], sim_time: f64, stop_time: &mut f64) -> bool {
     let mut rv = false;
@@ -22,7 +49,7 @@ pub fn should_stop(_: [f64; NUM_DIMS], sim_time: f64, stop_time: &mut f64) -> bo
     rv
 }
 
-pub fn get_simulated_safe_time(system_model: &QuadcopterModel, start: [f64; NUM_DIMS], ctrl_input: &Vec<f64>, store_state: bool) -> (f64, Vec<[f64; NUM_DIMS]>) {
+pub fn get_simulated_safe_time(system_model: &QuadcopterModel, start: [f64; NUM_DIMS], ctrl_input: &[f64; NUM_CTRLS], store_state: bool) -> (f64, Vec<[f64; NUM_DIMS]>) {
     let step_size: f64 = 0.0002;
     let mut rv: f64 = 0.0;
     let mut storage_vec: Vec<[f64; NUM_DIMS]> = Vec::new();
@@ -32,21 +59,22 @@ pub fn get_simulated_safe_time(system_model: &QuadcopterModel, start: [f64; NUM_
 }
 
 // called on states reached during the computation
-pub fn intermediate_state(r: &mut HyperRectangle<NUM_DIMS>, time: f64, obstacle_sim_fn: fn(t: f64, obs: &mut Vec<Vec<Vec<f64>>>), store_rect: bool, storage_vec: &mut Vec<(f64, HyperRectangle<NUM_DIMS>)>) -> bool {
+pub fn intermediate_state(r: &mut HyperRectangle<NUM_DIMS>, time: f64, obstacle_sim_fn: fn(t: f64, obs: &mut Vec<Obstacle>), store_rect: bool, storage_vec: &mut Vec<(f64, HyperRectangle<NUM_DIMS>)>) -> bool {
     if store_rect {
         storage_vec.push((time, *r));
     }
+    if stl_monitor_enabled() {
+        stl_monitor_record(r, time);
+    }
     
     let mut allowed: bool;
-    let dxm = 0.16;
+    // bloat the box for the footprint of the quadcopter over the headings in the box
+    let footprint: Footprint = *QUADCOPTER_FOOTPRINT.lock().unwrap();
+    let mut b = footprint.bloat(r, Some(HEADING_DIM));
+    b.dims[Z_DIM].min -= HALF_HEIGHT;
+    b.dims[Z_DIM].max += HALF_HEIGHT;
 
-    // bloat the box for the width of the quadcopter
-    r.dims[0].min = r.dims[0].min - dxm;
-    r.dims[0].max = r.dims[0].max + dxm;
-    r.dims[1].min = r.dims[1].min - dxm;
-    r.dims[1].max = r.dims[1].max + dxm;
-
-    let obstacles: &Option<Vec<Vec<Vec<f64>>>> = &*OBSTACLES.lock().unwrap();
+    let obstacles: &Option<Vec<Obstacle>> = &*OBSTACLES.lock().unwrap();
     match obstacles {
         Some(obst) => {
             let dyn_obs_ct = *DYNAMIC_OBSTACLE_COUNT.lock().unwrap();
@@ -54,8 +82,8 @@ pub fn intermediate_state(r: &mut HyperRectangle<NUM_DIMS>, time: f64, obstacle_
             let mut dyn_obs_vec = obst[0..dyn_obs_ct as usize].to_vec();
             obstacle_sim_fn(time, &mut dyn_obs_vec);
             allowed = 
-            check_safety_obstacles(r, &dyn_obs_vec, dyn_obs_ct) &&
-            check_safety_obstacles(r, &obst[(dyn_obs_ct as usize)..], tot_obs_ct - dyn_obs_ct);
+            check_safety_obstacles_3d(&b, &dyn_obs_vec, dyn_obs_ct, Some(Z_DIM)) &&
+            check_safety_obstacles_indexed_3d(&b, obst, dyn_obs_ct, tot_obs_ct - dyn_obs_ct, Some(Z_DIM));
         },
         None => {
             allowed = true;
@@ -63,27 +91,55 @@ pub fn intermediate_state(r: &mut HyperRectangle<NUM_DIMS>, time: f64, obstacle_
     }
 
     if allowed {
-        allowed = check_safety_wall(r);
+        allowed = check_safety_wall(&b);
     }
 
-    // reset it
-    r.dims[0].min = r.dims[0].min + dxm;
-    r.dims[0].max = r.dims[0].max - dxm;
-    r.dims[1].min = r.dims[1].min + dxm;
-    r.dims[1].max = r.dims[1].max - dxm;
-
     allowed
 }
 
 // This function enumerates all of the corners of the current HyperRectangle and 
 // returns whether or not any of the points lies outside of the ellipsoid
-pub fn final_state(r: &mut HyperRectangle<NUM_DIMS>, time: f64, obstacle_sim_fn: fn(t: f64, obs: &mut Vec<Vec<Vec<f64>>>), store_rect: bool, storage_vec: &mut Vec<(f64, HyperRectangle<NUM_DIMS>)>) -> bool {
-    intermediate_state(r, time, obstacle_sim_fn, store_rect, storage_vec)
+pub fn final_state(r: &mut HyperRectangle<NUM_DIMS>, time: f64, obstacle_sim_fn: fn(t: f64, obs: &mut Vec<Obstacle>), store_rect: bool, storage_vec: &mut Vec<(f64, HyperRectangle<NUM_DIMS>)>) -> bool {
+    // the stl monitor, if set, is evaluated on the whole tube at the final time
+    intermediate_state(r, time, obstacle_sim_fn, store_rect, storage_vec) && stl_monitor_check()
 }
 
 // Clear all but the first rectangle (initial state) in the storage vector
 pub fn restarted_computation(_: bool, storage_vec: &mut Vec<(f64, HyperRectangle<NUM_DIMS>)>) {
     storage_vec.truncate(1);
+    stl_monitor_restarted();
+}
+
+// Signed distance from the footprint over the box to the nearest obstacle or wall at the
+// given time, negative if it overlaps an obstacle and 0 if it touches a wall. Cuboids are
+// measured in 3D, flying over one keeps a clearance.
+// Capped at CLEARANCE_RANGE.
+pub fn clearance(r: &HyperRectangle<NUM_DIMS>, time: f64, obstacle_sim_fn: fn(t: f64, obs: &mut Vec<Obstacle>)) -> f64 {
+    let footprint: Footprint = *QUADCOPTER_FOOTPRINT.lock().unwrap();
+    let mut b = footprint.bloat(r, Some(HEADING_DIM));
+    b.dims[Z_DIM].min -= HALF_HEIGHT;
+    b.dims[Z_DIM].max += HALF_HEIGHT;
+
+    let mut rv = wall_clearance(b.dims[0], b.dims[1], CLEARANCE_RANGE).min;
+    let obstacles: &Option<Vec<Obstacle>> = &*OBSTACLES.lock().unwrap();
+    if let Some(obst) = obstacles {
+        let dyn_obs_ct = *DYNAMIC_OBSTACLE_COUNT.lock().unwrap();
+        let tot_obs_ct = *OBSTACLE_COUNT.lock().unwrap();
+        let mut dyn_obs_vec = obst[0..dyn_obs_ct as usize].to_vec();
+        obstacle_sim_fn(time, &mut dyn_obs_vec);
+        rv = rv
+            .min(obstacles_clearance_3d(&b, &dyn_obs_vec, dyn_obs_ct, Some(Z_DIM)))
+            .min(obstacles_clearance_3d(&b, &obst[dyn_obs_ct as usize..], tot_obs_ct - dyn_obs_ct, Some(Z_DIM)));
+    }
+    rv
+}
+
+// Obstacle covered by the footprint of the quadcopter over the box, for other agents to
+// avoid, see rtreach::multi_agent
+pub fn footprint_obstacle(r: &HyperRectangle<NUM_DIMS>) -> Obstacle {
+    let footprint: Footprint = *QUADCOPTER_FOOTPRINT.lock().unwrap();
+    let b = footprint.bloat(r, Some(HEADING_DIM));
+    Obstacle::Cuboid { x: b.dims[0], y: b.dims[1], z: new_interval(b.dims[Z_DIM].min - HALF_HEIGHT, b.dims[Z_DIM].max + HALF_HEIGHT) }
 }
 
 pub fn has_collided(state: &[f64; NUM_DIMS]) -> bool {
@@ -93,18 +149,20 @@ pub fn has_collided(state: &[f64; NUM_DIMS]) -> bool {
         r.dims[d].min = state[d];
         r.dims[d].max = state[d];
     }
-    let dxm = 0.16;
-    r.dims[0].min = r.dims[0].min - dxm;
-    r.dims[0].max = r.dims[0].max + dxm;
-    r.dims[1].min = r.dims[1].min - dxm;
-    r.dims[1].max = r.dims[1].max + dxm;
+    let footprint: Footprint = *QUADCOPTER_FOOTPRINT.lock().unwrap();
+    let mut r = footprint.bloat(&r, Some(HEADING_DIM));
+    r.dims[Z_DIM].min -= HALF_HEIGHT;
+    r.dims[Z_DIM].max += HALF_HEIGHT;
 
     let mut allowed: bool;
-    let obstacles: &Option<Vec<Vec<Vec<f64>>>> = &*OBSTACLES.lock().unwrap();
+    let obstacles: &Option<Vec<Obstacle>> = &*OBSTACLES.lock().unwrap();
     match obstacles {
         Some(obst) => {
+            let dyn_obs_ct = *DYNAMIC_OBSTACLE_COUNT.lock().unwrap();
             let tot_obs_ct = *OBSTACLE_COUNT.lock().unwrap();
-            allowed = check_safety_obstacles(&r, obst, tot_obs_ct);
+            allowed = 
+            check_safety_obstacles_3d(&r, obst, dyn_obs_ct, Some(Z_DIM)) &&
+            check_safety_obstacles_indexed_3d(&r, obst, dyn_obs_ct, tot_obs_ct - dyn_obs_ct, Some(Z_DIM));
         },
         None => {
             allowed = true;
@@ -126,20 +184,93 @@ pub fn run_reachability_quadcopter(system_model: &QuadcopterModel,
     start: [f64; NUM_DIMS], 
     sim_time: f64,
     init_step_size: f64, 
-    wall_time_ms: u64, 
+    max_runtime_us: u64, 
     start_ms: u64, 
-    ctrl_input: &Vec<f64>,
+    ctrl_input: &[f64; NUM_CTRLS],
     store_rect: bool,
     fixed_step: bool,
     dynamic_control: bool,
-    obstacle_sim_fn: fn(t: f64, obs: &mut Vec<Vec<Vec<f64>>>)) -> (bool, Vec<(f64, HyperRectangle<NUM_DIMS>)>)
+    obstacle_sim_fn: fn(t: f64, obs: &mut Vec<Obstacle>)) -> (bool, Vec<(f64, HyperRectangle<NUM_DIMS>)>)
      {
+    let mut set = lifting_settings(start, sim_time, init_step_size, max_runtime_us, obstacle_sim_fn);
+    let mut storage_vec: Vec<(f64, HyperRectangle<NUM_DIMS>)> = Vec::new();
+    storage_vec.push((0.0, set.init));
+    let safe = face_lifting_iterative_improvement(system_model, 
+                                start_ms, 
+                                &mut set, 
+                                ctrl_input, 
+                                store_rect, 
+                                &mut storage_vec,
+                                fixed_step,
+                                dynamic_control);
+    (safe, storage_vec)
+}
+
+
+// Safe zones of the initial states in region on a grid of cells[d] cells along dimension d,
+// see label_grid. The reach set of each cell is computed for sim_time within max_runtime_us,
+// under the fixed control or, with dynamic_control, the policy of the model.
+pub fn label_safe_zones_quadcopter(system_model: &QuadcopterModel, 
+                                   region: HyperRectangle<NUM_DIMS>, 
+                                   cells: [usize; NUM_DIMS], 
+                                   sim_time: f64,
+                                   init_step_size: f64, 
+                                   max_runtime_us: u64, 
+                                   start_ms: u64, 
+                                   ctrl_input: &[f64; NUM_CTRLS],
+                                   fixed_step: bool,
+                                   dynamic_control: bool,
+                                   obstacle_sim_fn: fn(t: f64, obs: &mut Vec<Obstacle>)) -> Vec<(HyperRectangle<NUM_DIMS>, bool)> {
+    let set = lifting_settings([0.0; NUM_DIMS], sim_time, init_step_size, max_runtime_us, obstacle_sim_fn);
+    label_grid(system_model, 
+               start_ms, 
+               &set, 
+               &Grid { region, cells }, 
+               ctrl_input, 
+               fixed_step, 
+               dynamic_control)
+}
+
+// run_reachability_quadcopter from settings made by lifting_settings with what options adds,
+// see face_lifting_with. Returns the result and the stored rects, which start with the
+// initial state.
+pub fn run_reachability_quadcopter_with(system_model: &QuadcopterModel, 
+                                        start_ms: u64, 
+                                        set: &mut LiftingSettings<NUM_DIMS>, 
+                                        ctrl_input: &[f64; NUM_CTRLS], 
+                                        options: LiftingOptions<NUM_DIMS>) -> (LiftingResult, Vec<(f64, HyperRectangle<NUM_DIMS>)>) {
+    let mut storage_vec: Vec<(f64, HyperRectangle<NUM_DIMS>)> = Vec::new();
+    storage_vec.push((0.0, set.init));
+    let result = face_lifting_with(system_model, start_ms, set, ctrl_input, &mut storage_vec, options);
+    (result, storage_vec)
+}
+
+// face_lifting_split from set.init, for uncertain initial states. The rects of each sub-box
+// follow set.init in the stored rects. The sub-boxes cannot run in parallel while an STL
+// monitor is set, the callbacks record into its one trace.
+pub fn run_reachability_quadcopter_split(system_model: &QuadcopterModel, 
+                                         start_ms: u64, 
+                                         set: &LiftingSettings<NUM_DIMS>, 
+                                         ctrl_input: &[f64; NUM_CTRLS], 
+                                         split: &InitialSetSplit, 
+                                         run: &SubBoxRun) -> Result<(bool, Vec<(f64, HyperRectangle<NUM_DIMS>)>), String> {
+    if split.parallel && stl_monitor_enabled() {
+        return Err("the sub-boxes cannot run in parallel while an STL monitor is set".to_string());
+    }
+    let mut storage_vec: Vec<(f64, HyperRectangle<NUM_DIMS>)> = Vec::new();
+    storage_vec.push((0.0, set.init));
+    let safe = face_lifting_split(system_model, start_ms, set, ctrl_input, split, run, &mut storage_vec);
+    Ok((safe, storage_vec))
+}
+
+// settings of a reach computation from start, checked against the obstacles and walls
+pub fn lifting_settings(start: [f64; NUM_DIMS], sim_time: f64, init_step_size: f64, max_runtime_us: u64, obstacle_sim_fn: fn(t: f64, obs: &mut Vec<Obstacle>)) -> LiftingSettings<NUM_DIMS> {
     let mut set: LiftingSettings<NUM_DIMS> = LiftingSettings::<NUM_DIMS> {
         init: HyperRectangle::default(),
         reach_time: sim_time,
         initial_step_size: init_step_size,
         max_rect_width_before_error: 100.0,
-        max_runtime_milliseconds: wall_time_ms,
+        max_runtime_us,
         obstacle_sim_fn: obstacle_sim_fn,
         reached_at_intermediate_time: Some(intermediate_state),
         reached_at_final_time: Some(final_state),
@@ -149,15 +280,5 @@ pub fn run_reachability_quadcopter(system_model: &QuadcopterModel,
         set.init.dims[d].min = start[d];
         set.init.dims[d].max = start[d];
     }
-    let mut storage_vec: Vec<(f64, HyperRectangle<NUM_DIMS>)> = Vec::new();
-    storage_vec.push((0.0, set.init));
-    let safe = face_lifting_iterative_improvement(system_model, 
-                                start_ms, 
-                                &mut set, 
-                                ctrl_input, 
-                                store_rect, 
-                                &mut storage_vec,
-                                fixed_step,
-                                dynamic_control);
-    (safe, storage_vec)
+    set
 }
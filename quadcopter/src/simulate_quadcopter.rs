@@ -1,13 +1,13 @@
 use rtreach::geometry::HyperRectangle;
 use rtreach::system_model::SystemModel;
-use super::dynamics_quadcopter::{QuadcopterModel, QUAD_NUM_DIMS as NUM_DIMS};
+use super::dynamics_quadcopter::{QuadcopterModel, QUAD_NUM_DIMS as NUM_DIMS, QUAD_NUM_CTRLS as NUM_CTRLS};
 use rtreach::debug::DEBUG;
 
 // simulate dynamics using Euler's method
 pub fn simulate_quadcopter_exp(
     system_model: &QuadcopterModel, 
     start_point: [f64; NUM_DIMS], 
-    ctrl_input: &Vec<f64>,
+    ctrl_input: &[f64; NUM_CTRLS],
     step_size: f64,
     should_stop: fn([f64; NUM_DIMS], f64, &mut f64) -> bool,
     stop_time: &mut f64,
@@ -45,7 +45,7 @@ pub fn simulate_quadcopter_exp(
 pub fn simulate_quadcopter(
     system_model: &QuadcopterModel, 
     start_point: [f64; NUM_DIMS], 
-    ctrl_input: &Vec<f64>,
+    ctrl_input: &[f64; NUM_CTRLS],
     step_size: f64,
     max_time: f64) 
 -> [f64; NUM_DIMS] {
@@ -66,7 +66,7 @@ pub fn simulate_quadcopter(
 pub fn step_quadcopter(
     system_model: &QuadcopterModel,
     point: &[f64; NUM_DIMS],
-    ctrl_input: &Vec<f64>,
+    ctrl_input: &[f64; NUM_CTRLS],
     step_size: f64,
 )-> [f64; NUM_DIMS] {
     let mut rect: HyperRectangle<NUM_DIMS> = HyperRectangle::default();
@@ -0,0 +1,176 @@
+// Backward reachability. Face lifting the negated dynamics from a target set, e.g. an obstacle,
+// over-approximates the states that can reach the target within the horizon. Under a bounded
+// control this is the states that can reach it for some control in the bounds.
+
+use super::face_lift::{LiftingSettings, face_lifting_iterative_improvement};
+use super::geometry::{HyperRectangle, Interval};
+use super::obstacle::Obstacle;
+use super::system_model::SystemModel;
+
+// The dynamics of model with time reversed, x' = -f(x, u). Assumes the per-face bounds of
+// model only depend on the face through its dimension and side, then the min of -f over a
+// neighborhood is minus the max of f over it.
+pub struct Backward<'a, T> {
+    pub model: &'a T,
+}
+
+impl<const NUM_DIMS: usize, const NUM_CTRLS: usize, T: SystemModel<NUM_DIMS, NUM_CTRLS>> SystemModel<NUM_DIMS, NUM_CTRLS> for Backward<'_, T> {
+    fn get_derivative_bounds(
+        &self,
+        rect: &HyperRectangle<NUM_DIMS>,
+        face_index: usize,
+        ctrl_inputs: &[f64; NUM_CTRLS],
+    ) -> f64 {
+        -self.model.get_derivative_bounds(rect, face_index ^ 1, ctrl_inputs)
+    }
+
+    fn sample_control(
+        &self,
+        rect: &HyperRectangle<NUM_DIMS>,
+    ) -> [f64; NUM_CTRLS] {
+        self.model.sample_control(rect)
+    }
+}
+
+// The dynamics of model under any control in ctrl_bounds, the control input passed in is
+// ignored. The bounds are taken over the corners of ctrl_bounds, which is exact when the
+// derivatives are monotone in each control, as for the bicycle and the quadcopter.
+pub struct BoundedControl<'a, T, const NUM_CTRLS: usize> {
+    pub model: &'a T,
+    pub ctrl_bounds: [Interval; NUM_CTRLS],
+}
+
+impl<const NUM_DIMS: usize, const NUM_CTRLS: usize, T: SystemModel<NUM_DIMS, NUM_CTRLS>> SystemModel<NUM_DIMS, NUM_CTRLS> for BoundedControl<'_, T, NUM_CTRLS> {
+    fn get_derivative_bounds(
+        &self,
+        rect: &HyperRectangle<NUM_DIMS>,
+        face_index: usize,
+        _: &[f64; NUM_CTRLS],
+    ) -> f64 {
+        let is_min: bool = face_index.is_multiple_of(2);
+        let mut rv: f64 = if is_min { f64::MAX } else { f64::MIN };
+        for corner in 0..(1usize << NUM_CTRLS) {
+            let ctrl: [f64; NUM_CTRLS] = std::array::from_fn(|c| {
+                if (corner >> c) & 1 == 0 { self.ctrl_bounds[c].min } else { self.ctrl_bounds[c].max }
+            });
+            let der = self.model.get_derivative_bounds(rect, face_index, &ctrl);
+            rv = if is_min { rv.min(der) } else { rv.max(der) };
+        }
+        rv
+    }
+
+    fn sample_control(
+        &self,
+        _: &HyperRectangle<NUM_DIMS>,
+    ) -> [f64; NUM_CTRLS] {
+        std::array::from_fn(|c| (self.ctrl_bounds[c].min + self.ctrl_bounds[c].max) / 2.0)
+    }
+}
+
+fn store_backward_rect<const NUM_DIMS: usize>(r: &mut HyperRectangle<NUM_DIMS>, time: f64, _: fn(t: f64, obs: &mut Vec<Obstacle>), _: bool, storage_vec: &mut Vec<(f64, HyperRectangle<NUM_DIMS>)>) -> bool {
+    storage_vec.push((time, *r));
+    true
+}
+
+// Boxes covering the states from which target can be reached within horizon under the fixed
+// control ctrl_input, computed with step_size and no deadline. Each box is stored with the
+// time to go it covers the start of, the target itself at 0. A box wider than
+// max_rect_width ends the computation early, what was computed is still returned, and the
+// returned flag is false.
+pub fn backward_reach_set<const NUM_DIMS: usize, const NUM_CTRLS: usize, T: SystemModel<NUM_DIMS, NUM_CTRLS>>(
+    system_model: &T,
+    target: &HyperRectangle<NUM_DIMS>,
+    horizon: f64,
+    step_size: f64,
+    max_rect_width: f64,
+    ctrl_input: &[f64; NUM_CTRLS],
+) -> (bool, Vec<(f64, HyperRectangle<NUM_DIMS>)>) {
+    let mut settings = LiftingSettings::<NUM_DIMS> {
+        init: *target,
+        reach_time: horizon,
+        initial_step_size: step_size,
+        max_rect_width_before_error: max_rect_width,
+        max_runtime_us: 0,
+        obstacle_sim_fn: |_, _| {},
+        reached_at_intermediate_time: Some(store_backward_rect),
+        reached_at_final_time: None,
+        restarted_computation: None,
+    };
+    let mut storage_vec: Vec<(f64, HyperRectangle<NUM_DIMS>)> = vec![(0.0, *target)];
+    let complete = face_lifting_iterative_improvement(&Backward { model: system_model }, 0, &mut settings, ctrl_input, true, &mut storage_vec, true, false);
+    (complete, storage_vec)
+}
+
+// Whether state is in one of the boxes of a backward reach set, i.e. may reach its target
+pub fn in_backward_reach_set<const NUM_DIMS: usize>(backward_set: &[(f64, HyperRectangle<NUM_DIMS>)], state: &[f64; NUM_DIMS]) -> bool {
+    backward_set.iter().any(|(_, rect)| {
+        (0..NUM_DIMS).all(|d| rect.dims[d].min <= state[d] && state[d] <= rect.dims[d].max)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interval::new_interval;
+
+    // x' = u
+    struct Integrator;
+
+    impl SystemModel<1, 1> for Integrator {
+        fn get_derivative_bounds(&self, _: &HyperRectangle<1>, _: usize, ctrl_inputs: &[f64; 1]) -> f64 {
+            ctrl_inputs[0]
+        }
+
+        fn sample_control(&self, _: &HyperRectangle<1>) -> [f64; 1] {
+            [0.0]
+        }
+    }
+
+    // x' = -x
+    struct Decay;
+
+    impl SystemModel<1, 0> for Decay {
+        fn get_derivative_bounds(&self, rect: &HyperRectangle<1>, face_index: usize, _: &[f64; 0]) -> f64 {
+            if face_index.is_multiple_of(2) { -rect.dims[0].max } else { -rect.dims[0].min }
+        }
+
+        fn sample_control(&self, _: &HyperRectangle<1>) -> [f64; 0] {
+            []
+        }
+    }
+
+    #[test]
+    fn test_backward_reach_set() {
+        let target = HyperRectangle { dims: [new_interval(1.0, 2.0)] };
+
+        // moving right at 1, the states up to 1 left of the target reach it within 1
+        let (complete, backward_set) = backward_reach_set(&Integrator, &target, 1.0, 0.1, 100.0, &[1.0]);
+        assert!(complete);
+        let last = backward_set.last().unwrap().1;
+        assert!((last.dims[0].min - 0.0).abs() < 1e-3);
+        assert!((last.dims[0].max - 1.0).abs() < 1e-3);
+        assert!(in_backward_reach_set(&backward_set, &[0.2]));
+        assert!(!in_backward_reach_set(&backward_set, &[-0.2]));
+        assert!(!in_backward_reach_set(&backward_set, &[2.5]));
+
+        // any control in [-1, 1] reaches it from 1 on either side
+        let bounded = BoundedControl { model: &Integrator, ctrl_bounds: [new_interval(-1.0, 1.0)] };
+        let (_, backward_set) = backward_reach_set(&bounded, &target, 1.0, 0.1, 100.0, &[0.0]);
+        assert!(in_backward_reach_set(&backward_set, &[0.2]));
+        assert!(in_backward_reach_set(&backward_set, &[2.5]));
+        assert!(!in_backward_reach_set(&backward_set, &[3.5]));
+
+        // decaying towards 0, the states that reach [1, 2] within 1 are in [1, 2e]
+        let (_, backward_set) = backward_reach_set(&Decay, &target, 1.0, 0.01, 100.0, &[]);
+        let last = backward_set.last().unwrap().1;
+        assert!(last.dims[0].min <= 1.0f64.exp());
+        assert!(last.dims[0].max >= 2.0 * 1.0f64.exp());
+        assert!(last.dims[0].max < 2.0 * 1.0f64.exp() + 0.1);
+        assert!(in_backward_reach_set(&backward_set, &[5.0]));
+        assert!(!in_backward_reach_set(&backward_set, &[0.5]));
+
+        // a box wider than the limit stops it
+        let (complete, _) = backward_reach_set(&bounded, &target, 1.0, 0.1, 2.0, &[0.0]);
+        assert!(!complete);
+    }
+}
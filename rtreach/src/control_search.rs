@@ -0,0 +1,70 @@
+// Raw control candidates for the direct safe control search, a safety filter on the action
+// of the policy instead of on its subgoal. The candidates are checked in order and the
+// first one with a safe fixed-control reach set is used.
+
+use super::geometry::Interval;
+
+// Evenly spaced grid of steps values per control input over bounds, all combinations
+pub fn control_grid(bounds: &[Interval], steps: u32) -> Vec<Vec<f64>> {
+    let mut grid: Vec<Vec<f64>> = vec![vec![]];
+    for b in bounds {
+        let values: Vec<f64> = if steps > 1 {
+            (0..steps).map(|i| b.min + (b.max - b.min) * i as f64 / (steps - 1) as f64).collect()
+        } else {
+            vec![(b.min + b.max) / 2.0]
+        };
+        grid = grid
+            .iter()
+            .flat_map(|c| values.iter().map(move |v| {
+                let mut c = c.clone();
+                c.push(*v);
+                c
+            }))
+            .collect();
+    }
+    grid
+}
+
+// The reference action (the one the policy proposes) followed by the grid over bounds sorted
+// by distance to it, each input scaled by the width of its bounds so units do not matter
+pub fn ranked_control_grid(bounds: &[Interval], steps: u32, reference: &[f64]) -> Vec<Vec<f64>> {
+    let scaled_distance = |c: &[f64]| {
+        c.iter()
+            .zip(reference)
+            .zip(bounds)
+            .map(|((v, r), b)| {
+                let width = b.max - b.min;
+                let d = if width > 0.0 { (v - r) / width } else { v - r };
+                d * d
+            })
+            .sum::<f64>()
+    };
+
+    let mut grid = control_grid(bounds, steps);
+    grid.sort_by(|a, b| scaled_distance(a).total_cmp(&scaled_distance(b)));
+    grid.insert(0, reference.to_vec());
+    grid
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interval::new_interval;
+
+    #[test]
+    fn test_control_grid() {
+        let bounds = [new_interval(-1.0, 1.0), new_interval(0.0, 10.0)];
+        let grid = control_grid(&bounds, 3);
+        assert_eq!(grid.len(), 9);
+        assert_eq!(grid[0], vec![-1.0, 0.0]);
+        assert_eq!(grid[4], vec![0.0, 5.0]);
+        assert_eq!(grid[8], vec![1.0, 10.0]);
+
+        // the reference first, then the closest grid point relative to the bounds
+        let ranked = ranked_control_grid(&bounds, 3, &[0.9, 4.0]);
+        assert_eq!(ranked.len(), 10);
+        assert_eq!(ranked[0], vec![0.9, 4.0]);
+        assert_eq!(ranked[1], vec![1.0, 5.0]);
+        assert_eq!(ranked[9], vec![-1.0, 10.0]);
+    }
+}
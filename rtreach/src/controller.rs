@@ -0,0 +1,510 @@
+// Safe subgoal and control selection of the vehicles, written once against the Vehicle
+// trait. The candidates are checked in priority order with the reach set of the vehicle
+// under the control for each of them, or with a circle around it for the circle check.
+
+use std::time::Instant;
+
+use super::face_lift::{LiftingOptions, LiftingResult};
+use super::geometry::HyperRectangle;
+use super::interval::{new_interval, new_interval_v};
+use super::obstacle::Obstacle;
+use super::reach_avoid::{ReachAvoidSpec, Verdict};
+use super::obstacle_safety::check_safety_obstacles_circumscribe;
+use super::control_search::ranked_control_grid;
+use super::subgoal::{pattern_subgoals, sort_by_progress, SubgoalCandidate, SubgoalPattern, SubgoalScorer};
+use super::util::normalize_angle;
+use super::vehicle::Vehicle;
+
+// length of the sliding window ahead of and behind the projection of the vehicle
+const SLIDING_RANGE: f64 = 5.0;
+
+type Rects<const NUM_DIMS: usize> = Vec<(f64, HyperRectangle<NUM_DIMS>)>;
+
+// Deadline shared by candidates checked one after the other. Each gets an even share of
+// max_runtime_us, at least 1 us, cut to what is left of the whole deadline, and none is
+// started once it has passed. 0 is no deadline as for LiftingSettings.
+struct SharedDeadline {
+    start: Instant,
+    max_runtime_us: u64,
+    share_us: u64,
+}
+
+impl SharedDeadline {
+    fn new(max_runtime_us: u64, candidates: usize) -> SharedDeadline {
+        let share_us = (max_runtime_us / candidates.max(1) as u64).max(1);
+        SharedDeadline { start: Instant::now(), max_runtime_us, share_us }
+    }
+
+    // budget of the next candidate, None if the deadline has passed
+    fn next_budget_us(&self) -> Option<u64> {
+        if self.max_runtime_us == 0 {
+            return Some(0);
+        }
+        let elapsed_us = self.start.elapsed().as_micros() as u64;
+        (elapsed_us < self.max_runtime_us).then(|| self.share_us.min(self.max_runtime_us - elapsed_us))
+    }
+}
+
+// Function to select the first subgoal the vehicle can reach on a straight line, checked
+// with a circle through the vehicle around the subgoal instead of a reach set. The vehicle
+// is only passed for its type.
+pub fn select_safe_subgoal_circle<V, const NUM_DIMS: usize, const GOAL_DIMS: usize, const NUM_CTRLS: usize>(
+    _system_model: &V,
+    state: &[f64; NUM_DIMS],
+    start: [f64; GOAL_DIMS],
+    goal: [f64; GOAL_DIMS],
+    num_subgoal_cands: u32,
+    sliding_window: bool,
+    pattern: SubgoalPattern,
+) -> (bool, [f64; GOAL_DIMS], Vec<(f64, HyperRectangle<NUM_DIMS>)>)
+where
+    V: Vehicle<NUM_DIMS, GOAL_DIMS, NUM_CTRLS>,
+{
+    let subgoals = generate_subgoals::<V, NUM_DIMS, GOAL_DIMS, NUM_CTRLS>(state, &start, &goal, num_subgoal_cands, sliding_window, V::CIRCLE_RANGE_BEHIND, pattern);
+    for subgoal in subgoals.iter() {
+        let rad_des = distance(state, subgoal);
+        // box of the circle over the position dims
+        let mut subgoal_rect = HyperRectangle::<NUM_DIMS> { dims: [new_interval_v(0.0); NUM_DIMS] };
+        for (dim, s) in subgoal_rect.dims.iter_mut().zip(subgoal) {
+            *dim = new_interval(s - rad_des, s + rad_des);
+        }
+        if check_safety_obstacles_circumscribe(subgoal, V::ROBOT_RADIUS, rad_des) {
+            return (true, *subgoal, vec![(0.0, subgoal_rect)]);
+        }
+    }
+    (false, [0.0; GOAL_DIMS], Vec::new())
+}
+
+// Function to select subgoal based on if its associated control input is safe
+// Every candidate is checked and the safe one with the best score is chosen, with the
+// FurthestAlong scorer this is the first safe one in the reversed candidate list
+// Output none if no safe subgoal is found
+#[allow(clippy::too_many_arguments)]
+pub fn select_safe_subgoal_rtreach<V, const NUM_DIMS: usize, const GOAL_DIMS: usize, const NUM_CTRLS: usize>(
+    system_model: &mut V,
+    state: [f64; NUM_DIMS],
+    start: [f64; GOAL_DIMS],
+    goal: [f64; GOAL_DIMS],
+    num_subgoal_cands: u32,
+    scorer: &dyn SubgoalScorer,
+    sim_time: f64,
+    init_step_size: f64,
+    max_runtime_us: u64,
+    start_ms: u64,
+    store_rect: bool,
+    fixed_step: bool,
+    rtreach_dynamic_control: bool,
+    sliding_window: bool,
+    pattern: SubgoalPattern,
+    obstacle_sim_fn: fn(t: f64, obs: &mut Vec<Obstacle>),
+) -> (bool, [f64; GOAL_DIMS], Vec<(f64, HyperRectangle<NUM_DIMS>)>)
+where
+    V: Vehicle<NUM_DIMS, GOAL_DIMS, NUM_CTRLS>,
+{
+    let subgoals = generate_subgoals::<V, NUM_DIMS, GOAL_DIMS, NUM_CTRLS>(&state, &start, &goal, num_subgoal_cands, sliding_window, SLIDING_RANGE, pattern);
+    let control_inputs = subgoal_controls(system_model, &state, &subgoals);
+    let (safe, idx, storage_vec) =
+    if !scorer.first_safe_is_best() {
+        select_scored_control(system_model, state, sim_time, init_step_size, max_runtime_us, start_ms, &subgoals, &control_inputs, goal, scorer, store_rect, fixed_step, rtreach_dynamic_control, obstacle_sim_fn)
+    } else {
+        select_safe_control(system_model, state, sim_time, init_step_size, max_runtime_us, start_ms, &subgoals, &control_inputs, store_rect, fixed_step, rtreach_dynamic_control, obstacle_sim_fn)
+    };
+    if safe {
+        return (true, subgoals[idx], storage_vec);
+    }
+    (false, [0.0; GOAL_DIMS], Vec::new())
+}
+
+// Safety filter on the policy action instead of its subgoal: the action for the goal and then
+// the grid of grid_steps values per input over CONTROL_BOUNDS, closest to the action first,
+// are checked with a fixed-control reach set, sharing max_runtime_us. Returns the first safe
+// control, or the action of the policy with false if none is safe in time.
+#[allow(clippy::too_many_arguments)]
+pub fn select_safe_control_direct<V, const NUM_DIMS: usize, const GOAL_DIMS: usize, const NUM_CTRLS: usize>(
+    system_model: &mut V,
+    state: [f64; NUM_DIMS],
+    goal: [f64; GOAL_DIMS],
+    grid_steps: u32,
+    sim_time: f64,
+    init_step_size: f64,
+    max_runtime_us: u64,
+    start_ms: u64,
+    store_rect: bool,
+    fixed_step: bool,
+    obstacle_sim_fn: fn(t: f64, obs: &mut Vec<Obstacle>),
+) -> (bool, [f64; NUM_CTRLS], Vec<(f64, HyperRectangle<NUM_DIMS>)>)
+where
+    V: Vehicle<NUM_DIMS, GOAL_DIMS, NUM_CTRLS>,
+{
+    system_model.set_goal(goal);
+    let action = system_model.sample_state_action(&state);
+    let control_inputs = ranked_control_grid(&V::CONTROL_BOUNDS, grid_steps, &action);
+    let deadline = SharedDeadline::new(max_runtime_us, control_inputs.len());
+    for control_input in control_inputs.iter() {
+        let Some(runtime_us) = deadline.next_budget_us() else {
+            break;
+        };
+        let control_input: [f64; NUM_CTRLS] = std::array::from_fn(|i| control_input[i]);
+        let options = LiftingOptions::default().store_rect(store_rect).fixed_step(fixed_step);
+        let (result, storage_vec) = system_model.run_reachability(state, &control_input, sim_time, init_step_size, runtime_us, start_ms, obstacle_sim_fn, options);
+        if result.safe {
+            return (true, control_input, storage_vec);
+        }
+    }
+    (false, action, Vec::new())
+}
+
+// select_safe_subgoal_rtreach retried with the velocity command scaled by each of
+// speed_scales in turn (e.g. [1.0, 0.5, 0.25]) until a safe subgoal is found. The first
+// scale gets all of max_runtime_us, the later ones what it leaves. The scale of the safe
+// subgoal stays set on system_model and is returned, if none is safe it is reset to 1.0.
+#[allow(clippy::too_many_arguments)]
+pub fn select_safe_subgoal_rtreach_scaled<V, const NUM_DIMS: usize, const GOAL_DIMS: usize, const NUM_CTRLS: usize>(
+    system_model: &mut V,
+    state: [f64; NUM_DIMS],
+    start: [f64; GOAL_DIMS],
+    goal: [f64; GOAL_DIMS],
+    num_subgoal_cands: u32,
+    scorer: &dyn SubgoalScorer,
+    speed_scales: &[f64],
+    sim_time: f64,
+    init_step_size: f64,
+    max_runtime_us: u64,
+    start_ms: u64,
+    store_rect: bool,
+    fixed_step: bool,
+    rtreach_dynamic_control: bool,
+    sliding_window: bool,
+    pattern: SubgoalPattern,
+    obstacle_sim_fn: fn(t: f64, obs: &mut Vec<Obstacle>),
+) -> (bool, [f64; GOAL_DIMS], Vec<(f64, HyperRectangle<NUM_DIMS>)>, f64)
+where
+    V: Vehicle<NUM_DIMS, GOAL_DIMS, NUM_CTRLS>,
+{
+    let deadline = SharedDeadline::new(max_runtime_us, 1);
+    for scale in speed_scales.iter() {
+        let Some(runtime_us) = deadline.next_budget_us() else {
+            break;
+        };
+        system_model.set_speed_scale(*scale);
+        let (safe, subgoal, storage_vec) = select_safe_subgoal_rtreach(system_model, state, start, goal, num_subgoal_cands, scorer, sim_time, init_step_size, runtime_us, start_ms, store_rect, fixed_step, rtreach_dynamic_control, sliding_window, pattern, obstacle_sim_fn);
+        if safe {
+            return (true, subgoal, storage_vec, *scale);
+        }
+    }
+    system_model.set_speed_scale(1.0);
+    (false, [0.0; GOAL_DIMS], Vec::new(), 1.0)
+}
+
+// Like select_safe_subgoal_rtreach, but each candidate must also reach the box of half
+// width goal_radius around the subgoal position within sim_time. The first candidate
+// that provably does (Sat) is chosen, otherwise the first safe one as before.
+// Also returns the reach-avoid verdict of the chosen candidate.
+#[allow(clippy::too_many_arguments)]
+pub fn select_safe_subgoal_reach_avoid<V, const NUM_DIMS: usize, const GOAL_DIMS: usize, const NUM_CTRLS: usize>(
+    system_model: &mut V,
+    state: [f64; NUM_DIMS],
+    start: [f64; GOAL_DIMS],
+    goal: [f64; GOAL_DIMS],
+    num_subgoal_cands: u32,
+    goal_radius: f64,
+    sim_time: f64,
+    init_step_size: f64,
+    max_runtime_us: u64,
+    start_ms: u64,
+    store_rect: bool,
+    fixed_step: bool,
+    rtreach_dynamic_control: bool,
+    sliding_window: bool,
+    pattern: SubgoalPattern,
+    obstacle_sim_fn: fn(t: f64, obs: &mut Vec<Obstacle>),
+) -> (bool, [f64; GOAL_DIMS], Vec<(f64, HyperRectangle<NUM_DIMS>)>, Verdict)
+where
+    V: Vehicle<NUM_DIMS, GOAL_DIMS, NUM_CTRLS>,
+{
+    let subgoals = generate_subgoals::<V, NUM_DIMS, GOAL_DIMS, NUM_CTRLS>(&state, &start, &goal, num_subgoal_cands, sliding_window, SLIDING_RANGE, pattern);
+    let control_inputs = subgoal_controls(system_model, &state, &subgoals);
+    let (safe, idx, storage_vec, verdict) = select_reach_avoid_control(system_model, state, sim_time, init_step_size, max_runtime_us, start_ms, &subgoals, &control_inputs, goal_radius, store_rect, fixed_step, rtreach_dynamic_control, obstacle_sim_fn);
+    if safe {
+        return (true, subgoals[idx], storage_vec, verdict);
+    }
+    (false, [0.0; GOAL_DIMS], Vec::new(), verdict)
+}
+
+// Control input of the controller for each subgoal
+fn subgoal_controls<V, const NUM_DIMS: usize, const GOAL_DIMS: usize, const NUM_CTRLS: usize>(
+    system_model: &mut V,
+    state: &[f64; NUM_DIMS],
+    subgoals: &[[f64; GOAL_DIMS]],
+) -> Vec<[f64; NUM_CTRLS]>
+where
+    V: Vehicle<NUM_DIMS, GOAL_DIMS, NUM_CTRLS>,
+{
+    subgoals
+        .iter()
+        .map(|subgoal| {
+            system_model.set_goal(*subgoal);
+            system_model.sample_state_action(state)
+        })
+        .collect()
+}
+
+// Given a list of control inputs in priority order and current state,
+// Return the first control input index rtreach determined to be safe
+// If none are determined to be safe boolean is false
+#[allow(clippy::too_many_arguments)]
+fn select_safe_control<V, const NUM_DIMS: usize, const GOAL_DIMS: usize, const NUM_CTRLS: usize>(
+    system_model: &mut V,
+    start_state: [f64; NUM_DIMS],
+    sim_time: f64,
+    init_step_size: f64,
+    max_runtime_us: u64,
+    start_ms: u64,
+    subgoals: &[[f64; GOAL_DIMS]],
+    control_inputs: &[[f64; NUM_CTRLS]],
+    store_rect: bool,
+    fixed_step: bool,
+    rtreach_dynamic_control: bool,
+    obstacle_sim_fn: fn(t: f64, obs: &mut Vec<Obstacle>),
+) -> (bool, usize, Vec<(f64, HyperRectangle<NUM_DIMS>)>)
+where
+    V: Vehicle<NUM_DIMS, GOAL_DIMS, NUM_CTRLS>,
+{
+    let deadline = SharedDeadline::new(max_runtime_us, control_inputs.len());
+    for (idx, control_input) in control_inputs.iter().enumerate() {
+        let Some(runtime_us) = deadline.next_budget_us() else {
+            break;
+        };
+        system_model.set_goal(subgoals[idx]);
+        let options = LiftingOptions::default().store_rect(store_rect).fixed_step(fixed_step).dynamic_control(rtreach_dynamic_control);
+        let (result, storage_vec) = system_model.run_reachability(start_state, control_input, sim_time, init_step_size, runtime_us, start_ms, obstacle_sim_fn, options);
+        if result.safe {
+            return (true, idx, storage_vec);
+        }
+    }
+    (false, 0, Vec::new())
+}
+
+// Given a list of control inputs in priority order and current state,
+// Return the index of the safe control input with the best score
+// If none are determined to be safe boolean is false
+#[allow(clippy::too_many_arguments)]
+fn select_scored_control<V, const NUM_DIMS: usize, const GOAL_DIMS: usize, const NUM_CTRLS: usize>(
+    system_model: &mut V,
+    start_state: [f64; NUM_DIMS],
+    sim_time: f64,
+    init_step_size: f64,
+    max_runtime_us: u64,
+    start_ms: u64,
+    subgoals: &[[f64; GOAL_DIMS]],
+    control_inputs: &[[f64; NUM_CTRLS]],
+    goal: [f64; GOAL_DIMS],
+    scorer: &dyn SubgoalScorer,
+    store_rect: bool,
+    fixed_step: bool,
+    rtreach_dynamic_control: bool,
+    obstacle_sim_fn: fn(t: f64, obs: &mut Vec<Obstacle>),
+) -> (bool, usize, Vec<(f64, HyperRectangle<NUM_DIMS>)>)
+where
+    V: Vehicle<NUM_DIMS, GOAL_DIMS, NUM_CTRLS>,
+{
+    let deadline = SharedDeadline::new(max_runtime_us, control_inputs.len());
+    let mut best: Option<(f64, usize, Rects<NUM_DIMS>)> = None;
+    for (idx, control_input) in control_inputs.iter().enumerate() {
+        let Some(runtime_us) = deadline.next_budget_us() else {
+            break;
+        };
+        system_model.set_goal(subgoals[idx]);
+        let options = LiftingOptions::default().store_rect(store_rect).fixed_step(fixed_step).dynamic_control(rtreach_dynamic_control).clearance(V::CLEARANCE_FN);
+        let (LiftingResult { safe, clearance, .. }, storage_vec) = system_model.run_reachability(start_state, control_input, sim_time, init_step_size, runtime_us, start_ms, obstacle_sim_fn, options);
+        if !safe {
+            continue;
+        }
+        let direction = (subgoals[idx][1] - start_state[1]).atan2(subgoals[idx][0] - start_state[0]);
+        let score = scorer.score(&SubgoalCandidate {
+            rank: idx,
+            subgoal: &subgoals[idx],
+            waypoint: &goal,
+            control: control_input,
+            clearance,
+            heading_change: normalize_angle(direction - start_state[V::HEADING_DIM]).abs(),
+        });
+        if best.as_ref().is_none_or(|(best_score, _, _)| score > *best_score) {
+            best = Some((score, idx, storage_vec));
+        }
+    }
+    match best {
+        Some((_, idx, storage_vec)) => (true, idx, storage_vec),
+        None => (false, 0, Vec::new()),
+    }
+}
+
+// Given a list of control inputs in priority order and current state,
+// Return the first control input index whose subgoal is provably reached safely,
+// or the first safe one if there is none, with its reach-avoid verdict
+// If none are determined to be safe boolean is false
+#[allow(clippy::too_many_arguments)]
+fn select_reach_avoid_control<V, const NUM_DIMS: usize, const GOAL_DIMS: usize, const NUM_CTRLS: usize>(
+    system_model: &mut V,
+    start_state: [f64; NUM_DIMS],
+    sim_time: f64,
+    init_step_size: f64,
+    max_runtime_us: u64,
+    start_ms: u64,
+    subgoals: &[[f64; GOAL_DIMS]],
+    control_inputs: &[[f64; NUM_CTRLS]],
+    goal_radius: f64,
+    store_rect: bool,
+    fixed_step: bool,
+    rtreach_dynamic_control: bool,
+    obstacle_sim_fn: fn(t: f64, obs: &mut Vec<Obstacle>),
+) -> (bool, usize, Vec<(f64, HyperRectangle<NUM_DIMS>)>, Verdict)
+where
+    V: Vehicle<NUM_DIMS, GOAL_DIMS, NUM_CTRLS>,
+{
+    let deadline = SharedDeadline::new(max_runtime_us, control_inputs.len());
+    let mut first_safe: Option<(usize, Rects<NUM_DIMS>, Verdict)> = None;
+    for (idx, control_input) in control_inputs.iter().enumerate() {
+        let Some(runtime_us) = deadline.next_budget_us() else {
+            break;
+        };
+        system_model.set_goal(subgoals[idx]);
+        let spec = ReachAvoidSpec::position_goal(
+            new_interval(subgoals[idx][0] - goal_radius, subgoals[idx][0] + goal_radius),
+            new_interval(subgoals[idx][1] - goal_radius, subgoals[idx][1] + goal_radius),
+            new_interval(0.0, sim_time),
+        );
+        let options = LiftingOptions::default().store_rect(store_rect).fixed_step(fixed_step).dynamic_control(rtreach_dynamic_control).reach_avoid(&spec);
+        let (LiftingResult { safe, verdict, .. }, storage_vec) = system_model.run_reachability(start_state, control_input, sim_time, init_step_size, runtime_us, start_ms, obstacle_sim_fn, options);
+        if verdict == Verdict::Sat {
+            return (true, idx, storage_vec, verdict);
+        }
+        if safe && first_safe.is_none() {
+            first_safe = Some((idx, storage_vec, verdict));
+        }
+    }
+    match first_safe {
+        Some((idx, storage_vec, verdict)) => (true, idx, storage_vec, verdict),
+        None => (false, 0, Vec::new(), Verdict::Unknown),
+    }
+}
+
+// Candidates on the path segment from start to goal, closest to the goal first, followed by
+// the ones of the pattern around them and the vehicle
+fn generate_subgoals<V, const NUM_DIMS: usize, const GOAL_DIMS: usize, const NUM_CTRLS: usize>(
+    state: &[f64; NUM_DIMS],
+    start: &[f64; GOAL_DIMS],
+    goal: &[f64; GOAL_DIMS],
+    num_subgoal_cands: u32,
+    sliding_window: bool,
+    range_behind: f64,
+    pattern: SubgoalPattern,
+) -> Vec<[f64; GOAL_DIMS]>
+where
+    V: Vehicle<NUM_DIMS, GOAL_DIMS, NUM_CTRLS>,
+{
+    let position: [f64; GOAL_DIMS] = std::array::from_fn(|d| state[d]);
+    let mut subgoals =
+    if sliding_window {
+        generate_linear_subgoals_sliding(start, goal, &position, num_subgoal_cands, range_behind, SLIDING_RANGE)
+    } else {
+        generate_linear_subgoals_simple(start, goal, num_subgoal_cands, V::SUBGOALS_FROM_START)
+    };
+    subgoals.reverse(); // Reverse the order to prioritize subgoals closer to the goal
+    // the pattern candidates follow the segment ones, ranked by how close they get to the goal
+    let mut extra = pattern_subgoals(pattern, start, goal, &subgoals, [state[0], state[1]], state[V::HEADING_DIM]);
+    sort_by_progress(&mut extra, goal);
+    subgoals.extend(extra);
+    subgoals
+}
+
+// Function to generate subgoal candiates evenly spaced along the path
+// Inputs: start and goal points, number of subgoals to generate, whether the start is one
+// Output: Vector of subgoal candidates
+pub fn generate_linear_subgoals_simple<const DIMS: usize>(start: &[f64; DIMS], goal: &[f64; DIMS], num_subgoals: u32, from_start: bool) -> Vec<[f64; DIMS]> {
+    let step: [f64; DIMS] = std::array::from_fn(|d| (goal[d] - start[d]) / num_subgoals as f64);
+    let first = if from_start { 0 } else { 1 };
+    (first..=num_subgoals)
+        .map(|i| std::array::from_fn(|d| start[d] + i as f64 * step[d]))
+        .collect()
+}
+
+// The window slides along the line from start to goal around the projection of the robot
+// position, range_behind before and range_ahead after it, trimmed to the segment
+pub fn generate_linear_subgoals_sliding<const DIMS: usize>(
+    start: &[f64; DIMS],
+    goal: &[f64; DIMS],
+    robot_position: &[f64; DIMS],
+    num_subgoals: u32,
+    range_behind: f64,
+    range_ahead: f64,
+) -> Vec<[f64; DIMS]> {
+    // Vector from start to goal
+    let line_length = distance(start, goal);
+    if line_length == 0.0 {
+        return vec![*goal];
+    }
+    let unit_line_vec: [f64; DIMS] = std::array::from_fn(|d| (goal[d] - start[d]) / line_length);
+    let along = |p: &[f64; DIMS], q: &[f64; DIMS]| (0..DIMS).map(|d| (p[d] - q[d]) * unit_line_vec[d]).sum::<f64>();
+    let point_at = |l: f64| -> [f64; DIMS] { std::array::from_fn(|d| start[d] + l * unit_line_vec[d]) };
+
+    // Projection of the robot position on the line
+    let projection_length = along(robot_position, start);
+
+    // Define the segment start and end around the projected point
+    let mut segment_start = point_at(projection_length - range_behind);
+    let mut segment_end = point_at(projection_length + range_ahead);
+
+    // Trim segment start if it is behind the `start` point
+    if along(&segment_start, start) < 0.0 {
+        segment_start = *start;
+    }
+
+    // Trim segment end if it is ahead of the `goal` point
+    if along(&segment_end, goal) > 0.0 {
+        segment_end = *goal;
+    }
+
+    // Divide the segment into `num_subgoals` evenly spaced points
+    generate_linear_subgoals_simple(&segment_start, &segment_end, num_subgoals, true)
+}
+
+// Euclidean distance over the dims of pos2
+fn distance(pos1: &[f64], pos2: &[f64]) -> f64 {
+    pos2.iter().zip(pos1).map(|(a, b)| (a - b) * (a - b)).sum::<f64>().sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_linear_subgoals() {
+        let simple = generate_linear_subgoals_simple(&[0.0, 0.0], &[4.0, 2.0], 2, true);
+        assert_eq!(simple, vec![[0.0, 0.0], [2.0, 1.0], [4.0, 2.0]]);
+        let simple = generate_linear_subgoals_simple(&[0.0, 0.0, 1.0], &[4.0, 0.0, 3.0], 2, false);
+        assert_eq!(simple, vec![[2.0, 0.0, 2.0], [4.0, 0.0, 3.0]]);
+
+        // window of 1 m behind and 2 m ahead of the projection at x = 3
+        let sliding = generate_linear_subgoals_sliding(&[0.0, 0.0], &[10.0, 0.0], &[3.0, 1.0], 3, 1.0, 2.0);
+        assert_eq!(sliding, vec![[2.0, 0.0], [3.0, 0.0], [4.0, 0.0], [5.0, 0.0]]);
+        // trimmed to the segment
+        let sliding = generate_linear_subgoals_sliding(&[0.0, 0.0, 0.0], &[0.0, 0.0, 4.0], &[0.0, 0.0, 3.0], 2, 5.0, 5.0);
+        assert_eq!(sliding, vec![[0.0, 0.0, 0.0], [0.0, 0.0, 2.0], [0.0, 0.0, 4.0]]);
+        assert_eq!(generate_linear_subgoals_sliding(&[1.0, 1.0], &[1.0, 1.0], &[0.0, 0.0], 3, 5.0, 5.0), vec![[1.0, 1.0]]);
+    }
+
+    #[test]
+    fn test_shared_deadline() {
+        // the quadcopter grid of 82 candidates in 100 ms
+        let budget = SharedDeadline::new(100_000, 82).next_budget_us().unwrap();
+        assert!(budget > 0 && budget <= 1219);
+        // more candidates than microseconds still get a deadline
+        assert_eq!(SharedDeadline::new(1_000, 5_000).next_budget_us(), Some(1));
+        assert_eq!(SharedDeadline::new(0, 82).next_budget_us(), Some(0));
+
+        let deadline = SharedDeadline::new(1_000, 2);
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        assert_eq!(deadline.next_budget_us(), None);
+    }
+}
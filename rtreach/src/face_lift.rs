@@ -1,13 +1,61 @@
-use std::sync::Mutex;
-use std::time::{SystemTime, UNIX_EPOCH, Duration};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 use lazy_static::lazy_static;
 use super::geometry::*;
+use super::obstacle::Obstacle;
+use super::reach_avoid::{ReachAvoidSpec, ReachTracker, Verdict};
 use super::system_model::SystemModel;
 use super::util::*;
 use super::debug::DEBUG;
 
 lazy_static! {
     pub static ref ITERATIONS_AT_QUIT: Mutex<u64> = Mutex::new(0); // lazy_
+    // predicts the duration of the next iteration, see CostModel
+    pub static ref COST_MODEL: Mutex<CostModel> = Mutex::new(CostModel::default());
+}
+
+// Predicted duration of the next iteration, growth times the last one plus margin_us, and at
+// least growth times the previous prediction. Halving the step size about doubles the work.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CostModel {
+    pub growth: f64,
+    pub margin_us: u64,
+}
+
+impl Default for CostModel {
+    fn default() -> Self {
+        CostModel { growth: 2.0, margin_us: 1000 }
+    }
+}
+
+impl CostModel {
+    pub fn next_iteration_us(&self, last_iteration_us: u64, previous_estimate_us: u64) -> u64 {
+        let estimate = (self.growth * last_iteration_us as f64) as u64 + self.margin_us;
+        if estimate < previous_estimate_us {
+            (self.growth * previous_estimate_us as f64) as u64
+        } else {
+            estimate
+        }
+    }
+}
+
+pub fn set_cost_model(cost_model: CostModel) {
+    let mut cost_model_lock = COST_MODEL.lock().unwrap();
+    *cost_model_lock = cost_model;
+}
+
+// How close to the deadline a call finished, returned by the calls that have a use for it
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct DeadlineReport {
+    pub budget_us: u64,             // 0 without a deadline
+    pub elapsed_us: u64,            // time spent in the call
+    pub slack_us: i64,              // budget_us - elapsed_us, negative if the deadline was missed
+    pub iterations: u64,
+    pub last_iteration_us: u64,     // duration of the last iteration
+    pub predicted_next_us: u64,     // predicted duration of the iteration that was not started
+    pub step_size: f64,             // step size of the returned result, 0 if none
+    pub cancelled: bool,            // whether the call was cut off by its CancellationToken
 }
 
 #[derive(Copy, Clone)]
@@ -16,13 +64,48 @@ pub struct LiftingSettings<const NUM_DIMS: usize> {
     pub reach_time: f64,                     // total reach time
     pub initial_step_size: f64,              // the initial size of the steps to use
     pub max_rect_width_before_error: f64,    // maximum allowed rectangle size
-    pub max_runtime_milliseconds: u64,       // maximum runtime in milliseconds
-    pub obstacle_sim_fn: fn(t: f64, obs: &mut Vec<Vec<Vec<f64>>>), // obstacle simulation function
-    pub reached_at_intermediate_time: Option<fn(&mut HyperRectangle<NUM_DIMS>, time: f64, obstacle_sim_fn: fn(t: f64, obs: &mut Vec<Vec<Vec<f64>>>), store_rect: bool, storage_vec: &mut Vec<(f64, HyperRectangle<NUM_DIMS>)>) -> bool>, // callback for intermediate time
-    pub reached_at_final_time: Option<fn(&mut HyperRectangle<NUM_DIMS>, time: f64, obstacle_sim_fn: fn(t: f64, obs: &mut Vec<Vec<Vec<f64>>>), store_rect: bool, storage_vec: &mut Vec<(f64, HyperRectangle<NUM_DIMS>)>) -> bool>,        // callback for final time
+    pub max_runtime_us: u64,                 // maximum runtime in microseconds
+    pub obstacle_sim_fn: fn(t: f64, obs: &mut Vec<Obstacle>), // obstacle simulation function
+    pub reached_at_intermediate_time: Option<fn(&mut HyperRectangle<NUM_DIMS>, time: f64, obstacle_sim_fn: fn(t: f64, obs: &mut Vec<Obstacle>), store_rect: bool, storage_vec: &mut Vec<(f64, HyperRectangle<NUM_DIMS>)>) -> bool>, // callback for intermediate time
+    pub reached_at_final_time: Option<fn(&mut HyperRectangle<NUM_DIMS>, time: f64, obstacle_sim_fn: fn(t: f64, obs: &mut Vec<Obstacle>), store_rect: bool, storage_vec: &mut Vec<(f64, HyperRectangle<NUM_DIMS>)>) -> bool>,        // callback for final time
     pub restarted_computation: Option<fn(store_rect: bool, storage_vec: &mut Vec<(f64, HyperRectangle<NUM_DIMS>)>)>,         // callback for restarted computation
 }
 
+// What a call learned about the step sizes that fit its deadline, to warm start the next
+// call with the same deadline from there instead of from initial_step_size
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct ReachHint {
+    pub step_size: f64,         // step size of the iteration whose result was returned, 0 if none
+    pub iteration_us: u64,      // how long that iteration took
+}
+
+// Shared flag to abort a call from another thread. It is checked before every step of the
+// lifting loop, a cancelled call returns the result of the last completed iteration, unsafe
+// if there is none. Clones share the flag.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        CancellationToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    // make the token usable for another call
+    pub fn reset(&self) {
+        self.cancelled.store(false, Ordering::SeqCst);
+    }
+}
+
 // Constants necessary to guarantee loop termination.
 // These bound the values of the derivatives
 pub const MAX_DER_B: f64 = 99999.0;
@@ -36,7 +119,7 @@ pub const MIN_DER_B: f64 = -99999.0;
 // For two dimensional Rectangle:     0 <= x1 <= 2; 1 <= x2 <= 3: at the dimension 1 (i.e., x1 axis) the minimum face
 // is a line x1 = 0, 1 <= x2 <= 3 and the maximum face is a line x1 = 2, 1 <= x2 <= 3
 
-fn make_neighborhood_rect<const NUM_DIMS: usize>(
+pub fn make_neighborhood_rect<const NUM_DIMS: usize>(
     out: &mut HyperRectangle<NUM_DIMS>,
     face_index: usize,
     bloated_rect: &HyperRectangle<NUM_DIMS>,
@@ -81,32 +164,31 @@ fn make_neighborhood_rect<const NUM_DIMS: usize>(
 // et (error tracker) is set if you want to track the sources of errors, can be null
 // returns time elapsed
 
-fn lift_single_rect<const NUM_DIMS: usize, T: SystemModel<NUM_DIMS>>(system_model: &T, rect: &mut HyperRectangle<NUM_DIMS>, step_size: f64, time_remaining: f64, ctrl_input: &Vec<f64>, settings: &LiftingSettings<NUM_DIMS>) -> f64 {
+fn lift_single_rect<const NUM_DIMS: usize, const NUM_CTRLS: usize, T: SystemModel<NUM_DIMS, NUM_CTRLS>>(system_model: &T, rect: &mut HyperRectangle<NUM_DIMS>, step_size: f64, time_remaining: f64, ctrl_input: &[f64; NUM_CTRLS], settings: &LiftingSettings<NUM_DIMS>) -> f64 {
     // Create a copy of the rectangle for face-lifting operations
     let mut bloated_rect: HyperRectangle<NUM_DIMS> = *rect;
     
-    // Create an array to store neighborhood widths
-    let mut neb_width = vec![0.0; system_model.num_faces()];
+    // Create an array to store neighborhood widths, indexed by dimension then min / max face
+    // so it stays on the stack
+    let mut neb_width = [[0.0; 2]; NUM_DIMS];
 
     let mut need_recompute: bool = true;
     let mut min_neb_cross_time: f64 = 0.0;
-    let mut ders = vec![0.0; system_model.num_faces()]; // array that stores each derivative for each face
+    let mut ders = [[0.0; 2]; NUM_DIMS]; // array that stores each derivative for each face
     
     while need_recompute {
         need_recompute = false;
         min_neb_cross_time = f64::MAX;
+
+        // test derivatives inside the candidate neighborhoods of all faces
+        let face_ders: [Interval; NUM_DIMS] = system_model.get_face_derivative_bounds(rect, &bloated_rect, &neb_width, ctrl_input);
     
         for f in 0..system_model.num_faces() {
             let dim: usize = f / 2;
-            let is_min: bool = (f % 2) == 0;
-
-            let mut face_neb_rect: HyperRectangle<NUM_DIMS> = HyperRectangle::<NUM_DIMS>::default();
+            let side: usize = f % 2;
+            let is_min: bool = side == 0;
 
-            // make candidate neighborhood
-            make_neighborhood_rect::<NUM_DIMS>(&mut face_neb_rect, f, &bloated_rect, rect, neb_width[f]);
-
-            // test derivative inside neighborhood
-            let mut der: f64 = system_model.get_derivative_bounds(&face_neb_rect, f, ctrl_input);
+            let mut der: f64 = if is_min { face_ders[dim].min } else { face_ders[dim].max };
 
             // so we cap the derivative at 999999 and min at the negative of that
             if der > MAX_DER_B {
@@ -115,7 +197,7 @@ fn lift_single_rect<const NUM_DIMS: usize, T: SystemModel<NUM_DIMS>>(system_mode
                 der = MIN_DER_B;
             }
 
-            let prev_neb_width: f64 = neb_width[f];
+            let prev_neb_width: f64 = neb_width[dim][side];
             let mut new_neb_width: f64 = der * step_size;
 
             // check if it's growing outward
@@ -138,12 +220,12 @@ fn lift_single_rect<const NUM_DIMS: usize, T: SystemModel<NUM_DIMS>>(system_mode
             }
 
             if need_recompute {
-                neb_width[f] = new_neb_width;
+                neb_width[dim][side] = new_neb_width;
 
-                if is_min && neb_width[f] < 0.0 {
-                    bloated_rect.dims[dim].min = rect.dims[dim].min + neb_width[f];
-                } else if !is_min && neb_width[f] > 0.0 {
-                    bloated_rect.dims[dim].max = rect.dims[dim].max + neb_width[f];
+                if is_min && neb_width[dim][side] < 0.0 {
+                    bloated_rect.dims[dim].min = rect.dims[dim].min + neb_width[dim][side];
+                } else if !is_min && neb_width[dim][side] > 0.0 {
+                    bloated_rect.dims[dim].max = rect.dims[dim].max + neb_width[dim][side];
                 }
 
             } else {
@@ -164,7 +246,7 @@ fn lift_single_rect<const NUM_DIMS: usize, T: SystemModel<NUM_DIMS>>(system_mode
                     }
                 }
 
-                ders[f] = der;
+                ders[dim][side] = der;
             }
         }
     }
@@ -190,8 +272,8 @@ fn lift_single_rect<const NUM_DIMS: usize, T: SystemModel<NUM_DIMS>>(system_mode
 
     // do the lifting
     for d in 0..NUM_DIMS{
-        rect.dims[d].min += ders[2*d] * time_to_elapse;
-        rect.dims[d].max += ders[2*d+1] * time_to_elapse;
+        rect.dims[d].min += ders[d][0] * time_to_elapse;
+        rect.dims[d].max += ders[d][1] * time_to_elapse;
     } 
 
     if !hyperrectangle_contains(&bloated_rect, rect, true){
@@ -201,32 +283,148 @@ fn lift_single_rect<const NUM_DIMS: usize, T: SystemModel<NUM_DIMS>>(system_mode
     time_to_elapse
 }
 
-pub fn face_lifting_iterative_improvement<const NUM_DIMS: usize, T: SystemModel<NUM_DIMS>>(
+pub fn face_lifting_iterative_improvement<const NUM_DIMS: usize, const NUM_CTRLS: usize, T: SystemModel<NUM_DIMS, NUM_CTRLS>>(
     system_model: &T,
-    _: u64,
+    start_ms: u64,
     settings: &mut LiftingSettings<NUM_DIMS>,
-    initial_ctrl_input: &Vec<f64>,
+    initial_ctrl_input: &[f64; NUM_CTRLS],
     store_rect: bool,
     storage_vec: &mut Vec<(f64, HyperRectangle<NUM_DIMS>)>,
     fixed_step: bool,
     dynamic_control: bool,
 ) -> bool {
+    let options = LiftingOptions::default().store_rect(store_rect).fixed_step(fixed_step).dynamic_control(dynamic_control);
+    face_lifting_with(system_model, start_ms, settings, initial_ctrl_input, storage_vec, options).safe
+}
+
+// Step size to start from with a hint: one level coarser than the hinted one so there is a
+// result early, and coarser still while that level is not expected to fit in half the deadline.
+// Each level costs about growth times the previous one, see CostModel.
+fn warm_start_step_size<const NUM_DIMS: usize>(hint: &ReachHint, settings: &LiftingSettings<NUM_DIMS>) -> f64 {
+    if hint.step_size <= 0.0 {
+        return settings.initial_step_size;
+    }
+    let growth = COST_MODEL.lock().unwrap().growth;
+    let mut step_size = hint.step_size * 2.0;
+    let mut estimate_us = hint.iteration_us as f64 / growth;
+    while settings.max_runtime_us > 0 && estimate_us * 2.0 > settings.max_runtime_us as f64 && step_size * 2.0 <= settings.initial_step_size {
+        step_size *= 2.0;
+        estimate_us /= growth;
+    }
+    step_size.min(settings.initial_step_size)
+}
+
+// Signed distance of a hull from the obstacles at a time, see LiftingOptions::clearance
+pub type ClearanceFn<const NUM_DIMS: usize> = fn(&HyperRectangle<NUM_DIMS>, time: f64, obstacle_sim_fn: fn(t: f64, obs: &mut Vec<Obstacle>)) -> f64;
+
+// What face_lifting_with checks besides the safety callbacks of the settings and how it runs.
+// The default refines the step size until the deadline under the initial control input,
+// stores no rects and checks nothing else.
+#[derive(Default)]
+pub struct LiftingOptions<'a, const NUM_DIMS: usize> {
+    spec: Option<&'a ReachAvoidSpec<NUM_DIMS>>,
+    clearance_fn: Option<ClearanceFn<NUM_DIMS>>,
+    store_rect: bool,
+    fixed_step: bool,
+    dynamic_control: bool,
+    hint: Option<&'a mut ReachHint>,
+    cancel: Option<&'a CancellationToken>,
+}
+
+impl<'a, const NUM_DIMS: usize> LiftingOptions<'a, NUM_DIMS> {
+    // store the rects of the returned iteration in storage_vec
+    pub fn store_rect(mut self, store_rect: bool) -> Self {
+        self.store_rect = store_rect;
+        self
+    }
+
+    // run the initial step size only
+    pub fn fixed_step(mut self, fixed_step: bool) -> Self {
+        self.fixed_step = fixed_step;
+        self
+    }
+
+    // resample the control input from the model over the boxes
+    pub fn dynamic_control(mut self, dynamic_control: bool) -> Self {
+        self.dynamic_control = dynamic_control;
+        self
+    }
+
+    // also check the reach part of a reach-avoid specification, the avoid part is what the
+    // settings callbacks check
+    pub fn reach_avoid(mut self, spec: &'a ReachAvoidSpec<NUM_DIMS>) -> Self {
+        self.spec = Some(spec);
+        self
+    }
+
+    // also measure how far the reach set stays from the obstacles with clearance_fn
+    pub fn clearance(mut self, clearance_fn: ClearanceFn<NUM_DIMS>) -> Self {
+        self.clearance_fn = Some(clearance_fn);
+        self
+    }
+
+    // start from the step size of the hint, which is updated for the next call
+    pub fn warm_start(mut self, hint: &'a mut ReachHint) -> Self {
+        self.hint = Some(hint);
+        self
+    }
+
+    // stop as soon as cancel is cancelled, with the result of the last completed iteration,
+    // unsafe if there is none
+    pub fn cancellable(mut self, cancel: &'a CancellationToken) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+}
+
+// Result of face_lifting_with, all from the same (last completed) iteration
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LiftingResult {
+    pub safe: bool,                 // whether the reach set passes the settings callbacks
+    pub verdict: Verdict,           // of the reach-avoid specification, Unknown without one
+    pub clearance: f64,             // smallest clearance over the horizon, infinite without clearance_fn
+    pub report: DeadlineReport,     // how the call used its deadline
+}
+
+// Face lifting of settings.init from initial_ctrl_input with what options adds on top
+pub fn face_lifting_with<const NUM_DIMS: usize, const NUM_CTRLS: usize, T: SystemModel<NUM_DIMS, NUM_CTRLS>>(
+    system_model: &T,
+    _: u64,
+    settings: &mut LiftingSettings<NUM_DIMS>,
+    initial_ctrl_input: &[f64; NUM_CTRLS],
+    storage_vec: &mut Vec<(f64, HyperRectangle<NUM_DIMS>)>,
+    options: LiftingOptions<NUM_DIMS>,
+) -> LiftingResult {
+    let LiftingOptions { spec, clearance_fn, store_rect, fixed_step, dynamic_control, hint, cancel } = options;
     let rv: bool;
+    let rv_verdict: Verdict;
+    let rv_clearance: f64;
     let mut last_iteration_safe = false;
+    let mut last_iteration_verdict = Verdict::Unknown;
+    let mut last_iteration_clearance = f64::NEG_INFINITY;
+    let mut last_iteration_us: u64 = 0;
+    // step size and duration of the iteration whose result is returned, for the hint
+    let mut rv_step_size: f64 = 0.0;
+    let mut rv_iteration_us: u64 = 0;
+    let mut cancelled = false;
     
-    let now: SystemTime = SystemTime::now();
-    let start: Duration = now.duration_since(UNIX_EPOCH).unwrap();
-    let mut elapsed_total: u64 = 0;
+    let start = Instant::now();
+    let cost_model: CostModel = *COST_MODEL.lock().unwrap();
+    let budget_us: u64 = settings.max_runtime_us;
+    let mut elapsed_total: u64 = 0;     // microseconds
 
     // Get the settings from the facelifting settings
-    let mut step_size: f64 = settings.initial_step_size;
+    let mut step_size: f64 = match &hint {
+        Some(hint) => warm_start_step_size(hint, settings),
+        None => settings.initial_step_size,
+    };
 
     let mut iter: u64 = 0;           // number of iterations
-    let mut previous_iter: u64;
+    let mut previous_iter: u64 = 0;
     let mut elapsed_prev: u64 = 0;
     let mut next_iter_estimate: u64 = 0;
 
-    let mut ctrl_input = initial_ctrl_input.clone();
+    let mut ctrl_input = *initial_ctrl_input;
 
     loop{
         iter += 1;
@@ -238,6 +436,12 @@ pub fn face_lifting_iterative_improvement<const NUM_DIMS: usize, T: SystemModel<
                 println!("Quitting from step size too small: stepSize: {} at iteration: {}\n\r", step_size, iter);
             }
             rv = false;
+            rv_verdict = Verdict::Unknown;
+            rv_clearance = f64::NEG_INFINITY;
+            if iter > 1 {
+                rv_step_size = step_size * 2.0;
+                rv_iteration_us = last_iteration_us;
+            }
             break;
         }
 
@@ -258,16 +462,36 @@ pub fn face_lifting_iterative_improvement<const NUM_DIMS: usize, T: SystemModel<
 		// I want to visualize an over-approximation of the over-all reachset too
         let mut total_hull: HyperRectangle<NUM_DIMS> = tracked_rect;
 
+        let mut tracker = spec.map(|spec| ReachTracker::new(spec, &tracked_rect));
+        let mut clearance = f64::INFINITY;
+
         // compute reachability up to split time
 		while safe && time_remaining > 0.0 {
+            // the caller no longer wants the result of this iteration
+            if cancel.is_some_and(|cancel| cancel.is_cancelled()) {
+                cancelled = true;
+                break;
+            }
+
             // reachedAtIntermediateTime is a function that checks the current hyper-rectangle against the safety specification,
 			// whatever that might be
             if let Some(_) = settings.reached_at_intermediate_time {
                 hull = tracked_rect;
             }
 
+            let step_start_rect = tracked_rect;
+
             // debug changed so error tracker is always passed in (see note)
-            let time_elapsed: f64 = lift_single_rect::<NUM_DIMS, T>(system_model, &mut tracked_rect, step_size, time_remaining, &ctrl_input, settings);
+            let time_elapsed: f64 = lift_single_rect::<NUM_DIMS, NUM_CTRLS, T>(system_model, &mut tracked_rect, step_size, time_remaining, &ctrl_input, settings);
+
+            // check the goal on the set at the end of the step and on the hull of the whole step
+            if let Some(tracker) = tracker.as_mut() {
+                let t_start = settings.reach_time - time_remaining;
+                let mut step_hull = step_start_rect;
+                hyperrectangle_grow_to_convex_hull(&mut step_hull, &tracked_rect);
+                tracker.observe_span(&step_hull, t_start, t_start + time_elapsed);
+                tracker.observe_time(&tracked_rect, t_start + time_elapsed);
+            }
             if dynamic_control {
                 ctrl_input = system_model.sample_control(&tracked_rect);
             }
@@ -284,9 +508,10 @@ pub fn face_lifting_iterative_improvement<const NUM_DIMS: usize, T: SystemModel<
                 hyperrectangle_grow_to_convex_hull(&mut total_hull, &tracked_rect);
                 
                 // println!("safe1: {}", safe);
-                let mut int_t = settings.reach_time - time_remaining - time_elapsed;
-                if int_t < 0.0 {
-                    int_t = 0.0;
+                // the hull covers the step starting at int_t
+                let int_t = settings.reach_time - time_remaining;
+                if let Some(clearance_fn) = clearance_fn {
+                    clearance = clearance.min(clearance_fn(&hull, int_t, settings.obstacle_sim_fn));
                 }
                 safe = safe && reached_at_intermediate_time(&mut hull, int_t, settings.obstacle_sim_fn, store_rect, storage_vec);
                 // println!("safe2: {}", safe);
@@ -294,10 +519,8 @@ pub fn face_lifting_iterative_improvement<const NUM_DIMS: usize, T: SystemModel<
 
             if time_elapsed == time_remaining{
                 if let Some(reached_at_final_time) = settings.reached_at_final_time {
-                    let mut f_t = settings.reach_time - time_remaining - time_elapsed;
-                    if f_t < 0.0 {
-                        f_t = 0.0;
-                    }
+                    // tracked_rect is the set at the end of the last step
+                    let f_t = settings.reach_time - time_remaining + time_elapsed;
                     safe = safe && reached_at_final_time(&mut tracked_rect, f_t, settings.obstacle_sim_fn, store_rect, storage_vec);
                 }
             }
@@ -309,31 +532,46 @@ pub fn face_lifting_iterative_improvement<const NUM_DIMS: usize, T: SystemModel<
         // it continues until the simulation time is over, or we encounter an unsafe state,
         // whichever occurs first. 
 
-        // Don't do another iteration unless you want to miss the deadline
-        let now: u64 = milliseconds2(&start);
-        elapsed_total = now;
-        previous_iter = elapsed_total - elapsed_prev;
-
-        // its O(2^N) in terms of box checking so have to scale the next iteration by 2 and add 1ms (for over-approximating how long it takes to compute the reachset)
-        if previous_iter == 0{
-            next_iter_estimate = 2;
-        } else {
-            if (previous_iter * 2 + 1) < next_iter_estimate{
-                next_iter_estimate = next_iter_estimate * 2;
+        if cancelled {
+            if DEBUG{
+                println!("Quitting from cancellation at iteration: {}\n\r", iter);
+            }
+            if iter > 1 {
+                rv = last_iteration_safe;
+                rv_verdict = last_iteration_verdict;
+                rv_clearance = last_iteration_clearance;
+                rv_step_size = step_size * 2.0;
+                rv_iteration_us = last_iteration_us;
             } else {
-                next_iter_estimate = previous_iter * 2 + 1;
+                rv = false;
+                rv_verdict = Verdict::Unknown;
+                rv_clearance = f64::NEG_INFINITY;
             }
+            break;
         }
 
+        let verdict = match &tracker {
+            Some(tracker) => tracker.verdict(safe),
+            None => Verdict::Unknown,
+        };
+
+        // Don't do another iteration unless you want to miss the deadline
+        elapsed_total = start.elapsed().as_micros() as u64;
+        previous_iter = elapsed_total - elapsed_prev;
+
+        // its O(2^N) in terms of box checking so the next iteration costs about twice as much,
+        // plus a margin for over-approximating how long it takes to compute the reachset
+        next_iter_estimate = cost_model.next_iteration_us(previous_iter, next_iter_estimate);
+
         elapsed_prev = elapsed_total;
-        if settings.max_runtime_milliseconds > 0 {
-            let remaining: i32  = settings.max_runtime_milliseconds as i32 - elapsed_total as i32;
+        if settings.max_runtime_us > 0 {
+            let remaining: i64 = settings.max_runtime_us as i64 - elapsed_total as i64;
             if DEBUG && remaining < 0 {
-                println!("remaining: {}\r\n",remaining);
+                println!("remaining: {}us\r\n",remaining);
             }
-            if remaining <= next_iter_estimate as i32 {
+            if remaining <= next_iter_estimate as i64 {
                 // we've exceeded our time, use the result from the last iteration
-				// note in a real system you would have an interrupt or something to cut off computation
+				// to cut off the computation itself, see CancellationToken
                 if DEBUG{
                     println!("Quitting from runtime maxed out");
                     println(&tracked_rect);
@@ -345,8 +583,16 @@ pub fn face_lifting_iterative_improvement<const NUM_DIMS: usize, T: SystemModel<
                 }
                 if iter > 1 {
                     rv = last_iteration_safe;
+                    rv_verdict = last_iteration_verdict;
+                    rv_clearance = last_iteration_clearance;
+                    rv_step_size = step_size * 2.0;
+                    rv_iteration_us = last_iteration_us;
                 } else {
                     rv = safe;
+                    rv_verdict = verdict;
+                    rv_clearance = clearance;
+                    rv_step_size = step_size;
+                    rv_iteration_us = previous_iter;
                 }
                 break;
             }
@@ -357,20 +603,31 @@ pub fn face_lifting_iterative_improvement<const NUM_DIMS: usize, T: SystemModel<
                 }
             }
         } else {
-            if settings.max_runtime_milliseconds == 0 {
-                settings.max_runtime_milliseconds += 1;
+            if settings.max_runtime_us == 0 {
+                settings.max_runtime_us += 1;
                 if DEBUG{
                     println!("Splitting\n\r");
                 }
                 rv = safe;
+                rv_verdict = verdict;
+                rv_clearance = clearance;
+                rv_step_size = step_size;
+                rv_iteration_us = previous_iter;
                 break;
             }
         }
 
         
 		last_iteration_safe = safe;
+        last_iteration_verdict = verdict;
+        last_iteration_clearance = clearance;
+        last_iteration_us = previous_iter;
         if fixed_step{
             rv = safe;
+            rv_verdict = verdict;
+            rv_clearance = clearance;
+            rv_step_size = step_size;
+            rv_iteration_us = previous_iter;
             break;
         }
 		// apply error-reducing strategy
@@ -383,10 +640,238 @@ pub fn face_lifting_iterative_improvement<const NUM_DIMS: usize, T: SystemModel<
         *iterations_at_quit = iter;
     }
 
+    if let Some(hint) = hint {
+        if rv_step_size > 0.0 {
+            *hint = ReachHint { step_size: rv_step_size, iteration_us: rv_iteration_us };
+        }
+    }
+
+    let elapsed_us = start.elapsed().as_micros() as u64;
+    let report = DeadlineReport {
+        budget_us,
+        elapsed_us,
+        slack_us: budget_us as i64 - elapsed_us as i64,
+        iterations: iter,
+        last_iteration_us: previous_iter,
+        predicted_next_us: next_iter_estimate,
+        step_size: rv_step_size,
+        cancelled,
+    };
+
     if DEBUG{
-        println!("{}ms: step_size = {}", elapsed_total, step_size);
+        println!("{}us: step_size = {}", elapsed_total, step_size);
         println!("iterations at quit: {}", iter);
     }
 
-    rv
+    LiftingResult { safe: rv, verdict: rv_verdict, clearance: rv_clearance, report }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interval::new_interval;
+    use std::cell::Cell;
+
+    struct Drift;
+
+    impl SystemModel<1, 0> for Drift {
+        fn get_derivative_bounds(&self, _: &HyperRectangle<1>, _: usize, _: &[f64; 0]) -> f64 {
+            1.0
+        }
+
+        fn sample_control(&self, _: &HyperRectangle<1>) -> [f64; 0] {
+            []
+        }
+    }
+
+    // Drift that cancels its token after a number of derivative evaluations
+    struct CancellingDrift {
+        cancel: CancellationToken,
+        calls: Cell<u64>,
+        cancel_after: u64,
+    }
+
+    impl SystemModel<1, 0> for CancellingDrift {
+        fn get_derivative_bounds(&self, _: &HyperRectangle<1>, _: usize, _: &[f64; 0]) -> f64 {
+            self.calls.set(self.calls.get() + 1);
+            if self.calls.get() == self.cancel_after {
+                self.cancel.cancel();
+            }
+            1.0
+        }
+
+        fn sample_control(&self, _: &HyperRectangle<1>) -> [f64; 0] {
+            []
+        }
+    }
+
+    // x' = y, y' = -x
+    struct Rotation;
+
+    impl SystemModel<2, 0> for Rotation {
+        fn get_derivative_bounds(&self, rect: &HyperRectangle<2>, face_index: usize, _: &[f64; 0]) -> f64 {
+            let der = if face_index / 2 == 0 { rect.dims[1] } else { new_interval(-rect.dims[0].max, -rect.dims[0].min) };
+            if face_index.is_multiple_of(2) { der.min } else { der.max }
+        }
+
+        fn sample_control(&self, _: &HyperRectangle<2>) -> [f64; 0] {
+            []
+        }
+    }
+
+    // Rotation evaluating both faces of a dimension on bloated_rect, neither derivative
+    // depends on its own dimension
+    struct SharedRotation;
+
+    impl SystemModel<2, 0> for SharedRotation {
+        fn get_derivative_bounds(&self, rect: &HyperRectangle<2>, face_index: usize, ctrl_inputs: &[f64; 0]) -> f64 {
+            Rotation.get_derivative_bounds(rect, face_index, ctrl_inputs)
+        }
+
+        fn get_face_derivative_bounds(&self, _: &HyperRectangle<2>, bloated_rect: &HyperRectangle<2>, _: &[[f64; 2]; 2], _: &[f64; 0]) -> [Interval; 2] {
+            [bloated_rect.dims[1], new_interval(-bloated_rect.dims[0].max, -bloated_rect.dims[0].min)]
+        }
+
+        fn sample_control(&self, _: &HyperRectangle<2>) -> [f64; 0] {
+            []
+        }
+    }
+
+    fn drift_settings() -> LiftingSettings<1> {
+        LiftingSettings::<1> {
+            init: HyperRectangle { dims: [new_interval(0.0, 0.1)] },
+            reach_time: 1.0,
+            initial_step_size: 0.5,
+            max_rect_width_before_error: 100.0,
+            max_runtime_us: 1_000_000,
+            obstacle_sim_fn: |_, _| {},
+            reached_at_intermediate_time: None,
+            reached_at_final_time: None,
+            restarted_computation: None,
+        }
+    }
+
+    #[test]
+    fn test_warm_start() {
+        let mut settings = drift_settings();
+        let mut storage_vec = vec![(0.0, settings.init)];
+        let mut hint = ReachHint::default();
+
+        // without a hint the first step size is the initial one
+        let result = face_lifting_with(&Drift, 0, &mut settings, &[], &mut storage_vec, LiftingOptions::default().store_rect(true).fixed_step(true).warm_start(&mut hint));
+        assert!(result.safe);
+        assert_eq!(hint.step_size, 0.5);
+        assert_eq!(storage_vec.len(), 1);
+        let report = result.report;
+        assert_eq!(report.budget_us, 1_000_000);
+        assert_eq!(report.iterations, 1);
+        assert_eq!(report.step_size, 0.5);
+        assert_eq!(report.slack_us, 1_000_000 - report.elapsed_us as i64);
+
+        // one level coarser than the hint
+        hint = ReachHint { step_size: 0.0625, iteration_us: 0 };
+        face_lifting_with(&Drift, 0, &mut settings, &[], &mut storage_vec, LiftingOptions::default().fixed_step(true).warm_start(&mut hint));
+        assert_eq!(hint.step_size, 0.125);
+
+        // the hinted level took four deadlines, start where half a deadline is expected
+        hint = ReachHint { step_size: 0.0625, iteration_us: 4_000_000 };
+        face_lifting_with(&Drift, 0, &mut settings, &[], &mut storage_vec, LiftingOptions::default().fixed_step(true).warm_start(&mut hint));
+        assert_eq!(hint.step_size, 0.5);
+    }
+
+    fn store_rect(rect: &mut HyperRectangle<1>, time: f64, _: fn(t: f64, obs: &mut Vec<Obstacle>), _: bool, storage_vec: &mut Vec<(f64, HyperRectangle<1>)>) -> bool {
+        storage_vec.push((time, *rect));
+        true
+    }
+
+    #[test]
+    fn test_callback_times() {
+        let mut settings = drift_settings();
+        settings.max_runtime_us = 0;
+        settings.reached_at_intermediate_time = Some(store_rect);
+        settings.reached_at_final_time = Some(store_rect);
+        settings.restarted_computation = Some(|_, storage_vec| storage_vec.clear());
+        let mut storage_vec = vec![];
+        assert!(face_lifting_iterative_improvement(&Drift, 0, &mut settings, &[], true, &mut storage_vec, true, false));
+
+        // x moves at speed 1 from [0, 0.1], each hull starts at the time it is passed
+        let (final_t, final_rect) = storage_vec.pop().unwrap();
+        assert_eq!(final_t, 1.0);
+        assert!((final_rect.dims[0].min - 1.0).abs() < 1e-9);
+        assert!(storage_vec.len() > 1);
+        assert_eq!(storage_vec[0].0, 0.0);
+        for (t, hull) in &storage_vec {
+            assert!((hull.dims[0].min - t).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_cost_model() {
+        let cost_model = CostModel::default();
+        assert_eq!(cost_model.next_iteration_us(0, 0), 1000);
+        assert_eq!(cost_model.next_iteration_us(500, 0), 2000);
+        // an iteration faster than predicted does not shrink the prediction
+        assert_eq!(cost_model.next_iteration_us(500, 3000), 6000);
+
+        let cost_model = CostModel { growth: 4.0, margin_us: 0 };
+        assert_eq!(cost_model.next_iteration_us(500, 0), 2000);
+    }
+
+    #[test]
+    fn test_cancellation() {
+        let mut settings = drift_settings();
+        let mut storage_vec = vec![];
+
+        // nothing completed before the cancellation
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let LiftingResult { safe, report, .. } = face_lifting_with(&Drift, 0, &mut settings, &[], &mut storage_vec, LiftingOptions::default().cancellable(&cancel));
+        assert!(!safe);
+        assert!(report.cancelled);
+        assert_eq!(report.step_size, 0.0);
+
+        // cancelled in the middle of a later iteration, the previous one is returned
+        cancel.reset();
+        let model = CancellingDrift { cancel: cancel.clone(), calls: Cell::new(0), cancel_after: 1000 };
+        let LiftingResult { safe, report, .. } = face_lifting_with(&model, 0, &mut settings, &[], &mut storage_vec, LiftingOptions::default().cancellable(&cancel));
+        assert!(safe);
+        assert!(report.cancelled);
+        assert!(report.iterations > 1);
+        assert_eq!(report.step_size, 0.5 / (1u64 << (report.iterations - 2)) as f64);
+        assert!(report.elapsed_us < report.budget_us);
+    }
+
+    #[test]
+    fn test_face_derivative_bounds() {
+        let settings = LiftingSettings::<2> {
+            init: HyperRectangle { dims: [new_interval(1.0, 1.1), new_interval(0.0, 0.1)] },
+            reach_time: 1.0,
+            initial_step_size: 0.01,
+            max_rect_width_before_error: 100.0,
+            max_runtime_us: 0,
+            obstacle_sim_fn: |_, _| {},
+            reached_at_intermediate_time: None,
+            reached_at_final_time: None,
+            restarted_computation: None,
+        };
+
+        // the default evaluates the derivative per face neighborhood
+        let neb_width = [[-0.1, 0.2], [-0.3, 0.4]];
+        let bloated_rect = HyperRectangle { dims: [new_interval(0.9, 1.3), new_interval(-0.3, 0.5)] };
+        let bounds = Rotation.get_face_derivative_bounds(&settings.init, &bloated_rect, &neb_width, &[]);
+        assert_eq!((bounds[0].min, bounds[0].max), (-0.3, 0.5));
+        assert_eq!((bounds[1].min, bounds[1].max), (-1.3, -0.9));
+
+        // sharing the evaluation between the faces lifts the same rects
+        let mut rect = settings.init;
+        let mut shared_rect = settings.init;
+        for _ in 0..10 {
+            let elapsed = lift_single_rect(&Rotation, &mut rect, 0.01, 1.0, &[], &settings);
+            let shared_elapsed = lift_single_rect(&SharedRotation, &mut shared_rect, 0.01, 1.0, &[], &settings);
+            assert_eq!(elapsed, shared_elapsed);
+        }
+        for d in 0..2 {
+            assert_eq!((rect.dims[d].min, rect.dims[d].max), (shared_rect.dims[d].min, shared_rect.dims[d].max));
+        }
+    }
 }
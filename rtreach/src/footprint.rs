@@ -0,0 +1,114 @@
+use std::f64::consts::PI;
+use serde::Deserialize;
+use super::geometry::{HyperRectangle, Interval};
+use super::interval::new_interval_v;
+
+// Shape of the vehicle in the x/y plane around its reference point. The safety checks
+// bloat the x/y dimensions of a reach box by the bounding box of the footprint over
+// all the headings in the box. Scenario files give it as { length, width } or { radius }.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(untagged, deny_unknown_fields)]
+pub enum Footprint {
+    // length along the heading, width across it
+    Rectangle { length: f64, width: f64 },
+    Circle { radius: f64 },
+}
+
+impl Footprint {
+    // half sizes in x and y of the smallest axis-aligned box containing the footprint
+    // for every heading in the interval
+    pub fn half_extents(&self, heading: Interval) -> [f64; 2] {
+        match *self {
+            Footprint::Circle { radius } => [radius, radius],
+            Footprint::Rectangle { length, width } => {
+                let (l, w) = (length / 2.0, width / 2.0);
+                // the y extent at heading t is the x extent at t + pi / 2
+                [
+                    max_rotated_extent(l, w, heading.min, heading.max),
+                    max_rotated_extent(l, w, heading.min + PI / 2.0, heading.max + PI / 2.0),
+                ]
+            },
+        }
+    }
+
+    // rect with dims 0 and 1 bloated by the footprint, the heading is read from heading_dim
+    // or taken as 0 if there is none
+    pub fn bloat<const NUM_DIMS: usize>(&self, rect: &HyperRectangle<NUM_DIMS>, heading_dim: Option<usize>) -> HyperRectangle<NUM_DIMS> {
+        let heading = match heading_dim {
+            Some(d) => rect.dims[d],
+            None => new_interval_v(0.0),
+        };
+        let [hx, hy] = self.half_extents(heading);
+
+        let mut rv = *rect;
+        rv.dims[0].min -= hx;
+        rv.dims[0].max += hx;
+        rv.dims[1].min -= hy;
+        rv.dims[1].max += hy;
+        rv
+    }
+
+    // radius of the circle around the reference point containing the footprint
+    pub fn circumscribed_radius(&self) -> f64 {
+        match *self {
+            Footprint::Circle { radius } => radius,
+            Footprint::Rectangle { length, width } => (length * length + width * width).sqrt() / 2.0,
+        }
+    }
+}
+
+// Maximum of |cos t| * l + |sin t| * w over t in [a, b]. The function has period pi and
+// peaks at sqrt(l^2 + w^2) where t = atan(w / l) or t = pi - atan(w / l) modulo pi,
+// otherwise the maximum is at one of the ends.
+fn max_rotated_extent(l: f64, w: f64, a: f64, b: f64) -> f64 {
+    let extent = |t: f64| t.cos().abs() * l + t.sin().abs() * w;
+    let peak = (l * l + w * w).sqrt();
+    if b - a >= PI || (b - a).is_nan() {
+        return peak;
+    }
+
+    let phi = w.atan2(l);
+    for c in [phi, PI - phi] {
+        let k = ((a - c) / PI).ceil();
+        if c + k * PI <= b {
+            return peak;
+        }
+    }
+    extent(a).max(extent(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interval::new_interval;
+
+    #[test]
+    fn test_rectangle_half_extents() {
+        let fp = Footprint::Rectangle { length: 0.5, width: 0.3 };
+        let [hx, hy] = fp.half_extents(new_interval_v(0.0));
+        assert!((hx - 0.25).abs() < 1e-12 && (hy - 0.15).abs() < 1e-12);
+
+        // turned by 90 degrees the car is long in y
+        let [hx, hy] = fp.half_extents(new_interval_v(-PI / 2.0));
+        assert!((hx - 0.15).abs() < 1e-12 && (hy - 0.25).abs() < 1e-12);
+
+        // a heading range containing the diagonal reaches the circumscribed radius
+        let [hx, hy] = fp.half_extents(new_interval(0.0, 1.0));
+        assert!((hx - fp.circumscribed_radius()).abs() < 1e-12);
+        assert!((hy - (1.0f64.sin() * 0.25 + 1.0f64.cos() * 0.15)).abs() < 1e-12);
+        let [hx, _] = fp.half_extents(new_interval(0.0, 10.0));
+        assert_eq!(hx, fp.circumscribed_radius());
+    }
+
+    #[test]
+    fn test_bloat() {
+        let fp = Footprint::Circle { radius: 0.16 };
+        let mut rect: HyperRectangle<3> = HyperRectangle::default();
+        rect.dims[0] = new_interval(1.0, 2.0);
+        rect.dims[2] = new_interval(0.0, 3.0);
+        let bloated = fp.bloat(&rect, Some(2));
+        assert_eq!(bloated.dims[0].min, 1.0 - 0.16);
+        assert_eq!(bloated.dims[1].max, 0.16);
+        assert_eq!(bloated.dims[2].max, 3.0);
+    }
+}
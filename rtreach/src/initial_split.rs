@@ -0,0 +1,266 @@
+// Splitting of the initial set. The reach box of a wide initial set suffers from the wrapping
+// effect, it grows faster than the states reached from it and can be declared unsafe (or
+// exceed max_rect_width_before_error) when the boxes reached from parts of it are not. The
+// initial set is bisected into sub-boxes, each gets its own reach tube and the whole is safe
+// if every sub-box is.
+
+use std::thread;
+use serde::Deserialize;
+use super::face_lift::{LiftingSettings, face_lifting_iterative_improvement};
+use super::geometry::{HyperRectangle, interval_width};
+use super::system_model::SystemModel;
+
+// Which dimension of a box to bisect
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SplitDimension {
+    Widest,
+    // the dimension whose bisection narrows the derivative bounds of the halves the most
+    Sensitive,
+}
+
+impl SplitDimension {
+    pub fn from_name(name: &str) -> Option<SplitDimension> {
+        match name {
+            "widest" => Some(SplitDimension::Widest),
+            "sensitive" => Some(SplitDimension::Sensitive),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct InitialSetSplit {
+    pub dimension: SplitDimension,
+    pub depth: u32,         // number of bisections, up to 2^depth sub-boxes
+    #[serde(default)]
+    pub parallel: bool,     // sub-boxes spread over up to one thread per core
+}
+
+// How each sub-box is run, as in face_lifting_iterative_improvement
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct SubBoxRun {
+    pub store_rect: bool,
+    pub fixed_step: bool,
+    pub dynamic_control: bool,
+}
+
+// The two halves of rect along dim
+pub fn bisect<const NUM_DIMS: usize>(rect: &HyperRectangle<NUM_DIMS>, dim: usize) -> (HyperRectangle<NUM_DIMS>, HyperRectangle<NUM_DIMS>) {
+    let mid = (rect.dims[dim].min + rect.dims[dim].max) / 2.0;
+    let mut lower = *rect;
+    let mut upper = *rect;
+    lower.dims[dim].max = mid;
+    upper.dims[dim].min = mid;
+    (lower, upper)
+}
+
+// Dimension of rect to bisect, None if rect is a point
+pub fn split_dimension<const NUM_DIMS: usize, const NUM_CTRLS: usize, T: SystemModel<NUM_DIMS, NUM_CTRLS>>(
+    system_model: &T,
+    rect: &HyperRectangle<NUM_DIMS>,
+    ctrl_input: &[f64; NUM_CTRLS],
+    dimension: SplitDimension,
+) -> Option<usize> {
+    let widths: [f64; NUM_DIMS] = std::array::from_fn(|d| interval_width(&rect.dims[d]));
+    let mut rv: Option<usize> = None;
+    let mut best: f64 = f64::MAX;
+    for d in (0..NUM_DIMS).filter(|d| widths[*d] > 0.0) {
+        let score = match dimension {
+            SplitDimension::Widest => -widths[d],
+            SplitDimension::Sensitive => {
+                // total width of the derivative bounds over the wider half
+                let (lower, upper) = bisect(rect, d);
+                [lower, upper]
+                    .iter()
+                    .map(|half| {
+                        system_model
+                            .get_face_derivative_bounds(half, half, &[[0.0; 2]; NUM_DIMS], ctrl_input)
+                            .iter()
+                            .map(interval_width)
+                            .sum::<f64>()
+                    })
+                    .fold(f64::MIN, f64::max)
+            }
+        };
+        if score < best {
+            best = score;
+            rv = Some(d);
+        }
+    }
+    rv
+}
+
+// Sub-boxes of rect after depth rounds of bisection, points are not split further
+pub fn split_initial_set<const NUM_DIMS: usize, const NUM_CTRLS: usize, T: SystemModel<NUM_DIMS, NUM_CTRLS>>(
+    system_model: &T,
+    rect: &HyperRectangle<NUM_DIMS>,
+    ctrl_input: &[f64; NUM_CTRLS],
+    split: &InitialSetSplit,
+) -> Vec<HyperRectangle<NUM_DIMS>> {
+    let mut boxes = vec![*rect];
+    for _ in 0..split.depth {
+        boxes = boxes
+            .iter()
+            .flat_map(|b| match split_dimension(system_model, b, ctrl_input, split.dimension) {
+                Some(d) => {
+                    let (lower, upper) = bisect(b, d);
+                    vec![lower, upper]
+                }
+                None => vec![*b],
+            })
+            .collect();
+    }
+    boxes
+}
+
+// face_lifting_iterative_improvement from each sub-box of settings.init, safe if all of them
+// are. The sub-boxes are run one after the other, or in parallel spread evenly over at most
+// one thread per core. The sub-boxes of a thread share the deadline and it stops at the
+// first unsafe one. The rects of every sub-box that was run, each starting with its initial
+// box, are appended to storage_vec. ITERATIONS_AT_QUIT is that of the last sub-box to finish.
+// The callbacks of the settings run concurrently when parallel.
+pub fn face_lifting_split<const NUM_DIMS: usize, const NUM_CTRLS: usize, T: SystemModel<NUM_DIMS, NUM_CTRLS> + Sync>(
+    system_model: &T,
+    start_ms: u64,
+    settings: &LiftingSettings<NUM_DIMS>,
+    initial_ctrl_input: &[f64; NUM_CTRLS],
+    split: &InitialSetSplit,
+    run: &SubBoxRun,
+    storage_vec: &mut Vec<(f64, HyperRectangle<NUM_DIMS>)>,
+) -> bool {
+    let boxes = split_initial_set(system_model, &settings.init, initial_ctrl_input, split);
+
+    let threads = if split.parallel {
+        let cores = thread::available_parallelism().map_or(1, |n| n.get());
+        boxes.len().min(cores)
+    } else {
+        1
+    };
+    let per_thread = boxes.len().div_ceil(threads.max(1)).max(1);
+    // 0 is no deadline, a share must not become that
+    let max_runtime_us = if settings.max_runtime_us > 0 {
+        (settings.max_runtime_us / per_thread as u64).max(1)
+    } else {
+        0
+    };
+
+    let run_boxes = |boxes: &[HyperRectangle<NUM_DIMS>]| {
+        let mut results: Vec<(bool, Vec<(f64, HyperRectangle<NUM_DIMS>)>)> = Vec::new();
+        for init in boxes.iter() {
+            let mut sub_settings = *settings;
+            sub_settings.init = *init;
+            sub_settings.max_runtime_us = max_runtime_us;
+            let mut sub_storage_vec: Vec<(f64, HyperRectangle<NUM_DIMS>)> = vec![(0.0, *init)];
+            let safe = face_lifting_iterative_improvement(system_model, start_ms, &mut sub_settings, initial_ctrl_input, run.store_rect, &mut sub_storage_vec, run.fixed_step, run.dynamic_control);
+            results.push((safe, sub_storage_vec));
+            if !safe {
+                break;
+            }
+        }
+        results
+    };
+
+    let results: Vec<(bool, Vec<(f64, HyperRectangle<NUM_DIMS>)>)> = if threads > 1 {
+        thread::scope(|s| {
+            let handles: Vec<_> = boxes.chunks(per_thread).map(|chunk| s.spawn(|| run_boxes(chunk))).collect();
+            handles.into_iter().flat_map(|h| h.join().unwrap()).collect()
+        })
+    } else {
+        run_boxes(&boxes)
+    };
+
+    let mut rv = true;
+    for (safe, sub_storage_vec) in results {
+        rv = rv && safe;
+        storage_vec.extend(sub_storage_vec);
+    }
+    rv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interval::new_interval;
+
+    // x' = y, y' = -x
+    struct Rotation;
+
+    impl SystemModel<2, 0> for Rotation {
+        fn get_derivative_bounds(&self, rect: &HyperRectangle<2>, face_index: usize, _: &[f64; 0]) -> f64 {
+            let der = if face_index / 2 == 0 { rect.dims[1] } else { new_interval(-rect.dims[0].max, -rect.dims[0].min) };
+            if face_index.is_multiple_of(2) { der.min } else { der.max }
+        }
+
+        fn sample_control(&self, _: &HyperRectangle<2>) -> [f64; 0] {
+            []
+        }
+    }
+
+    // x' = y, y' = 0
+    struct Shear;
+
+    impl SystemModel<2, 0> for Shear {
+        fn get_derivative_bounds(&self, rect: &HyperRectangle<2>, face_index: usize, _: &[f64; 0]) -> f64 {
+            let der = if face_index / 2 == 0 { rect.dims[1] } else { new_interval(0.0, 0.0) };
+            if face_index.is_multiple_of(2) { der.min } else { der.max }
+        }
+
+        fn sample_control(&self, _: &HyperRectangle<2>) -> [f64; 0] {
+            []
+        }
+    }
+
+    fn rotation_settings() -> LiftingSettings<2> {
+        LiftingSettings::<2> {
+            init: HyperRectangle { dims: [new_interval(-1.0, 1.0), new_interval(-1.0, 1.0)] },
+            reach_time: 0.8,
+            initial_step_size: 0.01,
+            max_rect_width_before_error: 2.6,
+            max_runtime_us: 0,
+            obstacle_sim_fn: |_, _| {},
+            reached_at_intermediate_time: None,
+            reached_at_final_time: None,
+            restarted_computation: None,
+        }
+    }
+
+    #[test]
+    fn test_split_dimension() {
+        let rect = HyperRectangle { dims: [new_interval(0.0, 10.0), new_interval(0.0, 1.0)] };
+        assert_eq!(split_dimension(&Shear, &rect, &[], SplitDimension::Widest), Some(0));
+        // no derivative depends on x, only halving y narrows them
+        assert_eq!(split_dimension(&Shear, &rect, &[], SplitDimension::Sensitive), Some(1));
+        let point = HyperRectangle { dims: [new_interval(1.0, 1.0), new_interval(2.0, 2.0)] };
+        assert_eq!(split_dimension(&Rotation, &point, &[], SplitDimension::Widest), None);
+
+        let split = InitialSetSplit { dimension: SplitDimension::Widest, depth: 2, parallel: false };
+        let boxes = split_initial_set(&Rotation, &rotation_settings().init, &[], &split);
+        assert_eq!(boxes.len(), 4);
+        for b in boxes.iter() {
+            assert_eq!(interval_width(&b.dims[0]), 1.0);
+            assert_eq!(interval_width(&b.dims[1]), 1.0);
+        }
+        assert_eq!(split_initial_set(&Rotation, &point, &[], &split).len(), 1);
+    }
+
+    #[test]
+    fn test_face_lifting_split() {
+        // the rotated square does not fit in the width limit, its halves do
+        let mut settings = rotation_settings();
+        let mut storage_vec = vec![];
+        assert!(!face_lifting_iterative_improvement(&Rotation, 0, &mut settings, &[], false, &mut storage_vec, true, false));
+
+        let run = SubBoxRun { store_rect: false, fixed_step: true, dynamic_control: false };
+        for parallel in [false, true] {
+            let split = InitialSetSplit { dimension: SplitDimension::Widest, depth: 0, parallel };
+            assert!(!face_lifting_split(&Rotation, 0, &settings, &[], &split, &run, &mut storage_vec));
+            let split = InitialSetSplit { dimension: SplitDimension::Widest, depth: 2, parallel };
+            storage_vec.clear();
+            assert!(face_lifting_split(&Rotation, 0, &settings, &[], &split, &run, &mut storage_vec));
+            // the initial box of each sub-box
+            assert_eq!(storage_vec.len(), 4);
+        }
+    }
+}
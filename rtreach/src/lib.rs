@@ -2,6 +2,22 @@ pub mod geometry;
 pub mod interval;
 pub mod system_model;
 pub mod face_lift;
+pub mod reach_avoid;
+pub mod stl;
 pub mod util;
+pub mod obstacle;
+pub mod footprint;
+pub mod occupancy_grid;
 pub mod obstacle_safety;
+pub mod spatial_index;
+pub mod subgoal;
+pub mod control_search;
+pub mod vehicle;
+pub mod controller;
+pub mod initial_split;
+pub mod backward;
+pub mod safe_zones;
+pub mod multi_agent;
+pub mod scenario;
+pub mod wall_segments;
 pub mod debug;
@@ -0,0 +1,171 @@
+// Reach tubes of other agents as dynamic obstacles. Agents simulated together take turns
+// computing their reach tubes, each avoiding the latest tubes of the others. These take the
+// place of the first DYNAMIC_OBSTACLE_COUNT obstacles of the environment, one per other
+// agent, and agent_tubes_sim_fn as obstacle_sim_fn moves them along the tubes.
+
+use lazy_static::lazy_static;
+use std::sync::Mutex;
+use super::geometry::HyperRectangle;
+use super::interval::new_interval;
+use super::obstacle::Obstacle;
+use super::obstacle_safety::{DYNAMIC_OBSTACLE_COUNT, OBSTACLES};
+
+// slack on the end of a tube, its times are sums of many step sizes
+const TIME_TOLERANCE: f64 = 1e-9;
+
+// The space an agent may occupy over time, empty for an agent that left
+#[derive(Clone, Default)]
+pub struct AgentTube {
+    pub start_time: f64,                    // simulation time the tube was computed at
+    pub obstacles: Vec<(f64, Obstacle)>,    // each covers the agent from its time after start_time to the next
+    pub end: f64,                           // time after start_time the tube covers the agent until
+}
+
+impl AgentTube {
+    // Tube of the rects stored by face lifting, each over-approximating the states from its
+    // time on, covered by to_obstacle, e.g. the footprint of the agent over the rect. It ends
+    // at the time of the last rect.
+    pub fn from_rects<const NUM_DIMS: usize>(start_time: f64, rects: &[(f64, HyperRectangle<NUM_DIMS>)], to_obstacle: fn(&HyperRectangle<NUM_DIMS>) -> Obstacle) -> AgentTube {
+        AgentTube {
+            start_time,
+            obstacles: rects.iter().map(|(t, r)| (*t, to_obstacle(r))).collect(),
+            end: rects.last().map_or(0.0, |(t, _)| *t),
+        }
+    }
+
+    // An agent standing in obstacle from start_time on
+    pub fn parked(start_time: f64, obstacle: Obstacle) -> AgentTube {
+        AgentTube {
+            start_time,
+            obstacles: vec![(0.0, obstacle)],
+            end: f64::INFINITY,
+        }
+    }
+
+    // Obstacle covering the agent over the simulation times [from, to], the union of the
+    // obstacles of the tube over that span as their bounding box. Before the tube starts it
+    // is the first obstacle, after it ends the agent can be anywhere. None if the tube is empty.
+    pub fn during(&self, from: f64, to: f64) -> Option<Obstacle> {
+        if self.obstacles.is_empty() {
+            return None;
+        }
+        if to - self.start_time > self.end + TIME_TOLERANCE {
+            return Some(everywhere());
+        }
+        let index_at = |time: f64| self.obstacles.partition_point(|(t, _)| *t <= time - self.start_time).saturating_sub(1);
+        let covering = &self.obstacles[index_at(from)..=index_at(to)];
+        if let [(_, obstacle)] = covering {
+            return Some(obstacle.clone());
+        }
+        let [mut x, mut y] = covering[0].1.bounding_box();
+        for (_, obstacle) in covering[1..].iter() {
+            let [ox, oy] = obstacle.bounding_box();
+            x = new_interval(x.min.min(ox.min), x.max.max(ox.max));
+            y = new_interval(y.min.min(oy.min), y.max.max(oy.max));
+        }
+        Some(Obstacle::Rect { x, y })
+    }
+}
+
+// intersects every box
+fn everywhere() -> Obstacle {
+    Obstacle::Rect { x: new_interval(f64::MIN, f64::MAX), y: new_interval(f64::MIN, f64::MAX) }
+}
+
+// The tubes avoided by the agent computing its reach tube from the current simulation time.
+// The hull the reach computation checks at time t spans its step, [t, t + step] capped at the
+// horizon, the other agents are only avoided up to the horizon.
+#[derive(Default)]
+pub struct AgentTubes {
+    pub now: f64,
+    pub step: f64,          // initial step size of the reach computation, no step is longer
+    pub horizon: f64,
+    pub tubes: Vec<AgentTube>,
+}
+
+lazy_static! {
+    pub static ref AGENT_TUBES: Mutex<AgentTubes> = Mutex::new(AgentTubes::default());
+}
+
+// Set the tubes of the other agents before an agent computes its reach tube. Also moves the
+// agent slots of the obstacles, the first DYNAMIC_OBSTACLE_COUNT, to where the tubes are over
+// the first step, so that has_collided sees the other agents.
+pub fn set_agent_tubes(tubes: AgentTubes) {
+    let mut agent_tubes = AGENT_TUBES.lock().unwrap();
+    *agent_tubes = tubes;
+
+    let dyn_obs_ct = *DYNAMIC_OBSTACLE_COUNT.lock().unwrap();
+    let mut obstacles_lock = OBSTACLES.lock().unwrap();
+    if let Some(obstacles) = obstacles_lock.as_mut() {
+        agent_tubes_at(&agent_tubes, 0.0, &mut obstacles[..dyn_obs_ct as usize]);
+    }
+}
+
+// obstacle_sim_fn that puts the other agents over the step at time t of the reach computation
+// into the dynamic obstacles
+pub fn agent_tubes_sim_fn(t: f64, obs: &mut Vec<Obstacle>) {
+    let agent_tubes = AGENT_TUBES.lock().unwrap();
+    agent_tubes_at(&agent_tubes, t, obs);
+}
+
+fn agent_tubes_at(agent_tubes: &AgentTubes, t: f64, obs: &mut [Obstacle]) {
+    let from = agent_tubes.now + t;
+    let to = agent_tubes.now + (t + agent_tubes.step).min(agent_tubes.horizon);
+    for (o, tube) in obs.iter_mut().zip(agent_tubes.tubes.iter()) {
+        let during = if t <= agent_tubes.horizon { tube.during(from, to) } else { None };
+        // an empty polygon intersects nothing
+        *o = during.unwrap_or(Obstacle::Polygon { vertices: vec![] });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interval::new_interval;
+
+    fn to_rect(r: &HyperRectangle<2>) -> Obstacle {
+        Obstacle::Rect { x: r.dims[0], y: r.dims[1] }
+    }
+
+    fn x_of(o: Option<&Obstacle>) -> (f64, f64) {
+        let [x, _] = o.unwrap().bounding_box();
+        (x.min, x.max)
+    }
+
+    #[test]
+    fn test_agent_tube() {
+        let rects: Vec<(f64, HyperRectangle<2>)> = (0..4)
+            .map(|i| (0.5 * i as f64, HyperRectangle { dims: [new_interval(i as f64, i as f64 + 1.0), new_interval(0.0, 1.0)] }))
+            .collect();
+        let tube = AgentTube::from_rects(10.0, &rects, to_rect);
+        assert_eq!(x_of(tube.during(9.0, 9.0).as_ref()), (0.0, 1.0));
+        assert_eq!(x_of(tube.during(10.0, 10.0).as_ref()), (0.0, 1.0));
+        assert_eq!(x_of(tube.during(10.7, 10.7).as_ref()), (1.0, 2.0));
+        // every obstacle over the span
+        assert_eq!(x_of(tube.during(10.2, 10.7).as_ref()), (0.0, 2.0));
+        assert_eq!(x_of(tube.during(11.2, 11.5).as_ref()), (2.0, 4.0));
+        // past the end the agent can be anywhere
+        let anywhere = tube.during(11.2, 11.6).unwrap();
+        assert!(anywhere.intersects_box(new_interval(100.0, 101.0), new_interval(-50.0, -49.0), None));
+        assert!(AgentTube::default().during(0.0, 1.0).is_none());
+
+        let parked = AgentTube::parked(0.0, Obstacle::rect_centered([5.0, 0.0], 1.0, 1.0));
+        assert_eq!(x_of(parked.during(3.0, 100.0).as_ref()), (4.5, 5.5));
+
+        // the sim fn follows the tube over the step from the current time, up to the horizon
+        let mut obs = vec![Obstacle::rect_centered([0.0, 0.0], 0.1, 0.1); 3];
+        let agent_tubes = AgentTubes { now: 10.5, step: 0.5, horizon: 1.0, tubes: vec![tube, parked, AgentTube::default()] };
+        agent_tubes_at(&agent_tubes, 0.5, &mut obs);
+        assert_eq!(x_of(Some(&obs[0])), (2.0, 4.0));
+        assert_eq!(x_of(Some(&obs[1])), (4.5, 5.5));
+        assert!(!obs[2].intersects_box(new_interval(-1.0, 1.0), new_interval(-1.0, 1.0), None));
+        agent_tubes_at(&agent_tubes, 0.8, &mut obs);
+        assert_eq!(x_of(Some(&obs[0])), (2.0, 4.0));
+        agent_tubes_at(&agent_tubes, 1.2, &mut obs);
+        assert!(!obs[0].intersects_box(new_interval(-10.0, 10.0), new_interval(-10.0, 10.0), None));
+
+        let later = AgentTubes { now: 11.0, ..agent_tubes };
+        agent_tubes_at(&later, 0.5, &mut obs);
+        assert!(obs[0].intersects_box(new_interval(100.0, 101.0), new_interval(0.0, 1.0), None));
+    }
+}
@@ -0,0 +1,292 @@
+use super::geometry::Interval;
+use super::interval::new_interval;
+use super::util::distance_2d;
+
+// Obstacle geometry used by the safety checks. Every shape lives in the x/y plane,
+// the cuboid additionally has a z extent for vehicles that track their altitude.
+// Touching an obstacle is not an intersection, only a positive overlap is, which
+// matches the original box check in obstacle_safety.
+#[derive(Clone)]
+pub enum Obstacle {
+    // axis-aligned box of arbitrary size
+    Rect { x: Interval, y: Interval },
+    Circle { center: [f64; 2], radius: f64 },
+    // convex polygon, the vertices may be given in either winding order
+    Polygon { vertices: Vec<[f64; 2]> },
+    // axis-aligned 3D box
+    Cuboid { x: Interval, y: Interval, z: Interval },
+}
+
+impl Obstacle {
+    // box of size width x height centered on a point
+    pub fn rect_centered(center: [f64; 2], width: f64, height: f64) -> Obstacle {
+        Obstacle::Rect {
+            x: new_interval(center[0] - width / 2.0, center[0] + width / 2.0),
+            y: new_interval(center[1] - height / 2.0, center[1] + height / 2.0),
+        }
+    }
+
+    // smallest axis-aligned box in the x/y plane containing the obstacle
+    pub fn bounding_box(&self) -> [Interval; 2] {
+        match self {
+            Obstacle::Rect { x, y } | Obstacle::Cuboid { x, y, .. } => [*x, *y],
+            Obstacle::Circle { center, radius } => [
+                new_interval(center[0] - radius, center[0] + radius),
+                new_interval(center[1] - radius, center[1] + radius),
+            ],
+            Obstacle::Polygon { vertices } => [project(vertices, [1.0, 0.0]), project(vertices, [0.0, 1.0])],
+        }
+    }
+
+    // circle enclosing the obstacle in the x/y plane as (center, radius)
+    pub fn circumscribed_circle(&self) -> ([f64; 2], f64) {
+        match self {
+            Obstacle::Circle { center, radius } => (*center, *radius),
+            Obstacle::Polygon { vertices } => {
+                let mut center = [0.0; 2];
+                for v in vertices {
+                    center[0] += v[0] / vertices.len() as f64;
+                    center[1] += v[1] / vertices.len() as f64;
+                }
+                let radius = vertices.iter().map(|v| distance_2d(&center, v)).fold(0.0, f64::max);
+                (center, radius)
+            }
+            _ => {
+                let [x, y] = self.bounding_box();
+                let center = [(x.min + x.max) / 2.0, (y.min + y.max) / 2.0];
+                (center, distance_2d(&center, &[x.min, y.min]))
+            }
+        }
+    }
+
+    // move the obstacle in the x/y plane
+    pub fn translate(&mut self, dx: f64, dy: f64) {
+        match self {
+            Obstacle::Rect { x, y } | Obstacle::Cuboid { x, y, .. } => {
+                x.min += dx;
+                x.max += dx;
+                y.min += dy;
+                y.max += dy;
+            }
+            Obstacle::Circle { center, .. } => {
+                center[0] += dx;
+                center[1] += dy;
+            }
+            Obstacle::Polygon { vertices } => {
+                for v in vertices.iter_mut() {
+                    v[0] += dx;
+                    v[1] += dy;
+                }
+            }
+        }
+    }
+
+    // Does the box x * y (* z) overlap the obstacle?
+    // Without a z interval the cuboid is treated as infinitely tall.
+    pub fn intersects_box(&self, x: Interval, y: Interval, z: Option<Interval>) -> bool {
+        match self {
+            Obstacle::Rect { x: ox, y: oy } => intervals_overlap(x, *ox) && intervals_overlap(y, *oy),
+            Obstacle::Cuboid { x: ox, y: oy, z: oz } => {
+                intervals_overlap(x, *ox)
+                    && intervals_overlap(y, *oy)
+                    && z.is_none_or(|z| intervals_overlap(z, *oz))
+            }
+            Obstacle::Circle { center, radius } => {
+                // closest point of the box to the circle center
+                let cx = center[0].clamp(x.min, x.max);
+                let cy = center[1].clamp(y.min, y.max);
+                distance_2d(center, &[cx, cy]) < *radius
+            }
+            Obstacle::Polygon { vertices } => polygon_intersects_box(vertices, x, y),
+        }
+    }
+
+    // Signed distance between the obstacle and the box x * y (* z): the gap between them if
+    // they are apart, minus the penetration depth if they overlap. Without a z interval the
+    // distance is in the x/y plane and the cuboid is treated as infinitely tall.
+    // Exact for boxes and circles, for polygons it is the largest gap over the separating
+    // axes, which can underestimate the gap but never overestimates it.
+    pub fn signed_distance_to_box(&self, x: Interval, y: Interval, z: Option<Interval>) -> f64 {
+        match self {
+            Obstacle::Rect { x: ox, y: oy } => box_gap(&[interval_gap(x, *ox), interval_gap(y, *oy)]),
+            Obstacle::Cuboid { x: ox, y: oy, z: oz } => match z {
+                Some(z) => box_gap(&[interval_gap(x, *ox), interval_gap(y, *oy), interval_gap(z, *oz)]),
+                None => box_gap(&[interval_gap(x, *ox), interval_gap(y, *oy)]),
+            },
+            Obstacle::Circle { center, radius } => {
+                let cx = center[0].clamp(x.min, x.max);
+                let cy = center[1].clamp(y.min, y.max);
+                let d = distance_2d(center, &[cx, cy]);
+                if d > 0.0 {
+                    d - radius
+                } else {
+                    // the center is inside the box, push it out through the nearest side
+                    let inside = (center[0] - x.min).min(x.max - center[0]).min(center[1] - y.min).min(y.max - center[1]);
+                    -(radius + inside)
+                }
+            }
+            Obstacle::Polygon { vertices } => polygon_box_gap(vertices, x, y),
+        }
+    }
+}
+
+// signed distance of two boxes from the gaps along each axis
+fn box_gap(gaps: &[f64]) -> f64 {
+    if gaps.iter().all(|g| *g <= 0.0) {
+        return gaps.iter().fold(f64::NEG_INFINITY, |a, g| a.max(*g));
+    }
+    gaps.iter().filter(|g| **g > 0.0).map(|g| g * g).sum::<f64>().sqrt()
+}
+
+fn intervals_overlap(a: Interval, b: Interval) -> bool {
+    a.min < b.max && b.min < a.max
+}
+
+// separating axis test between a convex polygon and an axis-aligned box
+fn polygon_intersects_box(vertices: &[[f64; 2]], x: Interval, y: Interval) -> bool {
+    if vertices.is_empty() {
+        return false;
+    }
+
+    // the box axes
+    if !intervals_overlap(x, project(vertices, [1.0, 0.0])) || !intervals_overlap(y, project(vertices, [0.0, 1.0])) {
+        return false;
+    }
+
+    // the polygon edge normals
+    let corners = [[x.min, y.min], [x.max, y.min], [x.max, y.max], [x.min, y.max]];
+    for i in 0..vertices.len() {
+        let a = vertices[i];
+        let b = vertices[(i + 1) % vertices.len()];
+        let normal = [a[1] - b[1], b[0] - a[0]];
+        if normal[0] == 0.0 && normal[1] == 0.0 {
+            continue;
+        }
+        let poly = project(vertices, normal);
+        let rect = project(&corners, normal);
+        if !intervals_overlap(poly, rect) {
+            return false;
+        }
+    }
+
+    true
+}
+
+// gap between two intervals, negative if they overlap
+fn interval_gap(a: Interval, b: Interval) -> f64 {
+    (b.min - a.max).max(a.min - b.max)
+}
+
+// largest gap over the separating axes between a convex polygon and an axis-aligned box
+fn polygon_box_gap(vertices: &[[f64; 2]], x: Interval, y: Interval) -> f64 {
+    if vertices.is_empty() {
+        return f64::INFINITY;
+    }
+
+    let mut gap = interval_gap(x, project(vertices, [1.0, 0.0])).max(interval_gap(y, project(vertices, [0.0, 1.0])));
+    let corners = [[x.min, y.min], [x.max, y.min], [x.max, y.max], [x.min, y.max]];
+    for i in 0..vertices.len() {
+        let a = vertices[i];
+        let b = vertices[(i + 1) % vertices.len()];
+        let len = distance_2d(&a, &b);
+        if len == 0.0 {
+            continue;
+        }
+        let normal = [(a[1] - b[1]) / len, (b[0] - a[0]) / len];
+        gap = gap.max(interval_gap(project(&corners, normal), project(vertices, normal)));
+    }
+    gap
+}
+
+fn project(points: &[[f64; 2]], axis: [f64; 2]) -> Interval {
+    let mut rv = new_interval(f64::MAX, f64::MIN);
+    for p in points {
+        let d = p[0] * axis[0] + p[1] * axis[1];
+        rv.min = rv.min.min(d);
+        rv.max = rv.max.max(d);
+    }
+    rv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rect_intersects_box() {
+        let obs = Obstacle::rect_centered([2.0, 0.0], 0.5, 0.5);
+        assert!(obs.intersects_box(new_interval(1.5, 1.8), new_interval(-0.1, 0.1), None));
+        assert!(!obs.intersects_box(new_interval(0.0, 1.75), new_interval(-0.1, 0.1), None));
+        assert!(!obs.intersects_box(new_interval(1.5, 1.8), new_interval(0.25, 1.0), None));
+    }
+
+    #[test]
+    fn test_circle_intersects_box() {
+        let obs = Obstacle::Circle { center: [0.0, 0.0], radius: 1.0 };
+        assert!(obs.intersects_box(new_interval(0.5, 2.0), new_interval(0.5, 2.0), None));
+        // the corner of the box is outside of the circle
+        assert!(!obs.intersects_box(new_interval(0.75, 2.0), new_interval(0.75, 2.0), None));
+        assert!(obs.intersects_box(new_interval(-0.1, 0.1), new_interval(-0.1, 0.1), None));
+    }
+
+    #[test]
+    fn test_polygon_intersects_box() {
+        // triangle with the hypotenuse from (1, 0) to (0, 1)
+        let obs = Obstacle::Polygon { vertices: vec![[0.0, 0.0], [1.0, 0.0], [0.0, 1.0]] };
+        assert!(obs.intersects_box(new_interval(0.2, 0.4), new_interval(0.2, 0.4), None));
+        assert!(!obs.intersects_box(new_interval(0.6, 1.0), new_interval(0.6, 1.0), None));
+    }
+
+    #[test]
+    fn test_cuboid_intersects_box() {
+        let obs = Obstacle::Cuboid {
+            x: new_interval(0.0, 1.0),
+            y: new_interval(0.0, 1.0),
+            z: new_interval(-2.0, 0.0),
+        };
+        assert!(obs.intersects_box(new_interval(0.5, 0.6), new_interval(0.5, 0.6), None));
+        assert!(obs.intersects_box(new_interval(0.5, 0.6), new_interval(0.5, 0.6), Some(new_interval(-1.0, -0.5))));
+        assert!(!obs.intersects_box(new_interval(0.5, 0.6), new_interval(0.5, 0.6), Some(new_interval(-4.0, -3.0))));
+    }
+
+    #[test]
+    fn test_signed_distance_to_box() {
+        let obs = Obstacle::rect_centered([2.0, 0.0], 1.0, 1.0);
+        assert_eq!(obs.signed_distance_to_box(new_interval(0.0, 1.0), new_interval(-0.1, 0.1), None), 0.5);
+        assert_eq!(obs.signed_distance_to_box(new_interval(0.0, 1.75), new_interval(-0.1, 0.1), None), -0.25);
+        assert!((obs.signed_distance_to_box(new_interval(0.0, 1.0), new_interval(1.5, 2.0), None) - 1.25f64.sqrt()).abs() < 1e-12);
+
+        let obs = Obstacle::Circle { center: [0.0, 0.0], radius: 1.0 };
+        assert!((obs.signed_distance_to_box(new_interval(2.0, 3.0), new_interval(-1.0, 1.0), None) - 1.0).abs() < 1e-12);
+        assert!((obs.signed_distance_to_box(new_interval(-0.2, 0.2), new_interval(-0.5, 0.5), None) + 1.2).abs() < 1e-12);
+
+        // the gap to the hypotenuse of the triangle
+        let obs = Obstacle::Polygon { vertices: vec![[0.0, 0.0], [1.0, 0.0], [0.0, 1.0]] };
+        assert!((obs.signed_distance_to_box(new_interval(1.0, 2.0), new_interval(1.0, 2.0), None) - 0.5f64.sqrt()).abs() < 1e-12);
+        assert!(obs.signed_distance_to_box(new_interval(0.2, 0.4), new_interval(0.2, 0.4), None) < 0.0);
+
+        // over the cuboid, or beside and above it
+        let obs = Obstacle::Cuboid { x: new_interval(0.0, 1.0), y: new_interval(0.0, 1.0), z: new_interval(0.0, 2.0) };
+        assert_eq!(obs.signed_distance_to_box(new_interval(0.2, 0.4), new_interval(0.2, 0.4), None), -0.4);
+        assert_eq!(obs.signed_distance_to_box(new_interval(0.2, 0.4), new_interval(0.2, 0.4), Some(new_interval(2.5, 3.0))), 0.5);
+        assert!((obs.signed_distance_to_box(new_interval(1.3, 2.0), new_interval(0.2, 0.4), Some(new_interval(2.4, 3.0))) - 0.5).abs() < 1e-12);
+        // penetrating the top
+        assert!((obs.signed_distance_to_box(new_interval(0.2, 0.4), new_interval(0.2, 0.4), Some(new_interval(1.9, 3.0))) + 0.1).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_circumscribed_circle() {
+        let (center, radius) = Obstacle::rect_centered([1.0, 1.0], 2.0, 2.0).circumscribed_circle();
+        assert_eq!(center, [1.0, 1.0]);
+        assert!((radius - 2.0f64.sqrt()).abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_translate() {
+        let mut obs = Obstacle::rect_centered([0.0, 0.0], 1.0, 1.0);
+        obs.translate(1.0, -1.0);
+        let [x, y] = obs.bounding_box();
+        assert_eq!(x.min, 0.5);
+        assert_eq!(y.max, -0.5);
+    }
+}
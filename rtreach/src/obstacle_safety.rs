@@ -3,20 +3,38 @@ use std::fs::File;
 use std::io::{self, BufRead, BufReader};
 use csv::ReaderBuilder;
 use lazy_static::lazy_static;
-use super::geometry::HyperRectangle;
+use super::geometry::{HyperRectangle, Interval};
+use super::interval::{new_interval, new_interval_v};
+use super::obstacle::Obstacle;
+use super::occupancy_grid::{load_map, OccupancyGrid};
+use super::spatial_index::GridIndex;
+use super::wall_segments::{point_box_distance, point_segment_distance, segment_bounding_box, segment_box_distance, segment_intersects_box, WallSegments};
 use super::util::distance_2d;
 use super::debug::DEBUG;
 
 // Define global variables using lazy_static
 lazy_static! {
     pub static ref WALL_COORDS: Mutex<Option<Vec<Vec<f64>>>> = Mutex::new(None);
-    pub static ref OBSTACLES: Mutex<Option<Vec<Vec<Vec<f64>>>>> = Mutex::new(None);
+    pub static ref OBSTACLES: Mutex<Option<Vec<Obstacle>>> = Mutex::new(None);
     pub static ref DYNAMIC_OBSTACLE_COUNT: Mutex<u32> = Mutex::new(0);
     static ref FILE_ROWS: Mutex<u32> = Mutex::new(0);
     static ref FILE_COLUMNS: Mutex<u32> = Mutex::new(2);
     pub static ref OBSTACLE_COUNT: Mutex<u32> = Mutex::new(0);
+    // grid indices over the wall points and the obstacles, rebuilt by load_wallpoints and set_obstacles
+    pub static ref WALL_INDEX: Mutex<Option<GridIndex>> = Mutex::new(None);
+    pub static ref OBSTACLE_INDEX: Mutex<Option<GridIndex>> = Mutex::new(None);
+    // disable to fall back to the linear scans, e.g. for benchmarking
+    pub static ref SPATIAL_INDEX_ENABLED: Mutex<bool> = Mutex::new(true);
+    // map_server map of the walls, when loaded it replaces the wall points
+    pub static ref OCCUPANCY_GRID: Mutex<Option<OccupancyGrid>> = Mutex::new(None);
+    // walls as line segments, checked together with the wall points
+    pub static ref WALL_SEGMENTS: Mutex<Option<WallSegments>> = Mutex::new(None);
+    pub static ref WALL_SEGMENT_INDEX: Mutex<Option<GridIndex>> = Mutex::new(None);
 }
 
+// cell size of the spatial indices in meters
+pub const INDEX_CELL_SIZE: f64 = 0.5;
+
 fn count_lines(filename: &str) -> io::Result<usize> {
     // Open the file
     let file = File::open(filename);
@@ -59,6 +77,17 @@ pub fn load_wallpoints(filename: &str, print: bool){
                     .collect();
                 wall_coords.push(coords);
             }
+            {
+                // malformed rows get NaN bounds so they are skipped but keep the ids aligned
+                let bounds: Vec<_> = wall_coords.iter()
+                    .map(|c| match c[..] {
+                        [x, y, ..] => [new_interval_v(x), new_interval_v(y)],
+                        _ => [new_interval_v(f64::NAN), new_interval_v(f64::NAN)],
+                    })
+                    .collect();
+                let mut wall_index = WALL_INDEX.lock().unwrap();
+                *wall_index = Some(GridIndex::new(&bounds, INDEX_CELL_SIZE));
+            }
             {
                 let mut wall_coords_lock: std::sync::MutexGuard<'_, Option<Vec<Vec<f64>>>> = WALL_COORDS.lock().unwrap();
                 *wall_coords_lock = Some(wall_coords);
@@ -70,6 +99,61 @@ pub fn load_wallpoints(filename: &str, print: bool){
     }
 }
 
+// load the walls from a map_server YAML header and its PGM/PNG image
+pub fn load_occupancy_grid(filename: &str, print: bool){
+    match load_map(filename) {
+        Ok(grid) => {
+            if print{
+                println!("Loaded map {} with {} x {} cells of {} m", filename, grid.width, grid.height, grid.resolution);
+            }
+            let mut grid_lock = OCCUPANCY_GRID.lock().unwrap();
+            *grid_lock = Some(grid);
+        },
+        Err(e) => {
+            eprintln!("Could not load map {}: {}", filename, e);
+        }
+    }
+}
+
+pub fn set_wall_segments(walls: WallSegments){
+    {
+        let bounds: Vec<_> = walls.segments.iter().map(segment_bounding_box).collect();
+        let mut segment_index = WALL_SEGMENT_INDEX.lock().unwrap();
+        *segment_index = Some(GridIndex::new(&bounds, INDEX_CELL_SIZE));
+    }
+    {
+        let mut segments_lock = WALL_SEGMENTS.lock().unwrap();
+        *segments_lock = Some(walls);
+    }
+}
+
+pub fn check_safety_wall_segments<const NUM_DIMS: usize>(rect: &HyperRectangle<NUM_DIMS>) -> bool {
+    let segments_guard = WALL_SEGMENTS.lock().unwrap();
+    let index_guard = WALL_SEGMENT_INDEX.lock().unwrap();
+    let enabled: bool = *SPATIAL_INDEX_ENABLED.lock().unwrap();
+
+    match &*segments_guard {
+        Some(walls) => {
+            let segments = &walls.segments;
+            // the wall points can be up to the tolerance away from the segments
+            let x = new_interval(rect.dims[0].min - walls.tolerance, rect.dims[0].max + walls.tolerance);
+            let y = new_interval(rect.dims[1].min - walls.tolerance, rect.dims[1].max + walls.tolerance);
+            let check_segment = |i: usize| -> bool {
+                let safe = !segment_intersects_box(&segments[i], x, y);
+                if !safe && DEBUG {
+                    println!("offending segment ({}, {}) - ({}, {})", segments[i][0][0], segments[i][0][1], segments[i][1][0], segments[i][1][1]);
+                }
+                safe
+            };
+            match &*index_guard {
+                Some(index) if enabled => index.visit(x, y, check_segment),
+                _ => !has_nan_position(rect) && (0..segments.len()).all(check_segment),
+            }
+        },
+        None => true,
+    }
+}
+
 pub fn check_safety<const NUM_DIMS: usize>(rect: &HyperRectangle<NUM_DIMS>, cone: &[[f64; 2]; 2]) -> bool {
     let l1: [f64; 2] = [rect.dims[0].min, rect.dims[1].max];
     let r1: [f64; 2] = [rect.dims[0].max, rect.dims[1].min];
@@ -88,23 +172,29 @@ pub fn check_safety<const NUM_DIMS: usize>(rect: &HyperRectangle<NUM_DIMS>, cone
     false
 }
 
-pub fn check_safety_obstacles<const NUM_DIMS: usize>(rect: &HyperRectangle<NUM_DIMS>, obst: &[Vec<Vec<f64>>], obstacle_count: u32) -> bool {
+pub fn check_safety_obstacles<const NUM_DIMS: usize>(rect: &HyperRectangle<NUM_DIMS>, obst: &[Obstacle], obstacle_count: u32) -> bool {
+    check_safety_obstacles_3d(rect, obst, obstacle_count, None)
+}
+
+// Same as check_safety_obstacles, with the altitude of the box taken from z_dim so that
+// cuboid obstacles can be passed over or under. Without z_dim every obstacle is infinitely tall.
+pub fn check_safety_obstacles_3d<const NUM_DIMS: usize>(rect: &HyperRectangle<NUM_DIMS>, obst: &[Obstacle], obstacle_count: u32, z_dim: Option<usize>) -> bool {
     let mut allowed: bool = true;
+    let z = z_dim.map(|d| rect.dims[d]);
+
+    // no comparison with NaN bounds finds an overlap
+    if has_nan_position(rect) || z.is_some_and(|z| z.min.is_nan() || z.max.is_nan()) {
+        return false;
+    }
 
     // let obstacle_count: u32 = *OBSTACLE_COUNT.lock().unwrap();
 
     for j in 0..obstacle_count {
-        let obs: [[f64; 2]; 2] = [
-            [obst[j as usize][0][0], obst[j as usize][0][1]],
-            [obst[j as usize][1][0], obst[j as usize][1][1]]
-        ];
-        
-        allowed = check_safety(rect, &obs);
+        allowed = !obst[j as usize].intersects_box(rect.dims[0], rect.dims[1], z);
         if !allowed {
             if DEBUG{
-                println!("offending cone [{}, {}], [{}, {}]",
-                obst[j as usize][0][0], obst[j as usize][0][1],
-                obst[j as usize][1][0], obst[j as usize][1][1]);
+                let [x, y] = obst[j as usize].bounding_box();
+                println!("offending obstacle [{}, {}], [{}, {}]", x.min, x.max, y.min, y.max);
             }
             break;
         }
@@ -113,20 +203,72 @@ pub fn check_safety_obstacles<const NUM_DIMS: usize>(rect: &HyperRectangle<NUM_D
     allowed
 }
 
+// Same as check_safety_obstacles for the obstacles first..first+obstacle_count of the environment,
+// obst must be the full OBSTACLES list. Uses OBSTACLE_INDEX, so only call it for obstacles
+// that have not moved since set_obstacles, i.e. the static ones.
+pub fn check_safety_obstacles_indexed<const NUM_DIMS: usize>(rect: &HyperRectangle<NUM_DIMS>, obst: &[Obstacle], first: u32, obstacle_count: u32) -> bool {
+    check_safety_obstacles_indexed_3d(rect, obst, first, obstacle_count, None)
+}
+
+// check_safety_obstacles_indexed with the altitude of the box taken from z_dim
+pub fn check_safety_obstacles_indexed_3d<const NUM_DIMS: usize>(rect: &HyperRectangle<NUM_DIMS>, obst: &[Obstacle], first: u32, obstacle_count: u32, z_dim: Option<usize>) -> bool {
+    let z = z_dim.map(|d| rect.dims[d]);
+    let index_guard = OBSTACLE_INDEX.lock().unwrap();
+    let enabled: bool = *SPATIAL_INDEX_ENABLED.lock().unwrap();
+
+    match &*index_guard {
+        Some(index) if enabled => {
+            let range = (first as usize)..((first + obstacle_count) as usize);
+            index.visit(rect.dims[0], rect.dims[1], |j| {
+                if !range.contains(&j) || j >= obst.len() {
+                    return true;
+                }
+                let allowed = !obst[j].intersects_box(rect.dims[0], rect.dims[1], z);
+                if !allowed && DEBUG {
+                    let [x, y] = obst[j].bounding_box();
+                    println!("offending obstacle [{}, {}], [{}, {}]", x.min, x.max, y.min, y.max);
+                }
+                allowed
+            })
+        },
+        _ => check_safety_obstacles_3d(rect, &obst[(first as usize)..], obstacle_count, z_dim),
+    }
+}
+
+// x or y bound of a diverged reach set, such a box may be anywhere and is never safe,
+// GridIndex::visit rejects it the same way
+fn has_nan_position<const NUM_DIMS: usize>(rect: &HyperRectangle<NUM_DIMS>) -> bool {
+    [rect.dims[0], rect.dims[1]].iter().any(|i| i.min.is_nan() || i.max.is_nan())
+}
+
+// Smallest signed distance between the box and the first obstacle_count obstacles,
+// negative if it overlaps one of them, infinite without obstacles.
+pub fn obstacles_clearance<const NUM_DIMS: usize>(rect: &HyperRectangle<NUM_DIMS>, obst: &[Obstacle], obstacle_count: u32) -> f64 {
+    obstacles_clearance_3d(rect, obst, obstacle_count, None)
+}
+
+// Same as obstacles_clearance, with the altitude of the box taken from z_dim as in
+// check_safety_obstacles_3d, so the distance over or under a cuboid counts
+pub fn obstacles_clearance_3d<const NUM_DIMS: usize>(rect: &HyperRectangle<NUM_DIMS>, obst: &[Obstacle], obstacle_count: u32, z_dim: Option<usize>) -> f64 {
+    let z = z_dim.map(|d| rect.dims[d]);
+    obst.iter()
+        .take(obstacle_count as usize)
+        .map(|o| o.signed_distance_to_box(rect.dims[0], rect.dims[1], z))
+        .fold(f64::INFINITY, f64::min)
+}
+
 pub fn check_safety_obstacles_circumscribe(candidate: &[f64], robot_radius: f64, cand_radius: f64) -> bool {
     let mut allowed: bool = true;
 
     let obstacle_count: u32 = *OBSTACLE_COUNT.lock().unwrap();
-    let obstacles: &Option<Vec<Vec<Vec<f64>>>> = &*OBSTACLES.lock().unwrap();
+    let obstacles: &Option<Vec<Obstacle>> = &*OBSTACLES.lock().unwrap();
 
     match obstacles {
         Some(obst) => {
             for j in 0..obstacle_count {
-                let obs_pos_x =  (obst[j as usize][0][1] + obst[j as usize][0][0]) / 2.0;
-                let obs_pos_y =  (obst[j as usize][1][1] + obst[j as usize][1][0]) / 2.0;
-                let obs_rad = distance_2d(&[obs_pos_x, obs_pos_y], &[obst[j as usize][0][0], obst[j as usize][1][0]]);
+                let (obs_pos, obs_rad) = obst[j as usize].circumscribed_circle();
                 
-                allowed = distance_2d(&[obs_pos_x, obs_pos_y], candidate) - (obs_rad + robot_radius + cand_radius) > 0.0;
+                allowed = distance_2d(&obs_pos, candidate) - (obs_rad + robot_radius + cand_radius) > 0.0;
                 if !allowed {
                     break;
                 }
@@ -141,6 +283,17 @@ pub fn check_safety_obstacles_circumscribe(candidate: &[f64], robot_radius: f64,
 }
 
 pub fn check_safety_wall<const NUM_DIMS: usize>(rect: &HyperRectangle<NUM_DIMS>) -> bool {
+    if let Some(grid) = &*OCCUPANCY_GRID.lock().unwrap() {
+        let safe = !grid.box_occupied(rect.dims[0], rect.dims[1]);
+        if !safe && DEBUG {
+            println!("offending map cell in [{}, {}], [{}, {}]", rect.dims[0].min, rect.dims[0].max, rect.dims[1].min, rect.dims[1].max);
+        }
+        return safe;
+    }
+    if !check_safety_wall_segments(rect) {
+        return false;
+    }
+
     let wall_coords_guard: &Option<Vec<Vec<f64>>> = &*WALL_COORDS.lock().unwrap();
     let file_rows: u32 = *FILE_ROWS.lock().unwrap();  // Get the value of file_rows
     
@@ -148,19 +301,33 @@ pub fn check_safety_wall<const NUM_DIMS: usize>(rect: &HyperRectangle<NUM_DIMS>)
 
     match wall_coords_guard {
         Some(wall_coords) => {
-
-            for i in 0..file_rows {
+            let check_point = |i: usize| -> bool {
                 // Access the wall coordinates
                 let point: [[f64; 2]; 2] = [
-                    [wall_coords[i as usize][0], wall_coords[i as usize][0]], // Assuming you meant `wallCoords[i][1]` for both coordinates
-                    [wall_coords[i as usize][1], wall_coords[i as usize][1]],
+                    [wall_coords[i][0], wall_coords[i][0]],
+                    [wall_coords[i][1], wall_coords[i][1]],
                 ];
                 
-                safe_val = check_safety(rect, &point);
-                if !safe_val {
-                    // Uncomment the next line to print the offending point
-                    println!("offending point ({}, {})", wall_coords[i as usize][0], wall_coords[i as usize][1]);
-                    break;
+                let safe = check_safety(rect, &point);
+                if !safe && DEBUG {
+                    println!("offending point ({}, {})", wall_coords[i][0], wall_coords[i][1]);
+                }
+                safe
+            };
+
+            let index_guard = WALL_INDEX.lock().unwrap();
+            let enabled: bool = *SPATIAL_INDEX_ENABLED.lock().unwrap();
+            match &*index_guard {
+                Some(index) if enabled => {
+                    safe_val = index.visit(rect.dims[0], rect.dims[1], check_point);
+                },
+                _ => {
+                    for i in 0..file_rows {
+                        safe_val = check_point(i as usize);
+                        if !safe_val {
+                            break;
+                        }
+                    }
                 }
             }
         },
@@ -172,6 +339,66 @@ pub fn check_safety_wall<const NUM_DIMS: usize>(rect: &HyperRectangle<NUM_DIMS>)
     safe_val
 }
 
+// Bounds on the distance from the box x * y to the nearest wall, over all the points of
+// the box. Walls further than max_range are not looked at, so a lower bound of max_range
+// means at least max_range. Uses the wall points, the wall segments and the occupancy grid.
+pub fn wall_clearance(x: Interval, y: Interval, max_range: f64) -> Interval {
+    let center = [(x.min + x.max) / 2.0, (y.min + y.max) / 2.0];
+    let half_diagonal = ((x.max - x.min).powi(2) + (y.max - y.min).powi(2)).sqrt() / 2.0;
+    let search_x = new_interval(x.min - max_range, x.max + max_range);
+    let search_y = new_interval(y.min - max_range, y.max + max_range);
+
+    // distance from the box and from its center to the nearest wall
+    let mut box_dist = max_range;
+    let mut center_dist = f64::INFINITY;
+    let enabled: bool = *SPATIAL_INDEX_ENABLED.lock().unwrap();
+
+    if let Some(wall_coords) = &*WALL_COORDS.lock().unwrap() {
+        let mut visit_point = |i: usize| {
+            if let [px, py, ..] = wall_coords[i][..] {
+                box_dist = box_dist.min(point_box_distance(&[px, py], x, y));
+                center_dist = center_dist.min(distance_2d(&center, &[px, py]));
+            }
+            true
+        };
+        match &*WALL_INDEX.lock().unwrap() {
+            Some(index) if enabled => {
+                index.visit(search_x, search_y, visit_point);
+            },
+            _ => (0..wall_coords.len()).for_each(|i| { visit_point(i); }),
+        }
+    }
+
+    if let Some(walls) = &*WALL_SEGMENTS.lock().unwrap() {
+        let segments = &walls.segments;
+        // the wall points can be up to the tolerance closer than the segments
+        let mut visit_segment = |i: usize| {
+            box_dist = box_dist.min((segment_box_distance(&segments[i], x, y) - walls.tolerance).max(0.0));
+            center_dist = center_dist.min(point_segment_distance(&center, &segments[i]));
+            true
+        };
+        match &*WALL_SEGMENT_INDEX.lock().unwrap() {
+            Some(index) if enabled => {
+                let (grow_x, grow_y) = (new_interval(search_x.min - walls.tolerance, search_x.max + walls.tolerance), new_interval(search_y.min - walls.tolerance, search_y.max + walls.tolerance));
+                index.visit(grow_x, grow_y, visit_segment);
+            },
+            _ => (0..segments.len()).for_each(|i| { visit_segment(i); }),
+        }
+    }
+
+    if let Some(grid) = &*OCCUPANCY_GRID.lock().unwrap() {
+        grid.visit_occupied(search_x, search_y, |cx, cy| {
+            let dx = (cx.min - x.max).max(x.min - cx.max).max(0.0);
+            let dy = (cy.min - y.max).max(y.min - cy.max).max(0.0);
+            box_dist = box_dist.min((dx * dx + dy * dy).sqrt());
+            center_dist = center_dist.min(point_box_distance(&center, cx, cy));
+        });
+    }
+
+    // the distance to the nearest wall changes at most as fast as the position
+    new_interval(box_dist, center_dist + half_diagonal)
+}
+
 pub fn load_obstacles_from_csv(filename: &std::path::PathBuf) -> Vec<Vec<[f64; 2]>>{
     let mut obstacles_vec: Vec<Vec<[f64; 2]>> = vec![];
     println!("Loading obstacles from file: {:?}", filename);
@@ -207,28 +434,69 @@ pub fn load_obstacles_from_csv_old(filename: &std::path::PathBuf) -> Vec<[f64; 2
     obstacles_vec
 }
 
+// square 0.5 x 0.5 obstacles centered on the given points
 pub fn allocate_obstacles(num_obstacles: u32, points: &[[f64; 2]]){
-    // println!("Allocating {} obstacles", num_obstacles);
-    let rows: usize = num_obstacles as usize;
-    let mut obstacle_count = OBSTACLE_COUNT.lock().unwrap();
-    *obstacle_count = num_obstacles;
-    let cols: usize = 2;
-    let height: usize = 2;
     let w: f64 = 0.5;
     let h: f64 = 0.5;
 
-    // println!("interval list of obstacles: ");
-    let mut obstacles = vec![vec![vec![0.0; cols]; height]; rows];
-    for i in 0..rows {
-        obstacles[i][0][0] = points[i][0] - w/2.0;
-        obstacles[i][0][1] = points[i][0] + w/2.0;
-        obstacles[i][1][0] = points[i][1] - h/2.0;
-        obstacles[i][1][1] = points[i][1] + h/2.0;
-        // println!("[{}, {}], [{}, {}]", obstacles[i][0][0], obstacles[i][0][1], obstacles[i][1][0], obstacles[i][1][1]);
+    let obstacles: Vec<Obstacle> = points[0..num_obstacles as usize]
+        .iter()
+        .map(|p| Obstacle::rect_centered(*p, w, h))
+        .collect();
+    set_obstacles(obstacles);
+}
+
+// same as allocate_obstacles with cuboid obstacles spanning the altitudes z, as the
+// quadcopter nbd episodes use. Scenario files get the same cuboids from shape = "cuboid". Only the quadcopter checks its altitude (Z_DIM) against z, the
+// other vehicles treat the cuboids as rects.
+pub fn allocate_obstacles_3d(num_obstacles: u32, points: &[[f64; 2]], z: Interval){
+    let w: f64 = 0.5;
+    let h: f64 = 0.5;
+
+    let obstacles: Vec<Obstacle> = points[0..num_obstacles as usize]
+        .iter()
+        .map(|p| Obstacle::Cuboid {
+            x: new_interval(p[0] - w / 2.0, p[0] + w / 2.0),
+            y: new_interval(p[1] - h / 2.0, p[1] + h / 2.0),
+            z,
+        })
+        .collect();
+    set_obstacles(obstacles);
+}
+
+// replace the obstacles of the environment, the first DYNAMIC_OBSTACLE_COUNT are the moving ones
+pub fn set_obstacles(obstacles: Vec<Obstacle>){
+    {
+        let mut obstacle_count = OBSTACLE_COUNT.lock().unwrap();
+        *obstacle_count = obstacles.len() as u32;
+    }
+    {
+        let bounds: Vec<_> = obstacles.iter().map(|o| o.bounding_box()).collect();
+        let mut obstacle_index = OBSTACLE_INDEX.lock().unwrap();
+        *obstacle_index = Some(GridIndex::new(&bounds, INDEX_CELL_SIZE));
     }
-    // println!();
     {
         let mut obstacles_lock = OBSTACLES.lock().unwrap();
         *obstacles_lock = Some(obstacles);
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interval::new_interval;
+
+    #[test]
+    fn test_check_safety_obstacles_nan() {
+        let obst = vec![Obstacle::rect_centered([5.0, 5.0], 1.0, 1.0), Obstacle::Cuboid { x: new_interval(-1.0, 1.0), y: new_interval(-1.0, 1.0), z: new_interval(0.0, 1.0) }];
+        let rect = HyperRectangle { dims: [new_interval(-1.0, 1.0), new_interval(2.0, 3.0), new_interval(2.0, 3.0)] };
+        assert!(check_safety_obstacles(&rect, &obst[..1], 1));
+        assert!(check_safety_obstacles_3d(&HyperRectangle { dims: [new_interval(-1.0, 1.0), new_interval(-1.0, 1.0), new_interval(2.0, 3.0)] }, &obst, 2, Some(2)));
+
+        // a diverged reach set may be anywhere
+        let nan_rect = HyperRectangle { dims: [new_interval(f64::NAN, 1.0), new_interval(2.0, 3.0), new_interval(2.0, 3.0)] };
+        assert!(!check_safety_obstacles(&nan_rect, &obst[..1], 1));
+        let nan_z = HyperRectangle { dims: [new_interval(-1.0, 1.0), new_interval(-1.0, 1.0), new_interval(2.0, f64::NAN)] };
+        assert!(!check_safety_obstacles_3d(&nan_z, &obst, 2, Some(2)));
+    }
+}
@@ -0,0 +1,310 @@
+use std::fs::{self, File};
+use std::io::{self, BufReader};
+use std::path::Path;
+use super::geometry::Interval;
+use super::interval::new_interval;
+
+// Occupancy grid loaded from a ROS map_server map, i.e. a PGM or PNG image plus a YAML
+// header with the resolution and the origin of the lower left pixel. Cells are stored
+// row by row starting at the bottom of the image, so row 0 is the smallest y.
+// Only the trinary interpretation is supported and unknown cells count as occupied,
+// as does everything outside of the map.
+#[derive(Clone)]
+pub struct OccupancyGrid {
+    pub resolution: f64,
+    pub origin: [f64; 2],
+    pub width: usize,
+    pub height: usize,
+    occupied: Vec<bool>,
+}
+
+// the fields of the YAML header we use. occupied_thresh is not one of them: it only
+// separates occupied from unknown cells, and unknown cells count as occupied.
+pub struct MapHeader {
+    pub image: String,
+    pub resolution: f64,
+    pub origin: [f64; 3],
+    pub negate: bool,
+    pub free_thresh: f64,
+}
+
+impl OccupancyGrid {
+    // Build the grid from 8-bit grey values given top row first, as they are stored in the image.
+    // A pixel is free if its occupancy probability is below free_thresh.
+    pub fn from_pixels(width: usize, height: usize, pixels: &[u8], resolution: f64, origin: [f64; 2], negate: bool, free_thresh: f64) -> OccupancyGrid {
+        let mut occupied = vec![true; width * height];
+        for row in 0..height {
+            // flip the image so that row 0 is at the origin
+            let img_row = height - 1 - row;
+            for col in 0..width {
+                let value = pixels[img_row * width + col] as f64 / 255.0;
+                let p = if negate { value } else { 1.0 - value };
+                occupied[row * width + col] = p >= free_thresh;
+            }
+        }
+
+        OccupancyGrid {
+            resolution,
+            origin,
+            width,
+            height,
+            occupied,
+        }
+    }
+
+    pub fn is_occupied(&self, col: usize, row: usize) -> bool {
+        self.occupied[row * self.width + col]
+    }
+
+    // Does the box x * y touch an occupied cell?
+    pub fn box_occupied(&self, x: Interval, y: Interval) -> bool {
+        let c0 = ((x.min - self.origin[0]) / self.resolution).floor();
+        let c1 = ((x.max - self.origin[0]) / self.resolution).floor();
+        let r0 = ((y.min - self.origin[1]) / self.resolution).floor();
+        let r1 = ((y.max - self.origin[1]) / self.resolution).floor();
+
+        // partly outside of the map, also catches NaN bounds
+        if !(c0 >= 0.0 && r0 >= 0.0 && c1 < self.width as f64 && r1 < self.height as f64) {
+            return true;
+        }
+
+        for row in (r0 as usize)..=(r1 as usize) {
+            for col in (c0 as usize)..=(c1 as usize) {
+                if self.is_occupied(col, row) {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    // visit the x * y boxes of the occupied cells overlapping the box x * y, ignoring the
+    // outside of the map
+    pub fn visit_occupied(&self, x: Interval, y: Interval, mut visit: impl FnMut(Interval, Interval)) {
+        let last_col = self.width as f64 - 1.0;
+        let last_row = self.height as f64 - 1.0;
+        let c0 = ((x.min - self.origin[0]) / self.resolution).floor().max(0.0);
+        let c1 = ((x.max - self.origin[0]) / self.resolution).floor().min(last_col);
+        let r0 = ((y.min - self.origin[1]) / self.resolution).floor().max(0.0);
+        let r1 = ((y.max - self.origin[1]) / self.resolution).floor().min(last_row);
+        if !(c0 <= c1 && r0 <= r1) {
+            return;
+        }
+
+        for row in (r0 as usize)..=(r1 as usize) {
+            for col in (c0 as usize)..=(c1 as usize) {
+                if self.is_occupied(col, row) {
+                    let cx = self.origin[0] + col as f64 * self.resolution;
+                    let cy = self.origin[1] + row as f64 * self.resolution;
+                    visit(new_interval(cx, cx + self.resolution), new_interval(cy, cy + self.resolution));
+                }
+            }
+        }
+    }
+}
+
+// load a map from its YAML header, the image path is relative to the header
+pub fn load_map(yaml_filename: &str) -> io::Result<OccupancyGrid> {
+    let header = parse_map_header(&fs::read_to_string(yaml_filename)?)?;
+    if header.origin[2] != 0.0 {
+        return Err(invalid_data("rotated map origins are not supported"));
+    }
+
+    let image_path = Path::new(yaml_filename).parent().unwrap_or(Path::new("")).join(&header.image);
+    let is_png = image_path.extension().is_some_and(|e| e.eq_ignore_ascii_case("png"));
+    let (width, height, pixels) = if is_png {
+        read_png(&image_path)?
+    } else {
+        parse_pgm(&fs::read(&image_path)?)?
+    };
+
+    Ok(OccupancyGrid::from_pixels(width, height, &pixels, header.resolution, [header.origin[0], header.origin[1]], header.negate, header.free_thresh))
+}
+
+// parse the flat key: value YAML written by map_server
+pub fn parse_map_header(text: &str) -> io::Result<MapHeader> {
+    let mut image: Option<String> = None;
+    let mut resolution: Option<f64> = None;
+    let mut origin: Option<[f64; 3]> = None;
+    let mut negate = false;
+    let mut free_thresh = 0.196;
+
+    for line in text.lines() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim().trim_matches(|c| c == '"' || c == '\'');
+        match key.trim() {
+            "image" => image = Some(value.to_string()),
+            "resolution" => resolution = Some(parse_f64(value)?),
+            "origin" => {
+                let values: Vec<f64> = value.trim_matches(|c| c == '[' || c == ']')
+                    .split(',')
+                    .map(|s| parse_f64(s.trim()))
+                    .collect::<io::Result<_>>()?;
+                if values.len() != 3 {
+                    return Err(invalid_data("origin must be [x, y, yaw]"));
+                }
+                origin = Some([values[0], values[1], values[2]]);
+            },
+            "negate" => negate = parse_f64(value)? != 0.0,
+            "free_thresh" => free_thresh = parse_f64(value)?,
+            _ => {}
+        }
+    }
+
+    Ok(MapHeader {
+        image: image.ok_or_else(|| invalid_data("missing image"))?,
+        resolution: resolution.ok_or_else(|| invalid_data("missing resolution"))?,
+        origin: origin.ok_or_else(|| invalid_data("missing origin"))?,
+        negate,
+        free_thresh,
+    })
+}
+
+// Parse a binary (P5) or ASCII (P2) PGM image into (width, height, pixels),
+// the pixels are rescaled to 8 bits.
+pub fn parse_pgm(data: &[u8]) -> io::Result<(usize, usize, Vec<u8>)> {
+    // header: magic, width, height, maxval separated by whitespace and comments
+    let mut pos = 0;
+    let mut fields: Vec<String> = Vec::new();
+    while fields.len() < 4 {
+        while pos < data.len() && (data[pos].is_ascii_whitespace() || data[pos] == b'#') {
+            if data[pos] == b'#' {
+                while pos < data.len() && data[pos] != b'\n' {
+                    pos += 1;
+                }
+            } else {
+                pos += 1;
+            }
+        }
+        let start = pos;
+        while pos < data.len() && !data[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        if start == pos {
+            return Err(invalid_data("truncated PGM header"));
+        }
+        fields.push(String::from_utf8_lossy(&data[start..pos]).to_string());
+    }
+
+    let width: usize = fields[1].parse().map_err(|_| invalid_data("bad PGM width"))?;
+    let height: usize = fields[2].parse().map_err(|_| invalid_data("bad PGM height"))?;
+    let maxval: u32 = fields[3].parse().map_err(|_| invalid_data("bad PGM maxval"))?;
+    if maxval == 0 || maxval > 65535 {
+        return Err(invalid_data("bad PGM maxval"));
+    }
+    let scale = |v: u32| (v.min(maxval) * 255 / maxval) as u8;
+
+    let count = width * height;
+    let pixels: Vec<u8> = match fields[0].as_str() {
+        "P5" => {
+            // a single whitespace character separates the header from the data
+            let body = &data[(pos + 1).min(data.len())..];
+            let bytes_per_value = if maxval > 255 { 2 } else { 1 };
+            if body.len() < count * bytes_per_value {
+                return Err(invalid_data("truncated PGM data"));
+            }
+            (0..count).map(|i| {
+                if bytes_per_value == 2 {
+                    scale(u16::from_be_bytes([body[2 * i], body[2 * i + 1]]) as u32)
+                } else {
+                    scale(body[i] as u32)
+                }
+            }).collect()
+        },
+        "P2" => {
+            let values: Vec<u32> = String::from_utf8_lossy(&data[pos..])
+                .split_ascii_whitespace()
+                .take(count)
+                .map(|s| s.parse().map_err(|_| invalid_data("bad PGM value")))
+                .collect::<io::Result<_>>()?;
+            if values.len() < count {
+                return Err(invalid_data("truncated PGM data"));
+            }
+            values.into_iter().map(scale).collect()
+        },
+        _ => return Err(invalid_data("not a PGM image")),
+    };
+
+    Ok((width, height, pixels))
+}
+
+// read a PNG image into (width, height, grey pixels), colors are averaged and alpha is ignored
+fn read_png(filename: &Path) -> io::Result<(usize, usize, Vec<u8>)> {
+    let mut decoder = png::Decoder::new(BufReader::new(File::open(filename)?));
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(|e| invalid_data(&e.to_string()))?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).map_err(|e| invalid_data(&e.to_string()))?;
+
+    let (channels, colors) = match info.color_type {
+        png::ColorType::Grayscale => (1, 1),
+        png::ColorType::GrayscaleAlpha => (2, 1),
+        png::ColorType::Rgb => (3, 3),
+        png::ColorType::Rgba => (4, 3),
+        png::ColorType::Indexed => return Err(invalid_data("indexed PNG images are not supported")),
+    };
+
+    let (width, height) = (info.width as usize, info.height as usize);
+    let mut pixels = Vec::with_capacity(width * height);
+    for row in 0..height {
+        let line = &buf[row * info.line_size..];
+        for col in 0..width {
+            let px = &line[col * channels..col * channels + colors];
+            pixels.push((px.iter().map(|v| *v as u32).sum::<u32>() / colors as u32) as u8);
+        }
+    }
+
+    Ok((width, height, pixels))
+}
+
+fn parse_f64(s: &str) -> io::Result<f64> {
+    s.parse().map_err(|_| invalid_data(&format!("bad number '{}'", s)))
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interval::new_interval;
+
+    #[test]
+    fn test_parse_pgm() {
+        let (w, h, pixels) = parse_pgm(b"P2\n# comment\n3 2\n15\n0 15 15\n15 15 0\n").unwrap();
+        assert_eq!((w, h), (3, 2));
+        assert_eq!(pixels, vec![0, 255, 255, 255, 255, 0]);
+
+        let mut data = b"P5 2 1 255\n".to_vec();
+        data.extend_from_slice(&[254, 0]);
+        assert_eq!(parse_pgm(&data).unwrap(), (2, 1, vec![254, 0]));
+    }
+
+    #[test]
+    fn test_parse_map_header() {
+        let header = parse_map_header("image: track.pgm\nresolution: 0.05\norigin: [-1.5, 2.0, 0.0]  # lower left\nnegate: 0\n").unwrap();
+        assert_eq!(header.image, "track.pgm");
+        assert_eq!(header.resolution, 0.05);
+        assert_eq!(header.origin, [-1.5, 2.0, 0.0]);
+        assert!(!header.negate);
+        assert_eq!(header.free_thresh, 0.196);
+        assert!(parse_map_header("image: track.pgm\n").is_err());
+    }
+
+    #[test]
+    fn test_box_occupied() {
+        // 2 x 2 map with 1 m cells, only the top left cell of the image is black
+        let grid = OccupancyGrid::from_pixels(2, 2, &[0, 254, 254, 254], 1.0, [-1.0, -1.0], false, 0.196);
+        assert!(grid.is_occupied(0, 1));
+        assert!(!grid.is_occupied(0, 0));
+
+        assert!(!grid.box_occupied(new_interval(-0.9, 0.9), new_interval(-0.9, -0.1)));
+        assert!(grid.box_occupied(new_interval(-0.5, -0.4), new_interval(0.5, 0.6)));
+        // outside of the map
+        assert!(grid.box_occupied(new_interval(0.5, 1.5), new_interval(-0.5, -0.4)));
+    }
+}
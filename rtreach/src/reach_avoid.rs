@@ -0,0 +1,172 @@
+use super::geometry::{HyperRectangle, Interval};
+use super::interval::new_interval;
+
+// slack on the window bounds, the times the tracker sees are sums of many step sizes and
+// can miss a window of a single instant, like the one of at_final_time, by rounding
+const TIME_TOLERANCE: f64 = 1e-9;
+
+// Three-valued result of checking a specification on an over-approximated reach set.
+// Sat and Unsat are proofs, Unknown means the over-approximation was too coarse
+// (or the deadline too short) to decide.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    Sat,
+    Unsat,
+    Unknown,
+}
+
+// Reach-avoid specification: avoid the obstacles and walls over the whole horizon, as
+// checked by the lifting callbacks, and at some time in the window be inside the goal.
+// Dimensions the goal does not constrain are [-inf, inf].
+#[derive(Copy, Clone)]
+pub struct ReachAvoidSpec<const NUM_DIMS: usize> {
+    pub goal: HyperRectangle<NUM_DIMS>,
+    pub window: Interval,
+}
+
+impl<const NUM_DIMS: usize> ReachAvoidSpec<NUM_DIMS> {
+    // goal box on the position (dims 0 and 1) only
+    pub fn position_goal(x: Interval, y: Interval, window: Interval) -> ReachAvoidSpec<NUM_DIMS> {
+        let mut goal: HyperRectangle<NUM_DIMS> = HyperRectangle::default();
+        for d in 0..NUM_DIMS {
+            goal.dims[d] = new_interval(f64::NEG_INFINITY, f64::INFINITY);
+        }
+        goal.dims[0] = x;
+        goal.dims[1] = y;
+        ReachAvoidSpec { goal, window }
+    }
+
+    // reach the goal at the final time of the reach computation
+    pub fn at_final_time(goal: HyperRectangle<NUM_DIMS>, reach_time: f64) -> ReachAvoidSpec<NUM_DIMS> {
+        ReachAvoidSpec { goal, window: new_interval(reach_time, reach_time) }
+    }
+
+    pub fn goal_contains(&self, rect: &HyperRectangle<NUM_DIMS>) -> bool {
+        (0..NUM_DIMS).all(|d| self.goal.dims[d].min <= rect.dims[d].min && rect.dims[d].max <= self.goal.dims[d].max)
+    }
+
+    pub fn goal_intersects(&self, rect: &HyperRectangle<NUM_DIMS>) -> bool {
+        (0..NUM_DIMS).all(|d| self.goal.dims[d].min <= rect.dims[d].max && rect.dims[d].min <= self.goal.dims[d].max)
+    }
+}
+
+// Tracks the reach part of the specification over one face lifting iteration
+pub struct ReachTracker<'a, const NUM_DIMS: usize> {
+    spec: &'a ReachAvoidSpec<NUM_DIMS>,
+    // some set inside the window is contained in the goal
+    reached: bool,
+    // some set overlapping the window touches the goal
+    maybe_reached: bool,
+    // time up to which the window has been checked
+    checked_until: f64,
+}
+
+impl<'a, const NUM_DIMS: usize> ReachTracker<'a, NUM_DIMS> {
+    pub fn new(spec: &'a ReachAvoidSpec<NUM_DIMS>, init: &HyperRectangle<NUM_DIMS>) -> ReachTracker<'a, NUM_DIMS> {
+        let mut tracker = ReachTracker {
+            spec,
+            reached: false,
+            maybe_reached: false,
+            checked_until: 0.0,
+        };
+        tracker.observe_time(init, 0.0);
+        tracker
+    }
+
+    // the set `rect` is reached at exactly time t
+    pub fn observe_time(&mut self, rect: &HyperRectangle<NUM_DIMS>, t: f64) {
+        let window = self.spec.window;
+        if window.min - TIME_TOLERANCE <= t && t <= window.max + TIME_TOLERANCE {
+            self.reached = self.reached || self.spec.goal_contains(rect);
+            self.maybe_reached = self.maybe_reached || self.spec.goal_intersects(rect);
+        }
+        self.checked_until = self.checked_until.max(t);
+    }
+
+    // `hull` contains every state reached between t_start and t_end
+    pub fn observe_span(&mut self, hull: &HyperRectangle<NUM_DIMS>, t_start: f64, t_end: f64) {
+        let window = self.spec.window;
+        if t_start <= window.max + TIME_TOLERANCE && window.min - TIME_TOLERANCE <= t_end {
+            self.maybe_reached = self.maybe_reached || self.spec.goal_intersects(hull);
+        }
+        self.checked_until = self.checked_until.max(t_end);
+    }
+
+    // combine with the avoid part, `safe` is the result of the lifting callbacks
+    pub fn verdict(&self, safe: bool) -> Verdict {
+        if self.reached && safe {
+            Verdict::Sat
+        } else if !self.maybe_reached && self.checked_until + TIME_TOLERANCE >= self.spec.window.max {
+            // no trajectory can be in the goal during the window
+            Verdict::Unsat
+        } else {
+            Verdict::Unknown
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect_2d(x: Interval, y: Interval) -> HyperRectangle<2> {
+        HyperRectangle { dims: [x, y] }
+    }
+
+    #[test]
+    fn test_reach_tracker_sat() {
+        let spec = ReachAvoidSpec::<2>::position_goal(new_interval(1.0, 2.0), new_interval(-1.0, 1.0), new_interval(0.5, 1.0));
+        let init = rect_2d(new_interval(0.0, 0.1), new_interval(0.0, 0.1));
+        let mut tracker = ReachTracker::new(&spec, &init);
+        tracker.observe_span(&rect_2d(new_interval(0.0, 1.3), new_interval(0.0, 0.1)), 0.0, 0.5);
+        tracker.observe_time(&rect_2d(new_interval(1.2, 1.3), new_interval(0.0, 0.1)), 0.5);
+        assert_eq!(tracker.verdict(true), Verdict::Sat);
+        // reached but the avoid part failed
+        assert_eq!(tracker.verdict(false), Verdict::Unknown);
+    }
+
+    #[test]
+    fn test_reach_tracker_unsat() {
+        let spec = ReachAvoidSpec::<2>::at_final_time(rect_2d(new_interval(1.0, 2.0), new_interval(-1.0, 1.0)), 1.0);
+        let init = rect_2d(new_interval(0.0, 0.1), new_interval(0.0, 0.1));
+        let mut tracker = ReachTracker::new(&spec, &init);
+        // passes through the goal before the final time
+        tracker.observe_span(&rect_2d(new_interval(0.0, 1.5), new_interval(0.0, 0.1)), 0.0, 0.5);
+        tracker.observe_time(&rect_2d(new_interval(1.4, 1.5), new_interval(0.0, 0.1)), 0.5);
+        assert_eq!(tracker.verdict(true), Verdict::Unknown);
+        tracker.observe_span(&rect_2d(new_interval(1.4, 3.0), new_interval(0.0, 0.1)), 0.5, 1.0);
+        tracker.observe_time(&rect_2d(new_interval(2.9, 3.0), new_interval(0.0, 0.1)), 1.0);
+        // the span to the final time still touches the goal
+        assert_eq!(tracker.verdict(true), Verdict::Unknown);
+
+        let mut tracker = ReachTracker::new(&spec, &init);
+        tracker.observe_span(&rect_2d(new_interval(0.0, 0.5), new_interval(0.0, 0.1)), 0.0, 1.0);
+        tracker.observe_time(&rect_2d(new_interval(0.4, 0.5), new_interval(0.0, 0.1)), 1.0);
+        assert_eq!(tracker.verdict(true), Verdict::Unsat);
+    }
+
+    #[test]
+    fn test_reach_tracker_accumulated_time() {
+        let spec = ReachAvoidSpec::<2>::at_final_time(rect_2d(new_interval(1.0, 2.0), new_interval(-1.0, 1.0)), 1.0);
+        let init = rect_2d(new_interval(0.0, 0.1), new_interval(0.0, 0.1));
+
+        // ten steps of 0.1 end just below 1.0
+        let steps = |tracker: &mut ReachTracker<2>, end_x: f64| {
+            let mut t = 0.0;
+            for i in 1..=10 {
+                let x = end_x * i as f64 / 10.0;
+                tracker.observe_span(&rect_2d(new_interval(0.0, x + 0.1), new_interval(0.0, 0.1)), t, t + 0.1);
+                t += 0.1;
+                tracker.observe_time(&rect_2d(new_interval(x, x + 0.1), new_interval(0.0, 0.1)), t);
+            }
+            t
+        };
+        let mut tracker = ReachTracker::new(&spec, &init);
+        assert!(steps(&mut tracker, 1.5) != 1.0);
+        assert_eq!(tracker.verdict(true), Verdict::Sat);
+
+        let mut tracker = ReachTracker::new(&spec, &init);
+        steps(&mut tracker, 0.5);
+        assert_eq!(tracker.verdict(true), Verdict::Unsat);
+    }
+}
@@ -0,0 +1,131 @@
+// Safe zones. The initial states in a region are labeled on a grid, a cell is safe if the
+// reach set from the whole cell is, under a fixed control or, with dynamic_control, the
+// policy of the model. Labels can be computed once per map and looked up at run time.
+
+use super::face_lift::{LiftingSettings, face_lifting_iterative_improvement};
+use super::geometry::HyperRectangle;
+use super::system_model::SystemModel;
+
+// A grid over region with cells[d] cells along dimension d
+#[derive(Copy, Clone)]
+pub struct Grid<const NUM_DIMS: usize> {
+    pub region: HyperRectangle<NUM_DIMS>,
+    pub cells: [usize; NUM_DIMS],
+}
+
+// The cells of a grid over region with cells[d] cells along dimension d, the first dimension
+// varying fastest
+pub fn grid_cells<const NUM_DIMS: usize>(region: &HyperRectangle<NUM_DIMS>, cells: &[usize; NUM_DIMS]) -> Vec<HyperRectangle<NUM_DIMS>> {
+    let total: usize = cells.iter().product();
+    let mut rv = Vec::with_capacity(total);
+    for i in 0..total {
+        let mut cell = *region;
+        let mut rest = i;
+        for (d, &count) in cells.iter().enumerate() {
+            let index = rest % count;
+            rest /= count;
+            let width = (region.dims[d].max - region.dims[d].min) / count as f64;
+            cell.dims[d].min = region.dims[d].min + width * index as f64;
+            cell.dims[d].max = if index + 1 == count { region.dims[d].max } else { cell.dims[d].min + width };
+        }
+        rv.push(cell);
+    }
+    rv
+}
+
+// Each cell of the grid with whether face lifting from it with settings is
+// safe. With dynamic_control the policy also picks the control at the start of each cell,
+// otherwise it is initial_ctrl_input. Every cell gets the whole settings.max_runtime_us, a
+// cell whose reach set could not be computed in time is unsafe.
+pub fn label_grid<const NUM_DIMS: usize, const NUM_CTRLS: usize, T: SystemModel<NUM_DIMS, NUM_CTRLS>>(
+    system_model: &T,
+    start_ms: u64,
+    settings: &LiftingSettings<NUM_DIMS>,
+    grid: &Grid<NUM_DIMS>,
+    initial_ctrl_input: &[f64; NUM_CTRLS],
+    fixed_step: bool,
+    dynamic_control: bool,
+) -> Vec<(HyperRectangle<NUM_DIMS>, bool)> {
+    grid_cells(&grid.region, &grid.cells)
+        .into_iter()
+        .map(|cell| {
+            let mut cell_settings = *settings;
+            cell_settings.init = cell;
+            let ctrl_input = if dynamic_control { system_model.sample_control(&cell) } else { *initial_ctrl_input };
+            let mut storage_vec: Vec<(f64, HyperRectangle<NUM_DIMS>)> = vec![(0.0, cell)];
+            let safe = face_lifting_iterative_improvement(system_model, start_ms, &mut cell_settings, &ctrl_input, false, &mut storage_vec, fixed_step, dynamic_control);
+            (cell, safe)
+        })
+        .collect()
+}
+
+// Whether state is in a cell labeled safe
+pub fn in_safe_zone<const NUM_DIMS: usize>(labels: &[(HyperRectangle<NUM_DIMS>, bool)], state: &[f64; NUM_DIMS]) -> bool {
+    labels.iter().any(|(cell, safe)| {
+        *safe && (0..NUM_DIMS).all(|d| cell.dims[d].min <= state[d] && state[d] <= cell.dims[d].max)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interval::new_interval;
+    use crate::obstacle::Obstacle;
+
+    // x' = u
+    struct Integrator;
+
+    impl SystemModel<1, 1> for Integrator {
+        fn get_derivative_bounds(&self, _: &HyperRectangle<1>, _: usize, ctrl_inputs: &[f64; 1]) -> f64 {
+            ctrl_inputs[0]
+        }
+
+        // drive back towards 0
+        fn sample_control(&self, rect: &HyperRectangle<1>) -> [f64; 1] {
+            let mid = (rect.dims[0].min + rect.dims[0].max) / 2.0;
+            [if mid > 0.0 { -1.0 } else { 1.0 }]
+        }
+    }
+
+    // unsafe past 3
+    fn below_three(r: &mut HyperRectangle<1>, _: f64, _: fn(t: f64, obs: &mut Vec<Obstacle>), _: bool, _: &mut Vec<(f64, HyperRectangle<1>)>) -> bool {
+        r.dims[0].max <= 3.0
+    }
+
+    #[test]
+    fn test_label_grid() {
+        let region = HyperRectangle { dims: [new_interval(0.0, 4.0)] };
+        let cells = grid_cells(&region, &[4]);
+        assert_eq!(cells.len(), 4);
+        assert_eq!((cells[1].dims[0].min, cells[1].dims[0].max), (1.0, 2.0));
+        let plane = HyperRectangle { dims: [new_interval(0.0, 1.0), new_interval(0.0, 3.0)] };
+        let cells = grid_cells(&plane, &[2, 3]);
+        assert_eq!(cells.len(), 6);
+        assert_eq!((cells[1].dims[0].min, cells[1].dims[1].min), (0.5, 0.0));
+        assert_eq!((cells[2].dims[0].min, cells[2].dims[1].min), (0.0, 1.0));
+
+        let settings = LiftingSettings::<1> {
+            init: region,
+            reach_time: 1.0,
+            initial_step_size: 0.1,
+            max_rect_width_before_error: 100.0,
+            max_runtime_us: 0,
+            obstacle_sim_fn: |_, _| {},
+            reached_at_intermediate_time: Some(below_three),
+            reached_at_final_time: Some(below_three),
+            restarted_computation: None,
+        };
+
+        // moving right at 1 for 1, the cells starting from 2 on cross 3
+        let labels = label_grid(&Integrator, 0, &settings, &Grid { region, cells: [4] }, &[1.0], true, false);
+        let safe: Vec<bool> = labels.iter().map(|(_, safe)| *safe).collect();
+        assert_eq!(safe, vec![true, true, false, false]);
+        assert!(in_safe_zone(&labels, &[0.5]));
+        assert!(!in_safe_zone(&labels, &[2.5]));
+
+        // the policy turns back those that start right of 0
+        let labels = label_grid(&Integrator, 0, &settings, &Grid { region, cells: [4] }, &[1.0], true, true);
+        assert!(labels[..3].iter().all(|(_, safe)| *safe));
+        assert!(!labels[3].1);
+    }
+}
@@ -0,0 +1,524 @@
+// Scenario files. An experiment is described in TOML instead of being compiled into its
+// binary: the vehicle, its model, the start and the waypoints, static and moving obstacles,
+// the walls, the reachability parameters and the algorithm. For example
+//
+//     vehicle = "bicycle"
+//     algorithm = "rrrlc"
+//     initial_state = [0.0, 0.0, 0.0, 0.0]
+//     waypoints = [[4.0, 0.0]]
+//
+//     [[obstacles]]
+//     shape = "rect"
+//     center = [2.0, 0.7]
+//     velocity = [0.0, -0.5]
+//     limits = [[1.75, 2.25], [-0.95, 0.95]]
+//
+//     [reach]
+//     sim_time = 2.0
+//
+//     [sim]
+//     goal_threshold = 0.2
+//
+// Everything but the vehicle, the algorithm, the initial state and the waypoints has a
+// default. Paths are relative to the working directory like those in the binaries.
+
+use lazy_static::lazy_static;
+use serde::Deserialize;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Mutex;
+use super::footprint::Footprint;
+use super::geometry::HyperRectangle;
+use super::initial_split::InitialSetSplit;
+use super::interval::new_interval;
+use super::obstacle::Obstacle;
+use super::obstacle_safety::{load_occupancy_grid, load_wallpoints, set_obstacles, set_wall_segments, DYNAMIC_OBSTACLE_COUNT, OBSTACLES, OBSTACLE_COUNT};
+use super::subgoal::{SubgoalPattern, SubgoalScore};
+use super::wall_segments::load_wall_segments_from_csv;
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VehicleKind {
+    Bicycle,
+    Quadcopter,
+}
+
+impl VehicleKind {
+    pub fn from_name(name: &str) -> Option<VehicleKind> {
+        match name {
+            "bicycle" => Some(VehicleKind::Bicycle),
+            "quadcopter" => Some(VehicleKind::Quadcopter),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            VehicleKind::Bicycle => "bicycle",
+            VehicleKind::Quadcopter => "quadcopter",
+        }
+    }
+}
+
+// The algorithms compared in the experiments
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum Algorithm {
+    // the learned controller without any safety layer
+    Wo,
+    // subgoals checked with reach sets under a fixed control
+    Rrfc,
+    // subgoals checked with reach sets under the learned controller
+    Rrrlc,
+    // rrfc and rrrlc with a verified braking/hover fallback when no subgoal is safe
+    RrfcFb,
+    RrrlcFb,
+    // raw controls closest to the action of the learned controller, no subgoals
+    Rrsf,
+}
+
+// What an algorithm turns on, as the flags of the binaries
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AlgorithmParameters {
+    pub learning_enabled: bool,
+    pub use_subgoal_ctrl: bool,
+    pub use_rtreach: bool,
+    pub use_rtreach_dynamic_control: bool,
+    pub use_fallback: bool,
+    pub use_safety_filter: bool,
+}
+
+impl Algorithm {
+    // algorithm by its name on the command line: wo, rrfc, rrrlc, rrfc_fb, rrrlc_fb or rrsf
+    pub fn from_name(name: &str) -> Option<Algorithm> {
+        match name {
+            "wo" => Some(Algorithm::Wo),
+            "rrfc" => Some(Algorithm::Rrfc),
+            "rrrlc" => Some(Algorithm::Rrrlc),
+            "rrfc_fb" => Some(Algorithm::RrfcFb),
+            "rrrlc_fb" => Some(Algorithm::RrrlcFb),
+            "rrsf" => Some(Algorithm::Rrsf),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Algorithm::Wo => "wo",
+            Algorithm::Rrfc => "rrfc",
+            Algorithm::Rrrlc => "rrrlc",
+            Algorithm::RrfcFb => "rrfc_fb",
+            Algorithm::RrrlcFb => "rrrlc_fb",
+            Algorithm::Rrsf => "rrsf",
+        }
+    }
+
+    pub fn parameters(&self) -> AlgorithmParameters {
+        let subgoals = !matches!(self, Algorithm::Wo | Algorithm::Rrsf);
+        AlgorithmParameters {
+            learning_enabled: true,
+            use_subgoal_ctrl: subgoals,
+            use_rtreach: subgoals,
+            use_rtreach_dynamic_control: matches!(self, Algorithm::Rrrlc | Algorithm::RrrlcFb),
+            use_fallback: matches!(self, Algorithm::RrfcFb | Algorithm::RrrlcFb),
+            use_safety_filter: matches!(self, Algorithm::Rrsf),
+        }
+    }
+}
+
+impl TryFrom<String> for Algorithm {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Algorithm, String> {
+        Algorithm::from_name(&name).ok_or_else(|| format!("unknown algorithm {:?}", name))
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModelParams {
+    // actor network, models/<vehicle>_model_actor.onnx if not given
+    pub onnx: Option<String>,
+    // vehicle shape, the default of the vehicle crate if not given
+    pub footprint: Option<Footprint>,
+}
+
+// Shape of an obstacle at time 0
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(tag = "shape", rename_all = "lowercase", deny_unknown_fields)]
+pub enum ShapeSpec {
+    Rect {
+        center: [f64; 2],
+        #[serde(default = "default_rect_size")]
+        size: [f64; 2],
+    },
+    Circle { center: [f64; 2], radius: f64 },
+    Polygon { vertices: Vec<[f64; 2]> },
+    Cuboid {
+        center: [f64; 2],
+        #[serde(default = "default_rect_size")]
+        size: [f64; 2],
+        z: [f64; 2],
+    },
+}
+
+// the size of the obstacles placed by allocate_obstacles
+fn default_rect_size() -> [f64; 2] {
+    [0.5, 0.5]
+}
+
+// An obstacle moving at a constant velocity in the x/y plane. It stops at the limits, the
+// [[x min, x max], [y min, y max]] its bounding box stays within, and is static with the
+// default velocity of zero.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct ObstacleSpec {
+    #[serde(flatten)]
+    pub shape: ShapeSpec,
+    #[serde(default)]
+    pub velocity: [f64; 2],
+    pub limits: Option<[[f64; 2]; 2]>,
+}
+
+impl ObstacleSpec {
+    pub fn is_moving(&self) -> bool {
+        self.velocity != [0.0, 0.0]
+    }
+
+    pub fn obstacle(&self) -> Obstacle {
+        match &self.shape {
+            ShapeSpec::Rect { center, size } => Obstacle::rect_centered(*center, size[0], size[1]),
+            ShapeSpec::Circle { center, radius } => Obstacle::Circle { center: *center, radius: *radius },
+            ShapeSpec::Polygon { vertices } => Obstacle::Polygon { vertices: vertices.clone() },
+            ShapeSpec::Cuboid { center, size, z } => Obstacle::Cuboid {
+                x: new_interval(center[0] - size[0] / 2.0, center[0] + size[0] / 2.0),
+                y: new_interval(center[1] - size[1] / 2.0, center[1] + size[1] / 2.0),
+                z: new_interval(z[0], z[1]),
+            },
+        }
+    }
+}
+
+// Parameters of the reach set computations and the subgoal selection
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReachParams {
+    pub sim_time: f64,
+    pub step_size: f64,
+    pub wall_time_ms: u64,
+    pub fixed_step: bool,
+    pub store_rect: bool,
+    pub num_subgoal_cands: u32,
+    pub subgoal_pattern: SubgoalPattern,
+    // how the safe subgoal is chosen among the candidates
+    pub scorer: SubgoalScore,
+    // velocity command scales tried in turn when no subgoal is safe
+    pub speed_scales: Vec<f64>,
+    // controls per dimension searched by the safety filter
+    pub control_grid_steps: u32,
+    // bisect the initial box of the reach command into sub-boxes, see Scenario::initial_box
+    pub split: Option<InitialSetSplit>,
+}
+
+impl Default for ReachParams {
+    fn default() -> ReachParams {
+        ReachParams {
+            sim_time: 1.0,
+            step_size: 0.1,
+            wall_time_ms: 100,
+            fixed_step: false,
+            store_rect: false,
+            num_subgoal_cands: 5,
+            subgoal_pattern: SubgoalPattern::Line,
+            scorer: SubgoalScore::FurthestAlong,
+            speed_scales: vec![1.0],
+            control_grid_steps: 3,
+            split: None,
+        }
+    }
+}
+
+impl ReachParams {
+    // the deadline of a reach set computation, as the entry points take it
+    pub fn max_runtime_us(&self) -> u64 {
+        self.wall_time_ms * 1000
+    }
+}
+
+// Parameters of the closed loop simulation
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SimParams {
+    pub step_size: f64,
+    // integration step inside a control step, for the vehicles simulated with euler steps
+    pub euler_step_size: f64,
+    pub total_steps: usize,
+    // distance at which a waypoint counts as reached
+    pub goal_threshold: f64,
+}
+
+impl Default for SimParams {
+    fn default() -> SimParams {
+        SimParams {
+            step_size: 0.1,
+            euler_step_size: 0.0002,
+            total_steps: 1000,
+            goal_threshold: 1.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    pub vehicle: VehicleKind,
+    pub algorithm: Algorithm,
+    #[serde(default)]
+    pub model: ModelParams,
+    // the dimensions not given start at 0
+    pub initial_state: Vec<f64>,
+    // half widths of the box of uncertain initial states around initial_state, the
+    // dimensions not given are exact
+    #[serde(default)]
+    pub initial_uncertainty: Vec<f64>,
+    // positions the vehicle visits in order, the last one is the goal
+    pub waypoints: Vec<Vec<f64>>,
+    #[serde(default)]
+    pub obstacles: Vec<ObstacleSpec>,
+    // wall points csv, wall segments csv and map_server map
+    pub walls: Option<String>,
+    pub wall_segments: Option<String>,
+    pub map: Option<String>,
+    #[serde(default)]
+    pub reach: ReachParams,
+    #[serde(default)]
+    pub sim: SimParams,
+}
+
+// velocity and limits of a moving obstacle
+pub type ObstacleMotion = ([f64; 2], Option<[[f64; 2]; 2]>);
+
+lazy_static! {
+    // velocity and limits of the moving obstacles set by install_obstacles, in the order of
+    // the dynamic obstacles
+    pub static ref OBSTACLE_MOTIONS: Mutex<Vec<ObstacleMotion>> = Mutex::new(Vec::new());
+}
+
+impl Scenario {
+    pub fn onnx_path(&self) -> String {
+        self.model.onnx.clone().unwrap_or_else(|| format!("models/{}_model_actor.onnx", self.vehicle.name()))
+    }
+
+    // the initial state of a vehicle with NUM_DIMS dimensions
+    pub fn initial_state<const NUM_DIMS: usize>(&self) -> io::Result<[f64; NUM_DIMS]> {
+        if self.initial_state.len() > NUM_DIMS {
+            return Err(invalid_data(&format!("initial_state has {} dimensions, the {} has {}", self.initial_state.len(), self.vehicle.name(), NUM_DIMS)));
+        }
+        let mut state = [0.0; NUM_DIMS];
+        state[..self.initial_state.len()].copy_from_slice(&self.initial_state);
+        Ok(state)
+    }
+
+    // the box of uncertain initial states of a vehicle with NUM_DIMS dimensions
+    pub fn initial_box<const NUM_DIMS: usize>(&self) -> io::Result<HyperRectangle<NUM_DIMS>> {
+        if self.initial_uncertainty.len() > NUM_DIMS {
+            return Err(invalid_data(&format!("initial_uncertainty has {} dimensions, the {} has {}", self.initial_uncertainty.len(), self.vehicle.name(), NUM_DIMS)));
+        }
+        let state = self.initial_state::<NUM_DIMS>()?;
+        Ok(HyperRectangle {
+            dims: std::array::from_fn(|d| {
+                let half_width = self.initial_uncertainty.get(d).copied().unwrap_or(0.0);
+                new_interval(state[d] - half_width, state[d] + half_width)
+            }),
+        })
+    }
+
+    // the waypoints of a vehicle with goals of GOAL_DIMS dimensions
+    pub fn waypoints<const GOAL_DIMS: usize>(&self) -> io::Result<Vec<[f64; GOAL_DIMS]>> {
+        self.waypoints
+            .iter()
+            .map(|w| {
+                <[f64; GOAL_DIMS]>::try_from(w.as_slice())
+                    .map_err(|_| invalid_data(&format!("waypoint {:?} does not have {} dimensions", w, GOAL_DIMS)))
+            })
+            .collect()
+    }
+
+    // Set up the obstacles and the walls of the scenario, see install_obstacles
+    pub fn install_environment(&self) {
+        install_obstacles(&self.obstacles);
+
+        if let Some(walls) = &self.walls {
+            load_wallpoints(walls, false);
+        }
+        if let Some(map) = &self.map {
+            load_occupancy_grid(map, false);
+        }
+        if let Some(segments) = &self.wall_segments {
+            set_wall_segments(load_wall_segments_from_csv(&Path::new(segments).to_path_buf()));
+        }
+    }
+
+    // whether any obstacle moves, otherwise the obstacle_sim_fn can be a no-op
+    pub fn has_moving_obstacles(&self) -> bool {
+        self.obstacles.iter().any(|o| o.is_moving())
+    }
+}
+
+// Replace the obstacles of the environment. The moving obstacles come first so they are the
+// DYNAMIC_OBSTACLE_COUNT dynamic ones, scenario_obstacle_sim_fn moves them.
+pub fn install_obstacles(obstacles: &[ObstacleSpec]) {
+    let (moving, fixed): (Vec<&ObstacleSpec>, Vec<&ObstacleSpec>) = obstacles.iter().partition(|o| o.is_moving());
+    {
+        let mut motions = OBSTACLE_MOTIONS.lock().unwrap();
+        *motions = moving.iter().map(|o| (o.velocity, o.limits)).collect();
+    }
+    {
+        let mut dyn_obs_count = DYNAMIC_OBSTACLE_COUNT.lock().unwrap();
+        *dyn_obs_count = moving.len() as u32;
+    }
+    if obstacles.is_empty() {
+        *OBSTACLES.lock().unwrap() = None;
+        *OBSTACLE_COUNT.lock().unwrap() = 0;
+    } else {
+        set_obstacles(moving.iter().chain(fixed.iter()).map(|o| o.obstacle()).collect());
+    }
+}
+
+pub fn load_scenario(filename: &Path) -> io::Result<Scenario> {
+    parse_scenario(&fs::read_to_string(filename)?)
+}
+
+pub fn parse_scenario(text: &str) -> io::Result<Scenario> {
+    let scenario: Scenario = toml::from_str(text).map_err(|e| invalid_data(e.message()))?;
+    if scenario.waypoints.is_empty() {
+        return Err(invalid_data("a scenario needs at least one waypoint"));
+    }
+    for o in &scenario.obstacles {
+        if let ShapeSpec::Polygon { vertices } = &o.shape {
+            if vertices.len() < 3 {
+                return Err(invalid_data("a polygon obstacle needs at least 3 vertices"));
+            }
+        }
+    }
+    Ok(scenario)
+}
+
+// obstacle_sim_fn for the moving obstacles of the installed scenario: each moves by its
+// velocity times t, stopping where its bounding box reaches the limits
+pub fn scenario_obstacle_sim_fn(t: f64, obs: &mut Vec<Obstacle>) {
+    let motions = OBSTACLE_MOTIONS.lock().unwrap();
+    move_obstacles(&motions, t, obs);
+}
+
+fn move_obstacles(motions: &[ObstacleMotion], t: f64, obs: &mut [Obstacle]) {
+    for (o, (velocity, limits)) in obs.iter_mut().zip(motions.iter()) {
+        let mut dx = velocity[0] * t;
+        let mut dy = velocity[1] * t;
+        if let Some([lx, ly]) = limits {
+            let [x, y] = o.bounding_box();
+            // min and max rather than clamp, an obstacle wider than its limits must not panic
+            dx = dx.min(lx[1] - x.max).max(lx[0] - x.min);
+            dy = dy.min(ly[1] - y.max).max(ly[0] - y.min);
+        }
+        o.translate(dx, dy);
+    }
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::initial_split::SplitDimension;
+    use crate::subgoal::WeightedScorer;
+
+    const SCENARIO: &str = r#"
+        vehicle = "bicycle"
+        algorithm = "rrrlc_fb"
+        initial_state = [1.0, 2.0]
+        initial_uncertainty = [0.1]
+        waypoints = [[4.0, 0.0], [8.0, 0.0]]
+
+        [model]
+        footprint = { radius = 0.3 }
+
+        [[obstacles]]
+        shape = "rect"
+        center = [2.0, 1.4]
+
+        [[obstacles]]
+        shape = "rect"
+        center = [2.0, 0.7]
+        velocity = [0.0, -0.5]
+        limits = [[1.75, 2.25], [-0.95, 0.95]]
+
+        [[obstacles]]
+        shape = "circle"
+        center = [5.0, 0.0]
+        radius = 0.5
+
+        [reach]
+        sim_time = 2.0
+        subgoal_pattern = "lateral"
+        scorer = { clearance = 0.5 }
+        split = { dimension = "sensitive", depth = 2 }
+
+        [sim]
+        goal_threshold = 0.2
+    "#;
+
+    #[test]
+    fn test_parse_scenario() {
+        let scenario = parse_scenario(SCENARIO).unwrap();
+        assert_eq!(scenario.vehicle, VehicleKind::Bicycle);
+        assert_eq!(scenario.algorithm, Algorithm::RrrlcFb);
+        assert!(scenario.algorithm.parameters().use_fallback);
+        assert_eq!(scenario.onnx_path(), "models/bicycle_model_actor.onnx");
+        assert_eq!(scenario.model.footprint, Some(Footprint::Circle { radius: 0.3 }));
+        assert_eq!(scenario.initial_state::<4>().unwrap(), [1.0, 2.0, 0.0, 0.0]);
+        assert!(scenario.initial_state::<1>().is_err());
+        let init = scenario.initial_box::<4>().unwrap();
+        assert_eq!((init.dims[0].min, init.dims[0].max), (0.9, 1.1));
+        assert_eq!((init.dims[1].min, init.dims[1].max), (2.0, 2.0));
+        assert_eq!(scenario.waypoints::<2>().unwrap(), vec![[4.0, 0.0], [8.0, 0.0]]);
+        assert!(scenario.waypoints::<3>().is_err());
+        assert_eq!(scenario.obstacles.len(), 3);
+        assert_eq!(scenario.obstacles[0].shape, ShapeSpec::Rect { center: [2.0, 1.4], size: [0.5, 0.5] });
+        assert!(!scenario.obstacles[0].is_moving());
+        assert!(scenario.obstacles[1].is_moving());
+        assert_eq!(scenario.reach.sim_time, 2.0);
+        assert_eq!(scenario.reach.wall_time_ms, 100);
+        assert_eq!(scenario.reach.subgoal_pattern, SubgoalPattern::from_name("lateral").unwrap());
+        assert_eq!(scenario.reach.scorer, SubgoalScore::Weighted(WeightedScorer { clearance: 0.5, ..WeightedScorer::default() }));
+        assert_eq!(scenario.reach.split, Some(InitialSetSplit { dimension: SplitDimension::Sensitive, depth: 2, parallel: false }));
+        assert_eq!(scenario.sim.goal_threshold, 0.2);
+        assert_eq!(scenario.sim.total_steps, 1000);
+
+        assert!(parse_scenario("vehicle = \"bicycle\"\nalgorithm = \"fast\"\ninitial_state = []\nwaypoints = [[1.0, 0.0]]").is_err());
+        assert!(parse_scenario("vehicle = \"bicycle\"\nalgorithm = \"wo\"\ninitial_state = []\nwaypoints = []").is_err());
+        assert!(parse_scenario("vehicle = \"boat\"\nalgorithm = \"wo\"\ninitial_state = []\nwaypoints = [[1.0, 0.0]]").is_err());
+        let scenario = parse_scenario("vehicle = \"bicycle\"\nalgorithm = \"wo\"\ninitial_state = []\nwaypoints = [[1.0, 0.0]]\n[reach]\nscorer = \"weighted\"").unwrap();
+        assert_eq!(scenario.reach.scorer, SubgoalScore::Weighted(WeightedScorer::default()));
+        assert!(parse_scenario("vehicle = \"bicycle\"\nalgorithm = \"wo\"\ninitial_state = []\nwaypoints = [[1.0, 0.0]]\n[reach]\nscorer = \"best\"").is_err());
+    }
+
+    #[test]
+    fn test_move_obstacles() {
+        let scenario = parse_scenario(SCENARIO).unwrap();
+        let spec = &scenario.obstacles[1];
+        let motions = vec![(spec.velocity, spec.limits)];
+        let mut obs = vec![spec.obstacle()];
+        move_obstacles(&motions, 0.4, &mut obs);
+        let [_, y] = obs[0].bounding_box();
+        assert!((y.min - 0.25).abs() < 1e-9 && (y.max - 0.75).abs() < 1e-9);
+
+        // stops at the lower limit
+        move_obstacles(&motions, 10.0, &mut obs);
+        let [x, y] = obs[0].bounding_box();
+        assert!((y.min + 0.95).abs() < 1e-9 && (y.max + 0.45).abs() < 1e-9);
+        assert_eq!((x.min, x.max), (1.75, 2.25));
+    }
+}
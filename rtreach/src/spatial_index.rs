@@ -0,0 +1,162 @@
+use super::geometry::Interval;
+
+// A uniform grid over the x/y plane. Every cell keeps the ids of the items whose
+// bounding box touches it, so a query only visits the items near the queried box
+// instead of scanning all of them. Build it once per environment.
+#[derive(Clone)]
+pub struct GridIndex {
+    cell_size: f64,
+    origin: [f64; 2],
+    cols: usize,
+    rows: usize,
+    cells: Vec<Vec<usize>>,
+}
+
+impl GridIndex {
+    // index the items by their bounding boxes, the id of an item is its position in `bounds`
+    pub fn new(bounds: &[[Interval; 2]], cell_size: f64) -> GridIndex {
+        let mut origin = [f64::MAX, f64::MAX];
+        let mut extent = [f64::MIN, f64::MIN];
+        for b in bounds {
+            for d in 0..2 {
+                origin[d] = origin[d].min(b[d].min);
+                extent[d] = extent[d].max(b[d].max);
+            }
+        }
+        if bounds.is_empty() {
+            origin = [0.0, 0.0];
+            extent = [0.0, 0.0];
+        }
+
+        let cols = ((extent[0] - origin[0]) / cell_size).floor() as usize + 1;
+        let rows = ((extent[1] - origin[1]) / cell_size).floor() as usize + 1;
+        let mut index = GridIndex {
+            cell_size,
+            origin,
+            cols,
+            rows,
+            cells: vec![Vec::new(); cols * rows],
+        };
+
+        for (id, b) in bounds.iter().enumerate() {
+            if let Some((c0, c1, r0, r1)) = index.cell_range(b[0], b[1]) {
+                for r in r0..=r1 {
+                    for c in c0..=c1 {
+                        index.cells[r * cols + c].push(id);
+                    }
+                }
+            }
+        }
+
+        index
+    }
+
+    // Visit the ids of the items that may overlap the box x * y until `visit` returns false.
+    // An item spanning several cells can be visited more than once.
+    // Returns false if the visit was stopped early, or without visiting anything for a box
+    // with NaN bounds (a diverged reach set), which may be anywhere and so is never safe.
+    pub fn visit(&self, x: Interval, y: Interval, mut visit: impl FnMut(usize) -> bool) -> bool {
+        if [x.min, x.max, y.min, y.max].iter().any(|v| v.is_nan()) {
+            return false;
+        }
+        if let Some((c0, c1, r0, r1)) = self.cell_range(x, y) {
+            for r in r0..=r1 {
+                for c in c0..=c1 {
+                    for id in &self.cells[r * self.cols + c] {
+                        if !visit(*id) {
+                            return false;
+                        }
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    // ids of the items that may overlap the box x * y, without duplicates
+    pub fn query(&self, x: Interval, y: Interval) -> Vec<usize> {
+        let mut rv = Vec::new();
+        self.visit(x, y, |id| {
+            rv.push(id);
+            true
+        });
+        rv.sort_unstable();
+        rv.dedup();
+        rv
+    }
+
+    // range of cells covered by a box, clamped to the grid, None if the box misses the grid
+    fn cell_range(&self, x: Interval, y: Interval) -> Option<(usize, usize, usize, usize)> {
+        let (c0, c1) = Self::axis_range(x, self.origin[0], self.cell_size, self.cols)?;
+        let (r0, r1) = Self::axis_range(y, self.origin[1], self.cell_size, self.rows)?;
+        Some((c0, c1, r0, r1))
+    }
+
+    fn axis_range(i: Interval, origin: f64, cell_size: f64, count: usize) -> Option<(usize, usize)> {
+        let lo = ((i.min - origin) / cell_size).floor();
+        let hi = ((i.max - origin) / cell_size).floor();
+        // also rejects NaN bounds from a diverged reach set
+        if !(hi >= 0.0 && lo < count as f64) {
+            return None;
+        }
+        let last = (count - 1) as f64;
+        Some((lo.clamp(0.0, last) as usize, hi.clamp(0.0, last) as usize))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interval::{new_interval, new_interval_v};
+
+    #[test]
+    fn test_query_points() {
+        let points = [[0.0, 0.0], [1.0, 1.0], [5.0, 5.0], [5.1, 4.9]];
+        let bounds: Vec<[Interval; 2]> = points.iter().map(|p| [new_interval_v(p[0]), new_interval_v(p[1])]).collect();
+        let index = GridIndex::new(&bounds, 0.5);
+
+        assert_eq!(index.query(new_interval(-0.1, 0.1), new_interval(-0.1, 0.1)), vec![0]);
+        assert_eq!(index.query(new_interval(4.0, 6.0), new_interval(4.0, 6.0)), vec![2, 3]);
+        assert!(index.query(new_interval(2.0, 3.0), new_interval(2.0, 3.0)).is_empty());
+        assert!(index.query(new_interval(10.0, 11.0), new_interval(0.0, 1.0)).is_empty());
+    }
+
+    #[test]
+    fn test_query_boxes_spanning_cells() {
+        let bounds = [[new_interval(0.0, 3.0), new_interval(0.0, 0.2)], [new_interval(2.5, 2.6), new_interval(2.5, 2.6)]];
+        let index = GridIndex::new(&bounds, 1.0);
+
+        assert_eq!(index.query(new_interval(1.2, 1.4), new_interval(0.1, 0.3)), vec![0]);
+        assert_eq!(index.query(new_interval(-5.0, 5.0), new_interval(-5.0, 5.0)), vec![0, 1]);
+        assert!(index.query(new_interval(f64::NAN, f64::NAN), new_interval(0.0, 1.0)).is_empty());
+    }
+
+    #[test]
+    fn test_visit_stops_early() {
+        let bounds = [[new_interval_v(0.0), new_interval_v(0.0)], [new_interval_v(0.1), new_interval_v(0.1)]];
+        let index = GridIndex::new(&bounds, 1.0);
+        let mut visited = 0;
+        let finished = index.visit(new_interval(-1.0, 1.0), new_interval(-1.0, 1.0), |_| {
+            visited += 1;
+            false
+        });
+        assert!(!finished);
+        assert_eq!(visited, 1);
+    }
+
+    #[test]
+    fn test_visit_nan_box() {
+        let bounds = [[new_interval_v(0.0), new_interval_v(0.0)]];
+        let index = GridIndex::new(&bounds, 1.0);
+        let mut visited = 0;
+        for (x, y) in [(new_interval(f64::NAN, 1.0), new_interval(-1.0, 1.0)), (new_interval(-1.0, 1.0), new_interval(-1.0, f64::NAN))] {
+            // a safety check visiting this box must fail, not pass for lack of items
+            assert!(!index.visit(x, y, |_| {
+                visited += 1;
+                true
+            }));
+        }
+        assert_eq!(visited, 0);
+        assert!(index.visit(new_interval(5.0, 6.0), new_interval(5.0, 6.0), |_| true));
+    }
+}
@@ -0,0 +1,583 @@
+use std::sync::Mutex;
+use csv::ReaderBuilder;
+use lazy_static::lazy_static;
+use super::geometry::{HyperRectangle, Interval};
+use super::interval::new_interval;
+use super::obstacle::Obstacle;
+use super::obstacle_safety::wall_clearance;
+use super::reach_avoid::Verdict;
+
+// Signal Temporal Logic over reach tubes.
+//
+// A trace is a list of (time, box) with increasing times where box i contains every state
+// between time i and time i + 1, the last box only the states at its time. This is what the
+// lifting callbacks store in storage_vec. Nothing is known after the last time.
+//
+// The robustness of a formula is evaluated with intervals: the returned interval contains
+// the robustness of every trajectory in the tube, so a positive lower bound proves the
+// formula (Sat) and a negative upper bound disproves it (Unsat).
+
+// the quantity a predicate compares against a constant
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    // sum of coefficient * x[dim]
+    Linear(Vec<(usize, f64)>),
+    // distance of (x[dims[0]], x[dims[1]]) to a point
+    Dist { dims: [usize; 2], point: [f64; 2] },
+    // distance of the position (x0, x1) to the nearest wall
+    WallClearance,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Formula {
+    True,
+    // expr >= bound if greater, expr <= bound otherwise
+    Predicate { expr: Expr, greater: bool, bound: f64 },
+    Not(Box<Formula>),
+    And(Box<Formula>, Box<Formula>),
+    Or(Box<Formula>, Box<Formula>),
+    // for all / for some time in [now + a, now + b]
+    Always([f64; 2], Box<Formula>),
+    Eventually([f64; 2], Box<Formula>),
+}
+
+lazy_static! {
+    // formula checked by the stl_* lifting callbacks and by the vehicle callbacks if set
+    pub static ref STL_MONITOR: Mutex<Option<Formula>> = Mutex::new(None);
+    static ref STL_TRACE: Mutex<Vec<(f64, Vec<Interval>)>> = Mutex::new(Vec::new());
+    static ref STL_VERDICT: Mutex<Verdict> = Mutex::new(Verdict::Unknown);
+}
+
+// Parse a formula such as "G[0, 2] wall_clearance >= 0.3 & F[0, 2] dist(x0, x1, 3.0, 1.0) <= 0.5".
+// Predicates compare x<dim>, linear combinations like "x0 - 2 * x1", dist(x<i>, x<j>, px, py)
+// or wall_clearance with >=, >, <= or < against a number. G[a, b] is always, F[a, b] eventually,
+// ! binds strongest, then & and then |.
+pub fn parse_formula(text: &str) -> Result<Formula, String> {
+    let tokens = tokenize(text)?;
+    let mut parser = Parser { tokens, pos: 0 };
+    let formula = parser.parse_or()?;
+    match parser.peek() {
+        None => Ok(formula),
+        Some(t) => Err(format!("unexpected '{}'", t)),
+    }
+}
+
+impl Formula {
+    pub fn negate(f: Formula) -> Formula {
+        Formula::Not(Box::new(f))
+    }
+
+    pub fn and(f: Formula, g: Formula) -> Formula {
+        Formula::And(Box::new(f), Box::new(g))
+    }
+
+    pub fn or(f: Formula, g: Formula) -> Formula {
+        Formula::Or(Box::new(f), Box::new(g))
+    }
+
+    pub fn always(a: f64, b: f64, f: Formula) -> Formula {
+        Formula::Always([a, b], Box::new(f))
+    }
+
+    pub fn eventually(a: f64, b: f64, f: Formula) -> Formula {
+        Formula::Eventually([a, b], Box::new(f))
+    }
+
+    // within radius of a point in the x/y plane
+    pub fn near(point: [f64; 2], radius: f64) -> Formula {
+        Formula::Predicate { expr: Expr::Dist { dims: [0, 1], point }, greater: false, bound: radius }
+    }
+
+    // at least clearance away from the walls
+    pub fn wall_clearance(clearance: f64) -> Formula {
+        Formula::Predicate { expr: Expr::WallClearance, greater: true, bound: clearance }
+    }
+}
+
+pub fn trace_from_rects<const NUM_DIMS: usize>(rects: &[(f64, HyperRectangle<NUM_DIMS>)]) -> Vec<(f64, Vec<Interval>)> {
+    rects.iter().map(|(t, r)| (*t, r.dims.to_vec())).collect()
+}
+
+// robustness interval of the formula at the start of the trace
+pub fn robustness(formula: &Formula, trace: &[(f64, Vec<Interval>)]) -> Interval {
+    if trace.is_empty() {
+        return new_interval(f64::NEG_INFINITY, f64::INFINITY);
+    }
+    let spans = spans(trace);
+    robustness_at(formula, trace, &spans, 0)
+}
+
+pub fn verdict_of(robustness: Interval) -> Verdict {
+    if robustness.min > 0.0 {
+        Verdict::Sat
+    } else if robustness.max < 0.0 {
+        Verdict::Unsat
+    } else {
+        Verdict::Unknown
+    }
+}
+
+pub fn evaluate(formula: &Formula, trace: &[(f64, Vec<Interval>)]) -> Verdict {
+    verdict_of(robustness(formula, trace))
+}
+
+pub fn set_stl_monitor(formula: Option<Formula>) {
+    {
+        let mut monitor = STL_MONITOR.lock().unwrap();
+        *monitor = formula;
+    }
+    stl_monitor_restarted();
+}
+
+pub fn stl_monitor_enabled() -> bool {
+    STL_MONITOR.lock().unwrap().is_some()
+}
+
+// start a new trace, call when face lifting restarts
+pub fn stl_monitor_restarted() {
+    STL_TRACE.lock().unwrap().clear();
+    *STL_VERDICT.lock().unwrap() = Verdict::Unknown;
+}
+
+pub fn stl_monitor_record<const NUM_DIMS: usize>(rect: &HyperRectangle<NUM_DIMS>, time: f64) {
+    STL_TRACE.lock().unwrap().push((time, rect.dims.to_vec()));
+}
+
+// evaluate the monitor on the trace so far, true only if the formula is proven to hold,
+// an Unknown verdict is not safe. Always true without a monitor.
+pub fn stl_monitor_check() -> bool {
+    let verdict = match &*STL_MONITOR.lock().unwrap() {
+        Some(formula) => evaluate(formula, &STL_TRACE.lock().unwrap()),
+        None => return true,
+    };
+    *STL_VERDICT.lock().unwrap() = verdict;
+    verdict == Verdict::Sat
+}
+
+// verdict of the last stl_monitor_check
+pub fn stl_monitor_verdict() -> Verdict {
+    *STL_VERDICT.lock().unwrap()
+}
+
+// Lifting callbacks that only check STL_MONITOR, for models without obstacles
+pub fn stl_reached_at_intermediate_time<const NUM_DIMS: usize>(rect: &mut HyperRectangle<NUM_DIMS>, time: f64, _: fn(t: f64, obs: &mut Vec<Obstacle>), store_rect: bool, storage_vec: &mut Vec<(f64, HyperRectangle<NUM_DIMS>)>) -> bool {
+    if store_rect {
+        storage_vec.push((time, *rect));
+    }
+    stl_monitor_record(rect, time);
+    true
+}
+
+pub fn stl_reached_at_final_time<const NUM_DIMS: usize>(rect: &mut HyperRectangle<NUM_DIMS>, time: f64, obstacle_sim_fn: fn(t: f64, obs: &mut Vec<Obstacle>), store_rect: bool, storage_vec: &mut Vec<(f64, HyperRectangle<NUM_DIMS>)>) -> bool {
+    stl_reached_at_intermediate_time(rect, time, obstacle_sim_fn, store_rect, storage_vec);
+    stl_monitor_check()
+}
+
+pub fn stl_restarted_computation<const NUM_DIMS: usize>(_: bool, storage_vec: &mut Vec<(f64, HyperRectangle<NUM_DIMS>)>) {
+    storage_vec.truncate(1);
+    stl_monitor_restarted();
+}
+
+// Load the rects saved by save_rects_to_csv as a trace
+pub fn load_trace_from_csv(filename: &std::path::PathBuf) -> Vec<(f64, Vec<Interval>)> {
+    let mut traces = load_traces_from_csv(filename, 0);
+    traces.pop().unwrap_or_default()
+}
+
+// Load the reach tubes saved by save_reachtubes_to_csv, one trace per value of the time column
+pub fn load_reachtubes_from_csv(filename: &std::path::PathBuf) -> Vec<Vec<(f64, Vec<Interval>)>> {
+    load_traces_from_csv(filename, 1)
+}
+
+// rows are [tube id,] rect_time, min0, max0, min1, max1, ...
+fn load_traces_from_csv(filename: &std::path::PathBuf, id_columns: usize) -> Vec<Vec<(f64, Vec<Interval>)>> {
+    let mut traces: Vec<Vec<(f64, Vec<Interval>)>> = vec![];
+    println!("Loading reach tube from file: {:?}", filename);
+    let mut reader = ReaderBuilder::new().has_headers(true).from_path(filename).unwrap();
+    let mut cur_id: Option<String> = None;
+    for result in reader.records() {
+        let record = result.unwrap();
+        let id = (id_columns > 0).then(|| record.get(0).unwrap().to_string());
+        if traces.is_empty() || id != cur_id {
+            traces.push(vec![]);
+            cur_id = id;
+        }
+        let values: Vec<f64> = record.iter().skip(id_columns).map(|v| v.parse::<f64>().unwrap()).collect();
+        let dims = values[1..].chunks(2).map(|c| new_interval(c[0], c[1])).collect();
+        traces.last_mut().unwrap().push((values[0], dims));
+    }
+    traces
+}
+
+// time span covered by every box of the trace
+fn spans(trace: &[(f64, Vec<Interval>)]) -> Vec<[f64; 2]> {
+    (0..trace.len()).map(|i| {
+        let end = if i + 1 < trace.len() { trace[i + 1].0 } else { trace[i].0 };
+        [trace[i].0, end.max(trace[i].0)]
+    }).collect()
+}
+
+// robustness interval valid for every time in the span of box j
+fn robustness_at(formula: &Formula, trace: &[(f64, Vec<Interval>)], spans: &[[f64; 2]], j: usize) -> Interval {
+    match formula {
+        Formula::True => new_interval(f64::INFINITY, f64::INFINITY),
+        Formula::Predicate { expr, greater, bound } => {
+            let e = expr_bounds(expr, &trace[j].1, *bound);
+            if *greater {
+                new_interval(e.min - bound, e.max - bound)
+            } else {
+                new_interval(bound - e.max, bound - e.min)
+            }
+        },
+        Formula::Not(f) => {
+            let r = robustness_at(f, trace, spans, j);
+            new_interval(-r.max, -r.min)
+        },
+        Formula::And(f, g) => {
+            let (r1, r2) = (robustness_at(f, trace, spans, j), robustness_at(g, trace, spans, j));
+            new_interval(r1.min.min(r2.min), r1.max.min(r2.max))
+        },
+        Formula::Or(f, g) => {
+            let (r1, r2) = (robustness_at(f, trace, spans, j), robustness_at(g, trace, spans, j));
+            new_interval(r1.min.max(r2.min), r1.max.max(r2.max))
+        },
+        Formula::Always([a, b], f) => temporal(f, trace, spans, j, *a, *b, true),
+        Formula::Eventually([a, b], f) => temporal(f, trace, spans, j, *a, *b, false),
+    }
+}
+
+// Always (min) or eventually (max) of f over the window [t + a, t + b] for all t in span j.
+// Every box touching one of the windows can hold the extreme value, only the boxes
+// touching all of them are sure to be inside the window.
+fn temporal(f: &Formula, trace: &[(f64, Vec<Interval>)], spans: &[[f64; 2]], j: usize, a: f64, b: f64, always: bool) -> Interval {
+    let [s, e] = spans[j];
+    let inf = f64::INFINITY;
+    // bound on the extreme value from any window, and from all windows
+    let mut any = if always { inf } else { -inf };
+    let mut all = any;
+
+    for (k, [ks, ke]) in spans.iter().enumerate() {
+        if *ks > e + b || *ke < s + a {
+            continue;
+        }
+        let r = robustness_at(f, trace, spans, k);
+        let in_all = *ks <= s + b && *ke >= e + a;
+        if always {
+            any = any.min(r.min);
+            if in_all {
+                all = all.min(r.max);
+            }
+        } else {
+            any = any.max(r.max);
+            if in_all {
+                all = all.max(r.min);
+            }
+        }
+    }
+
+    // windows reaching past the end of the trace can see anything there
+    let end = spans[spans.len() - 1][1];
+    if e + b > end {
+        any = if always { -inf } else { inf };
+    }
+
+    if always {
+        new_interval(any, all)
+    } else {
+        new_interval(all, any)
+    }
+}
+
+fn expr_bounds(expr: &Expr, dims: &[Interval], bound: f64) -> Interval {
+    let unknown = new_interval(f64::NEG_INFINITY, f64::INFINITY);
+    match expr {
+        Expr::Linear(terms) => {
+            let mut rv = new_interval(0.0, 0.0);
+            for (d, c) in terms {
+                let Some(x) = dims.get(*d) else {
+                    return unknown;
+                };
+                let (lo, hi) = if *c >= 0.0 { (c * x.min, c * x.max) } else { (c * x.max, c * x.min) };
+                rv.min += lo;
+                rv.max += hi;
+            }
+            rv
+        },
+        Expr::Dist { dims: [dx, dy], point } => {
+            let (Some(x), Some(y)) = (dims.get(*dx), dims.get(*dy)) else {
+                return unknown;
+            };
+            let near_x = (x.min - point[0]).max(point[0] - x.max).max(0.0);
+            let near_y = (y.min - point[1]).max(point[1] - y.max).max(0.0);
+            let far_x = (x.min - point[0]).abs().max((x.max - point[0]).abs());
+            let far_y = (y.min - point[1]).abs().max((y.max - point[1]).abs());
+            new_interval((near_x * near_x + near_y * near_y).sqrt(), (far_x * far_x + far_y * far_y).sqrt())
+        },
+        Expr::WallClearance => {
+            if dims.len() < 2 {
+                return unknown;
+            }
+            // only distances around the bound matter for the sign
+            wall_clearance(dims[0], dims[1], bound.abs() + 1.0)
+        },
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<String>, String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_alphanumeric() || c == '_' || c == '.' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_' || chars[i] == '.') {
+                i += 1;
+            }
+            tokens.push(chars[start..i].iter().collect());
+        } else if (c == '>' || c == '<') && chars.get(i + 1) == Some(&'=') {
+            tokens.push(format!("{}=", c));
+            i += 2;
+        } else if "()[],&|!<>+-*".contains(c) {
+            tokens.push(c.to_string());
+            i += 1;
+        } else {
+            return Err(format!("unexpected character '{}'", c));
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<String>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.pos).map(|t| t.as_str())
+    }
+
+    fn next(&mut self) -> Result<String, String> {
+        let t = self.tokens.get(self.pos).cloned().ok_or("unexpected end of formula")?;
+        self.pos += 1;
+        Ok(t)
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), String> {
+        let t = self.next()?;
+        if t != token {
+            return Err(format!("expected '{}' but found '{}'", token, t));
+        }
+        Ok(())
+    }
+
+    fn parse_or(&mut self) -> Result<Formula, String> {
+        let mut f = self.parse_and()?;
+        while self.peek() == Some("|") {
+            self.pos += 1;
+            f = Formula::or(f, self.parse_and()?);
+        }
+        Ok(f)
+    }
+
+    fn parse_and(&mut self) -> Result<Formula, String> {
+        let mut f = self.parse_unary()?;
+        while self.peek() == Some("&") {
+            self.pos += 1;
+            f = Formula::and(f, self.parse_unary()?);
+        }
+        Ok(f)
+    }
+
+    fn parse_unary(&mut self) -> Result<Formula, String> {
+        match self.peek() {
+            Some("!") => {
+                self.pos += 1;
+                Ok(Formula::negate(self.parse_unary()?))
+            },
+            Some("(") => {
+                self.pos += 1;
+                let f = self.parse_or()?;
+                self.expect(")")?;
+                Ok(f)
+            },
+            Some("true") => {
+                self.pos += 1;
+                Ok(Formula::True)
+            },
+            Some("false") => {
+                self.pos += 1;
+                Ok(Formula::negate(Formula::True))
+            },
+            Some(op @ ("G" | "F")) => {
+                let always = op == "G";
+                self.pos += 1;
+                self.expect("[")?;
+                let a = self.parse_number()?;
+                self.expect(",")?;
+                let b = self.parse_number()?;
+                self.expect("]")?;
+                if !(0.0 <= a && a <= b) {
+                    return Err(format!("bad time interval [{}, {}]", a, b));
+                }
+                let f = self.parse_unary()?;
+                Ok(if always { Formula::always(a, b, f) } else { Formula::eventually(a, b, f) })
+            },
+            _ => self.parse_predicate(),
+        }
+    }
+
+    fn parse_predicate(&mut self) -> Result<Formula, String> {
+        let expr = match self.peek() {
+            Some("wall_clearance") => {
+                self.pos += 1;
+                Expr::WallClearance
+            },
+            Some("dist") => {
+                self.pos += 1;
+                self.expect("(")?;
+                let dx = self.parse_variable()?;
+                self.expect(",")?;
+                let dy = self.parse_variable()?;
+                self.expect(",")?;
+                let px = self.parse_number()?;
+                self.expect(",")?;
+                let py = self.parse_number()?;
+                self.expect(")")?;
+                Expr::Dist { dims: [dx, dy], point: [px, py] }
+            },
+            _ => self.parse_linear()?,
+        };
+
+        let cmp = self.next()?;
+        let greater = match cmp.as_str() {
+            ">=" | ">" => true,
+            "<=" | "<" => false,
+            _ => return Err(format!("expected a comparison but found '{}'", cmp)),
+        };
+        let bound = self.parse_number()?;
+        Ok(Formula::Predicate { expr, greater, bound })
+    }
+
+    // [-] [c *] x<i> { (+|-) [c *] x<i> }
+    fn parse_linear(&mut self) -> Result<Expr, String> {
+        let mut terms = Vec::new();
+        let mut sign = 1.0;
+        if self.peek() == Some("-") {
+            self.pos += 1;
+            sign = -1.0;
+        }
+        loop {
+            let mut coeff = sign;
+            if !self.peek().is_some_and(|t| t.starts_with('x')) {
+                coeff *= self.parse_number()?;
+                self.expect("*")?;
+            }
+            terms.push((self.parse_variable()?, coeff));
+
+            match self.peek() {
+                Some("+") => sign = 1.0,
+                Some("-") => sign = -1.0,
+                _ => break,
+            }
+            self.pos += 1;
+        }
+        Ok(Expr::Linear(terms))
+    }
+
+    fn parse_variable(&mut self) -> Result<usize, String> {
+        let t = self.next()?;
+        t.strip_prefix('x')
+            .and_then(|d| d.parse().ok())
+            .ok_or(format!("expected a variable x<dim> but found '{}'", t))
+    }
+
+    fn parse_number(&mut self) -> Result<f64, String> {
+        let mut sign = 1.0;
+        if self.peek() == Some("-") {
+            self.pos += 1;
+            sign = -1.0;
+        }
+        let t = self.next()?;
+        t.parse::<f64>().map(|v| sign * v).map_err(|_| format!("expected a number but found '{}'", t))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 1D trace moving from x = 0 to x = 2 in one second steps
+    fn trace() -> Vec<(f64, Vec<Interval>)> {
+        vec![
+            (0.0, vec![new_interval(0.0, 0.0)]),
+            (0.0, vec![new_interval(0.0, 1.0)]),
+            (1.0, vec![new_interval(0.9, 2.0)]),
+            (2.0, vec![new_interval(1.9, 2.0)]),
+        ]
+    }
+
+    #[test]
+    fn test_parse_formula() {
+        let f = parse_formula("G[0, 2] wall_clearance >= 0.3 & F[0,2] dist(x0, x1, 3.0, -1.0) <= 0.5").unwrap();
+        assert_eq!(f, Formula::and(
+            Formula::always(0.0, 2.0, Formula::wall_clearance(0.3)),
+            Formula::eventually(0.0, 2.0, Formula::Predicate { expr: Expr::Dist { dims: [0, 1], point: [3.0, -1.0] }, greater: false, bound: 0.5 }),
+        ));
+
+        let f = parse_formula("!(x0 - 2 * x1 > -1 | x2 < 3)").unwrap();
+        assert_eq!(f, Formula::negate(Formula::or(
+            Formula::Predicate { expr: Expr::Linear(vec![(0, 1.0), (1, -2.0)]), greater: true, bound: -1.0 },
+            Formula::Predicate { expr: Expr::Linear(vec![(2, 1.0)]), greater: false, bound: 3.0 },
+        )));
+
+        assert!(parse_formula("G[2, 1] x0 > 0").is_err());
+        assert!(parse_formula("x0 >= 1 &").is_err());
+        assert!(parse_formula("x0 = 1").is_err());
+    }
+
+    #[test]
+    fn test_evaluate_temporal() {
+        let t = trace();
+        assert_eq!(evaluate(&parse_formula("G[0, 2] x0 >= -0.5").unwrap(), &t), Verdict::Sat);
+        assert_eq!(evaluate(&parse_formula("F[0, 2] x0 >= 1.5").unwrap(), &t), Verdict::Sat);
+        assert_eq!(evaluate(&parse_formula("F[0, 2] x0 >= 2.5").unwrap(), &t), Verdict::Unsat);
+        // the first second only reaches x = 1, the box also covers the second after it
+        assert_eq!(evaluate(&parse_formula("F[0, 0.5] x0 >= 1.5").unwrap(), &t), Verdict::Unsat);
+        assert_eq!(evaluate(&parse_formula("F[0, 1] x0 >= 0.95").unwrap(), &t), Verdict::Unknown);
+        // nothing is known after the end of the trace
+        assert_eq!(evaluate(&parse_formula("G[0, 3] x0 >= -0.5").unwrap(), &t), Verdict::Unknown);
+        assert_eq!(evaluate(&parse_formula("G[0, 3] x0 >= 2.5").unwrap(), &t), Verdict::Unsat);
+    }
+
+    #[test]
+    fn test_evaluate_dist() {
+        let t = vec![(0.0, vec![new_interval(0.0, 1.0), new_interval(0.0, 1.0)])];
+        assert_eq!(evaluate(&Formula::near([0.5, 0.5], 1.0), &t), Verdict::Sat);
+        assert_eq!(evaluate(&Formula::near([3.0, 0.5], 1.0), &t), Verdict::Unsat);
+        assert_eq!(evaluate(&Formula::near([1.5, 0.5], 1.0), &t), Verdict::Unknown);
+    }
+
+    #[test]
+    fn test_monitor_check() {
+        assert!(stl_monitor_check());
+
+        set_stl_monitor(Some(parse_formula("F[0, 1] x0 >= 0.95").unwrap()));
+        for (time, dims) in trace() {
+            STL_TRACE.lock().unwrap().push((time, dims));
+        }
+        // the formula may or may not hold on the tube
+        assert!(!stl_monitor_check());
+        assert_eq!(stl_monitor_verdict(), Verdict::Unknown);
+
+        set_stl_monitor(Some(parse_formula("F[0, 2] x0 >= 1.5").unwrap()));
+        for (time, dims) in trace() {
+            STL_TRACE.lock().unwrap().push((time, dims));
+        }
+        assert!(stl_monitor_check());
+        set_stl_monitor(None);
+    }
+}
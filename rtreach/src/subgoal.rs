@@ -0,0 +1,317 @@
+// Generation and ranking of subgoal candidates for the safe subgoal selection. A candidate
+// is scored once its reach set has been found safe, the safe candidate with the highest
+// score is chosen.
+
+use serde::Deserialize;
+
+// Candidates added to the ones on the path segment, so there are still safe candidates
+// when an obstacle sits on the segment. They follow the segment candidates in the list.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum SubgoalPattern {
+    // only the candidates on the path segment
+    Line,
+    // the segment candidates shifted sideways by 1..=count times spacing on both sides
+    LateralOffsets { spacing: f64, count: u32 },
+    // count points at distance radius from the vehicle, with headings spread evenly over
+    // [heading - spread, heading + spread]
+    Arcs { radius: f64, spread: f64, count: u32 },
+    // square lattice of (2 * half_count + 1)^2 points with the given spacing centered on the vehicle
+    Grid { spacing: f64, half_count: u32 },
+    // the segment candidates shifted up and down (dim 2) by 1..=count times spacing,
+    // detours over or under obstacles, nothing for planar vehicles
+    VerticalOffsets { spacing: f64, count: u32 },
+}
+
+impl SubgoalPattern {
+    // pattern with default parameters by name: line, lateral, arcs, grid or vertical
+    pub fn from_name(name: &str) -> Option<SubgoalPattern> {
+        match name {
+            "line" => Some(SubgoalPattern::Line),
+            "lateral" => Some(SubgoalPattern::LateralOffsets { spacing: 0.5, count: 2 }),
+            "arcs" => Some(SubgoalPattern::Arcs { radius: 2.0, spread: std::f64::consts::FRAC_PI_2, count: 7 }),
+            "grid" => Some(SubgoalPattern::Grid { spacing: 0.5, half_count: 2 }),
+            "vertical" => Some(SubgoalPattern::VerticalOffsets { spacing: 0.5, count: 2 }),
+            _ => None,
+        }
+    }
+}
+
+impl TryFrom<String> for SubgoalPattern {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<SubgoalPattern, String> {
+        SubgoalPattern::from_name(&name).ok_or_else(|| format!("unknown subgoal pattern {:?}", name))
+    }
+}
+
+// Candidates of the pattern for the segment candidates `line` on the segment from start to
+// goal, the vehicle is at position with heading. Only x and y (dims 0 and 1) are placed,
+// or the altitude (dim 2) for VerticalOffsets, the other dims are copied from the segment
+// candidate, or from the first one for the patterns around the vehicle.
+pub fn pattern_subgoals<const DIMS: usize>(
+    pattern: SubgoalPattern,
+    start: &[f64; DIMS],
+    goal: &[f64; DIMS],
+    line: &[[f64; DIMS]],
+    position: [f64; 2],
+    heading: f64,
+) -> Vec<[f64; DIMS]> {
+    let mut subgoals = Vec::new();
+    let Some(first) = line.first() else {
+        return subgoals;
+    };
+    let at = |x: f64, y: f64, base: &[f64; DIMS]| {
+        let mut p = *base;
+        p[0] = x;
+        p[1] = y;
+        p
+    };
+
+    match pattern {
+        SubgoalPattern::Line => {},
+        SubgoalPattern::LateralOffsets { spacing, count } => {
+            let (dx, dy) = (goal[0] - start[0], goal[1] - start[1]);
+            let len = (dx * dx + dy * dy).sqrt();
+            if len == 0.0 {
+                return subgoals;
+            }
+            // left normal of the segment
+            let normal = [-dy / len, dx / len];
+            for k in 1..=count {
+                for side in [1.0, -1.0] {
+                    let offset = side * k as f64 * spacing;
+                    for p in line {
+                        subgoals.push(at(p[0] + offset * normal[0], p[1] + offset * normal[1], p));
+                    }
+                }
+            }
+        },
+        SubgoalPattern::Arcs { radius, spread, count } => {
+            for i in 0..count {
+                let t = if count > 1 { i as f64 / (count - 1) as f64 } else { 0.5 };
+                let angle = heading - spread + 2.0 * spread * t;
+                subgoals.push(at(position[0] + radius * angle.cos(), position[1] + radius * angle.sin(), first));
+            }
+        },
+        SubgoalPattern::Grid { spacing, half_count } => {
+            let n = half_count as i64;
+            for i in -n..=n {
+                for j in -n..=n {
+                    if i != 0 || j != 0 {
+                        subgoals.push(at(position[0] + i as f64 * spacing, position[1] + j as f64 * spacing, first));
+                    }
+                }
+            }
+        },
+        SubgoalPattern::VerticalOffsets { spacing, count } => {
+            if DIMS < 3 {
+                return subgoals;
+            }
+            for k in 1..=count {
+                for side in [1.0, -1.0] {
+                    for p in line {
+                        let mut q = *p;
+                        q[2] += side * k as f64 * spacing;
+                        subgoals.push(q);
+                    }
+                }
+            }
+        },
+    }
+    subgoals
+}
+
+// Order pattern candidates by their distance to the waypoint, closest first, so their rank
+// follows the progress they make. Equally close candidates keep the pattern order.
+pub fn sort_by_progress<const DIMS: usize>(subgoals: &mut [[f64; DIMS]], waypoint: &[f64; DIMS]) {
+    let distance = |p: &[f64; DIMS]| p.iter().zip(waypoint).map(|(a, b)| (a - b) * (a - b)).sum::<f64>();
+    subgoals.sort_by(|a, b| distance(a).total_cmp(&distance(b)));
+}
+
+// What is known about a safe subgoal candidate
+pub struct SubgoalCandidate<'a> {
+    // rank in the candidate list, 0 is the furthest along the path segment
+    pub rank: usize,
+    pub subgoal: &'a [f64],
+    // end of the path segment the subgoals are placed on
+    pub waypoint: &'a [f64],
+    pub control: &'a [f64],
+    // smallest clearance of the reach set over the horizon, see LiftingOptions::clearance
+    pub clearance: f64,
+    // absolute heading change needed to face the subgoal, in radians
+    pub heading_change: f64,
+}
+
+pub trait SubgoalScorer {
+    // higher is better
+    fn score(&self, candidate: &SubgoalCandidate) -> f64;
+
+    // true if the score never increases with the rank, so the first safe candidate is the
+    // best and the others do not have to be checked
+    fn first_safe_is_best(&self) -> bool {
+        false
+    }
+}
+
+// The subgoal furthest along the segment, this is the original first-safe selection
+#[derive(Clone, Copy, Default)]
+pub struct FurthestAlong;
+
+impl SubgoalScorer for FurthestAlong {
+    fn score(&self, candidate: &SubgoalCandidate) -> f64 {
+        -(candidate.rank as f64)
+    }
+
+    fn first_safe_is_best(&self) -> bool {
+        true
+    }
+}
+
+// Weighted sum of the progress (minus the distance from the subgoal to the waypoint), the
+// clearance, the control effort (norm of the control input) and the heading change.
+// The clearance is capped at max_clearance so a free area does not outweigh the progress.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WeightedScorer {
+    pub progress: f64,
+    pub clearance: f64,
+    pub max_clearance: f64,
+    pub effort: f64,
+    pub heading: f64,
+}
+
+impl Default for WeightedScorer {
+    fn default() -> Self {
+        WeightedScorer { progress: 1.0, clearance: 1.0, max_clearance: 1.0, effort: 0.0, heading: 0.0 }
+    }
+}
+
+impl SubgoalScorer for WeightedScorer {
+    fn score(&self, candidate: &SubgoalCandidate) -> f64 {
+        let distance = candidate.subgoal.iter()
+            .zip(candidate.waypoint)
+            .map(|(s, w)| (s - w) * (s - w))
+            .sum::<f64>()
+            .sqrt();
+        let effort = candidate.control.iter().map(|u| u * u).sum::<f64>().sqrt();
+
+        -self.progress * distance
+            + self.clearance * candidate.clearance.min(self.max_clearance)
+            - self.effort * effort
+            - self.heading * candidate.heading_change
+    }
+}
+
+// The scorer of the subgoal selection. By name: furthest (FurthestAlong) or weighted
+// (WeightedScorer with the default weights), or in a scenario a table of the weights.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(try_from = "ScoreSpec")]
+pub enum SubgoalScore {
+    FurthestAlong,
+    Weighted(WeightedScorer),
+}
+
+impl SubgoalScore {
+    pub fn from_name(name: &str) -> Option<SubgoalScore> {
+        match name {
+            "furthest" => Some(SubgoalScore::FurthestAlong),
+            "weighted" => Some(SubgoalScore::Weighted(WeightedScorer::default())),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ScoreSpec {
+    Name(String),
+    Weights(WeightedScorer),
+}
+
+impl TryFrom<ScoreSpec> for SubgoalScore {
+    type Error = String;
+
+    fn try_from(spec: ScoreSpec) -> Result<SubgoalScore, String> {
+        match spec {
+            ScoreSpec::Name(name) => SubgoalScore::from_name(&name).ok_or_else(|| format!("unknown subgoal scorer {:?}", name)),
+            ScoreSpec::Weights(weights) => Ok(SubgoalScore::Weighted(weights)),
+        }
+    }
+}
+
+impl SubgoalScorer for SubgoalScore {
+    fn score(&self, candidate: &SubgoalCandidate) -> f64 {
+        match self {
+            SubgoalScore::FurthestAlong => FurthestAlong.score(candidate),
+            SubgoalScore::Weighted(scorer) => scorer.score(candidate),
+        }
+    }
+
+    fn first_safe_is_best(&self) -> bool {
+        match self {
+            SubgoalScore::FurthestAlong => FurthestAlong.first_safe_is_best(),
+            SubgoalScore::Weighted(scorer) => scorer.first_safe_is_best(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pattern_subgoals() {
+        let line = [[4.0, 0.0, 1.0], [2.0, 0.0, 1.0]];
+        let (start, goal) = ([0.0, 0.0, 1.0], [4.0, 0.0, 1.0]);
+
+        let offsets = pattern_subgoals(SubgoalPattern::LateralOffsets { spacing: 0.5, count: 2 }, &start, &goal, &line, [0.0, 0.0], 0.0);
+        assert_eq!(offsets.len(), 8);
+        assert_eq!(offsets[0], [4.0, 0.5, 1.0]);
+        assert_eq!(offsets[3], [2.0, -0.5, 1.0]);
+        assert_eq!(offsets[7], [2.0, -1.0, 1.0]);
+
+        let arcs = pattern_subgoals(SubgoalPattern::Arcs { radius: 1.0, spread: std::f64::consts::FRAC_PI_2, count: 3 }, &start, &goal, &line, [1.0, 1.0], 0.0);
+        assert_eq!(arcs.len(), 3);
+        assert!((arcs[0][0] - 1.0).abs() < 1e-12 && (arcs[0][1] - 0.0).abs() < 1e-12);
+        assert_eq!(arcs[1], [2.0, 1.0, 1.0]);
+
+        let grid = pattern_subgoals(SubgoalPattern::Grid { spacing: 0.5, half_count: 1 }, &start, &goal, &line, [1.0, 1.0], 0.0);
+        assert_eq!(grid.len(), 8);
+        assert!(grid.contains(&[1.5, 0.5, 1.0]) && !grid.contains(&[1.0, 1.0, 1.0]));
+        assert!(pattern_subgoals(SubgoalPattern::Line, &start, &goal, &line, [1.0, 1.0], 0.0).is_empty());
+
+        let vertical = pattern_subgoals(SubgoalPattern::VerticalOffsets { spacing: 0.5, count: 1 }, &start, &goal, &line, [0.0, 0.0], 0.0);
+        assert_eq!(vertical, vec![[4.0, 0.0, 1.5], [2.0, 0.0, 1.5], [4.0, 0.0, 0.5], [2.0, 0.0, 0.5]]);
+        // planar vehicles have no altitude to shift
+        assert!(pattern_subgoals(SubgoalPattern::VerticalOffsets { spacing: 0.5, count: 1 }, &[0.0, 0.0], &[4.0, 0.0], &[[4.0, 0.0]], [0.0, 0.0], 0.0).is_empty());
+    }
+
+    #[test]
+    fn test_sort_by_progress() {
+        let line = [[4.0, 0.0], [2.0, 0.0]];
+        let mut grid = pattern_subgoals(SubgoalPattern::Grid { spacing: 1.0, half_count: 1 }, &[0.0, 0.0], &[4.0, 0.0], &line, [1.0, 0.0], 0.0);
+        sort_by_progress(&mut grid, &[4.0, 0.0]);
+        assert_eq!(grid[0], [2.0, 0.0]);
+        // (2, -1) and (2, 1) are as close, the pattern lists (2, -1) first
+        assert_eq!(grid[1..3], [[2.0, -1.0], [2.0, 1.0]]);
+        assert_eq!(grid[7], [0.0, 1.0]);
+    }
+
+    fn candidate<'a>(rank: usize, subgoal: &'a [f64], clearance: f64) -> SubgoalCandidate<'a> {
+        SubgoalCandidate { rank, subgoal, waypoint: &[4.0, 0.0], control: &[0.0, 1.0], clearance, heading_change: 0.0 }
+    }
+
+    #[test]
+    fn test_weighted_scorer() {
+        let near_wall = candidate(0, &[3.0, 0.0], 0.05);
+        let open = candidate(1, &[2.5, 0.0], 1.5);
+
+        assert!(FurthestAlong.score(&near_wall) > FurthestAlong.score(&open));
+        let scorer = WeightedScorer::default();
+        // 0.5 m less progress for 0.95 m more (capped) clearance
+        assert!(scorer.score(&open) > scorer.score(&near_wall));
+        let scorer = WeightedScorer { clearance: 0.1, ..WeightedScorer::default() };
+        assert!(scorer.score(&open) < scorer.score(&near_wall));
+    }
+}
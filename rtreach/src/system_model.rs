@@ -1,23 +1,51 @@
-use super::geometry::HyperRectangle;
-pub trait SystemModel<const NUM_DIMS: usize> {
+use super::geometry::{HyperRectangle, Interval};
+use super::face_lift::make_neighborhood_rect;
+pub trait SystemModel<const NUM_DIMS: usize, const NUM_CTRLS: usize> {
     // Calculate derivative of hyperrectangle state for a given control input
     fn get_derivative_bounds(
         &self,
         rect: &HyperRectangle<NUM_DIMS>,
         face_index: usize,
-        ctrl_inputs: &Vec<f64>,
+        ctrl_inputs: &[f64; NUM_CTRLS],
     ) -> f64;
 
+    // Derivative bounds of all faces of rect at once, for each dimension the min is taken over
+    // the neighborhood of the min face and the max over the neighborhood of the max face, the
+    // neighborhoods being the faces of rect extended by neb_width inside bloated_rect.
+    // Face lifting calls this, the default calls get_derivative_bounds once per face. Override
+    // it to share work between faces, e.g. a derivative that does not depend on its own
+    // dimension has the same bounds on both neighborhoods as on bloated_rect.
+    fn get_face_derivative_bounds(
+        &self,
+        rect: &HyperRectangle<NUM_DIMS>,
+        bloated_rect: &HyperRectangle<NUM_DIMS>,
+        neb_width: &[[f64; 2]; NUM_DIMS],
+        ctrl_inputs: &[f64; NUM_CTRLS],
+    ) -> [Interval; NUM_DIMS] {
+        let mut face_neb_rect: HyperRectangle<NUM_DIMS> = HyperRectangle::<NUM_DIMS>::default();
+        std::array::from_fn(|d| {
+            make_neighborhood_rect::<NUM_DIMS>(&mut face_neb_rect, 2 * d, bloated_rect, rect, neb_width[d][0]);
+            let min = self.get_derivative_bounds(&face_neb_rect, 2 * d, ctrl_inputs);
+            make_neighborhood_rect::<NUM_DIMS>(&mut face_neb_rect, 2 * d + 1, bloated_rect, rect, neb_width[d][1]);
+            let max = self.get_derivative_bounds(&face_neb_rect, 2 * d + 1, ctrl_inputs);
+            Interval { min, max }
+        })
+    }
+
     // Calculate next control given a hyperrectangle state
     fn sample_control(
         &self,
         rect: &HyperRectangle<NUM_DIMS>,
-    ) -> Vec<f64>;
+    ) -> [f64; NUM_CTRLS];
 
     fn num_dims(&self) -> usize {
         NUM_DIMS
     }
 
+    fn num_ctrls(&self) -> usize {
+        NUM_CTRLS
+    }
+
     fn num_faces(&self) -> usize {
         2 * NUM_DIMS
     }
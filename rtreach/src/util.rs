@@ -1,15 +1,14 @@
-use std::sync::Mutex;
 use csv::Writer;
 use csv::ReaderBuilder;
 use lazy_static::lazy_static;
 use super::geometry::println;
-use std::time::{SystemTime, UNIX_EPOCH, Duration};
+use std::time::Instant;
 use super::face_lift::LiftingSettings;
 use super::geometry::HyperRectangle;
 
 lazy_static! {
-    pub static ref INIIALIZED: Mutex<bool> = Mutex::new(false);
-    pub static ref START_SEC: Mutex<u64> = Mutex::new(0);
+    // reference point of milliseconds and microseconds, monotonic unlike the system time
+    static ref CLOCK_START: Instant = Instant::now();
 }
 
 pub fn save_states_to_csv<const NUM_DIMS: usize>(filename: &str, data: &Vec<[f64; NUM_DIMS]>) {
@@ -82,6 +81,29 @@ pub fn save_reachtubes_to_csv<const NUM_DIMS: usize>(filename: &str, data: &Vec<
     wtr.flush().unwrap();
 }
 
+// Cells labeled by label_grid, one row per cell with its bounds and 1 if safe
+pub fn save_grid_labels_to_csv<const NUM_DIMS: usize>(filename: &str, data: &Vec<(HyperRectangle<NUM_DIMS>, bool)>) {
+    let mut wtr = Writer::from_path(filename).unwrap();
+    let mut header = vec![];
+    for d in 0..NUM_DIMS {
+        header.push(format!("min{}", d));
+        header.push(format!("max{}", d));
+    }
+    header.push("safe".to_string());
+    let _ = wtr.write_record(&header);
+
+    for (cell, safe) in data {
+        let mut record = vec![];
+        for d in 0..NUM_DIMS {
+            record.push(format!("{}", cell.dims[d].min));
+            record.push(format!("{}", cell.dims[d].max));
+        }
+        record.push(format!("{}", *safe as u8));
+        let _ = wtr.write_record(&record);
+    }
+    wtr.flush().unwrap();
+}
+
 pub fn load_paths_from_csv(filename: &std::path::PathBuf) -> Vec<Vec<[f64; 3]>>{
     let mut paths: Vec<Vec<[f64; 3]>> = vec![];
     println!("Loading paths from file: {:?}", filename);
@@ -113,7 +135,7 @@ pub fn error_exit<const NUM_DIMS: usize>(str: &str, error_print_params: &Lifting
     if error_params_assigned {
         println!("\nSettings:");
         println!("Reach Time = {}", error_print_params.reach_time);
-        println!("Runtime = {} ms", error_print_params.max_runtime_milliseconds);
+        println!("Runtime = {} us", error_print_params.max_runtime_us);
         println!("Init = ");
         println(&error_print_params.init);
     } else {
@@ -123,49 +145,14 @@ pub fn error_exit<const NUM_DIMS: usize>(str: &str, error_print_params: &Lifting
     std::process::exit(1);
 }
 
+// milliseconds since the first call to milliseconds or microseconds
 pub fn milliseconds() -> u64 {
-    // Set startSec to 0
-    let mut start_sec: std::sync::MutexGuard<'_, u64> = START_SEC.lock().unwrap();
-    *start_sec = 0;
-
-    // Get current time
-    let now: SystemTime = SystemTime::now();
-    let now_since_epoch: Duration = now.duration_since(UNIX_EPOCH).unwrap();
-    let now_secs: u64 = now_since_epoch.as_secs();
-    let now_millis: u32 = now_since_epoch.subsec_millis();
-
-    // Initialize if not already
-    let mut initialized: std::sync::MutexGuard<'_, bool> = INIIALIZED.lock().unwrap();
-    if !*initialized {
-        *initialized = true;
-    }
-
-    // Calculate difference in milliseconds
-    let dif_sec: u64 = now_secs - *start_sec;
-    let ds: u64 = dif_sec * 1000 + now_millis as u64;
-
-    // Uncomment to print the result
-    // println!("from milliseconds: {}", ds);
-
-    ds
+    CLOCK_START.elapsed().as_millis() as u64
 }
 
-
-pub fn milliseconds2(t1: &Duration) -> u64 {
-    // Get the current time
-    let now: SystemTime = SystemTime::now();
-    let now_duration: Duration = now.duration_since(UNIX_EPOCH).unwrap();
-    let now_sec: u64 = now_duration.as_secs();
-    let now_usec: u32 = now_duration.subsec_micros();
-
-    // Calculate elapsed time
-    let elapsed_time_secs: i64 = now_sec as i64 - t1.as_secs() as i64;
-    let elapsed_time_usecs: i64 = now_usec as i64 - t1.subsec_micros() as i64;
-
-    let mut elapsed_time_millis: i64 = elapsed_time_secs * 1000;
-    elapsed_time_millis += elapsed_time_usecs / 1000;
-
-    elapsed_time_millis as u64
+// microseconds since the first call to milliseconds or microseconds
+pub fn microseconds() -> u64 {
+    CLOCK_START.elapsed().as_micros() as u64
 }
 
 pub fn distance_3d(pos1: &[f64], pos2: &[f64]) -> f64 {
@@ -187,4 +174,15 @@ pub fn norm(vec: &[f64]) -> f64 {
         sum += v*v;
     }
     sum.sqrt()
-}
\ No newline at end of file
+}
+// Function to normalize the angle between -π and π
+pub fn normalize_angle(angle: f64) -> f64 {
+    let mut normalized = angle;
+    while normalized > std::f64::consts::PI {
+        normalized -= 2.0 * std::f64::consts::PI;
+    }
+    while normalized < -std::f64::consts::PI {
+        normalized += 2.0 * std::f64::consts::PI;
+    }
+    normalized
+}
@@ -0,0 +1,76 @@
+// The interface of a vehicle model the subgoal and control selection of controller.rs is
+// written against. The vehicle has NUM_DIMS states, NUM_CTRLS control inputs and takes
+// positions of GOAL_DIMS dimensions as goals, the first GOAL_DIMS dims of the state.
+
+use super::face_lift::{ClearanceFn, LiftingOptions, LiftingResult};
+use super::footprint::Footprint;
+use super::geometry::{HyperRectangle, Interval};
+use super::initial_split::{InitialSetSplit, SubBoxRun};
+use super::obstacle::Obstacle;
+
+pub trait Vehicle<const NUM_DIMS: usize, const GOAL_DIMS: usize, const NUM_CTRLS: usize> {
+    // state dim of the heading
+    const HEADING_DIM: usize;
+
+    // radius of the vehicle for the circle check of select_safe_subgoal_circle
+    const ROBOT_RADIUS: f64;
+
+    // how far behind the projection of the vehicle the sliding window of the circle check
+    // starts, the reach set checks use 5 m
+    const CIRCLE_RANGE_BEHIND: f64;
+
+    // whether the candidates on the whole segment include the segment start
+    const SUBGOALS_FROM_START: bool;
+
+    // raw controls searched by select_safe_control_direct
+    const CONTROL_BOUNDS: [Interval; NUM_CTRLS];
+
+    // signed distance of a hull from the obstacles and walls, see LiftingOptions::clearance
+    const CLEARANCE_FN: ClearanceFn<NUM_DIMS>;
+
+    fn set_goal(&mut self, goal: [f64; GOAL_DIMS]);
+
+    // action of the controller for the current goal
+    fn sample_state_action(&self, state: &[f64; NUM_DIMS]) -> [f64; NUM_CTRLS];
+
+    // multiplies the velocity command of the controller
+    fn set_speed_scale(&mut self, scale: f64);
+
+    // footprint used by the safety checks
+    fn footprint(&self) -> Footprint;
+
+    fn has_collided(&self, state: &[f64; NUM_DIMS]) -> bool;
+
+    // reach set from state under ctrl_input, or under the controller resampled over the
+    // boxes with options.dynamic_control, safe if it avoids the obstacles and walls. The
+    // stored rects start with the initial state.
+    #[allow(clippy::too_many_arguments)]
+    fn run_reachability(
+        &self,
+        state: [f64; NUM_DIMS],
+        ctrl_input: &[f64; NUM_CTRLS],
+        sim_time: f64,
+        init_step_size: f64,
+        max_runtime_us: u64,
+        start_ms: u64,
+        obstacle_sim_fn: fn(t: f64, obs: &mut Vec<Obstacle>),
+        options: LiftingOptions<NUM_DIMS>,
+    ) -> (LiftingResult, Vec<(f64, HyperRectangle<NUM_DIMS>)>);
+
+    // run_reachability from the box of uncertain initial states init, split into sub-boxes,
+    // see face_lifting_split. The rects of each sub-box follow init in the stored rects.
+    // Err if the split cannot be run, e.g. in parallel while an STL monitor is set.
+    #[allow(clippy::too_many_arguments)]
+    fn run_reachability_split(
+        &self,
+        init: HyperRectangle<NUM_DIMS>,
+        ctrl_input: &[f64; NUM_CTRLS],
+        sim_time: f64,
+        init_step_size: f64,
+        max_runtime_us: u64,
+        start_ms: u64,
+        obstacle_sim_fn: fn(t: f64, obs: &mut Vec<Obstacle>),
+        split: &InitialSetSplit,
+        run: &SubBoxRun,
+    ) -> Result<(bool, Vec<(f64, HyperRectangle<NUM_DIMS>)>), String>;
+}
@@ -0,0 +1,268 @@
+use csv::{ReaderBuilder, WriterBuilder};
+use super::geometry::Interval;
+use super::interval::new_interval;
+use super::spatial_index::GridIndex;
+use super::util::distance_2d;
+
+// wall segment from one end point to the other, a single point if both are equal
+pub type Segment = [[f64; 2]; 2];
+
+// Walls as segments, every wall point they were made from lies within tolerance of them.
+// The simplified segments can cut inside the walls, so the checks grow the boxes by the
+// tolerance first, 0 for exact segments.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WallSegments {
+    pub segments: Vec<Segment>,
+    pub tolerance: f64,
+}
+
+// Does the segment touch the box x * y? Liang-Barsky clipping of the segment against the box,
+// touching the box counts as an intersection.
+pub fn segment_intersects_box(seg: &Segment, x: Interval, y: Interval) -> bool {
+    let [p0, p1] = *seg;
+    let dx = p1[0] - p0[0];
+    let dy = p1[1] - p0[1];
+
+    // part of the segment inside the box is p0 + t * (p1 - p0) for t in [t0, t1]
+    let mut t0: f64 = 0.0;
+    let mut t1: f64 = 1.0;
+    for (p, q) in [(-dx, p0[0] - x.min), (dx, x.max - p0[0]), (-dy, p0[1] - y.min), (dy, y.max - p0[1])] {
+        if p == 0.0 {
+            // parallel to this side and outside of it
+            if q < 0.0 {
+                return false;
+            }
+        } else {
+            let r = q / p;
+            if p < 0.0 {
+                if r > t1 {
+                    return false;
+                }
+                t0 = t0.max(r);
+            } else {
+                if r < t0 {
+                    return false;
+                }
+                t1 = t1.min(r);
+            }
+        }
+    }
+
+    true
+}
+
+// distance between the segment and the box x * y, 0 if they touch
+pub fn segment_box_distance(seg: &Segment, x: Interval, y: Interval) -> f64 {
+    if segment_intersects_box(seg, x, y) {
+        return 0.0;
+    }
+    // otherwise the closest points are an end point of the segment or a corner of the box
+    let corners = [[x.min, y.min], [x.max, y.min], [x.max, y.max], [x.min, y.max]];
+    let from_ends = seg.iter().map(|p| point_box_distance(p, x, y));
+    let from_corners = corners.iter().map(|c| point_segment_distance(c, seg));
+    from_ends.chain(from_corners).fold(f64::INFINITY, f64::min)
+}
+
+pub fn point_box_distance(p: &[f64; 2], x: Interval, y: Interval) -> f64 {
+    let dx = (x.min - p[0]).max(p[0] - x.max).max(0.0);
+    let dy = (y.min - p[1]).max(p[1] - y.max).max(0.0);
+    (dx * dx + dy * dy).sqrt()
+}
+
+pub fn segment_bounding_box(seg: &Segment) -> [Interval; 2] {
+    [
+        new_interval(seg[0][0].min(seg[1][0]), seg[0][0].max(seg[1][0])),
+        new_interval(seg[0][1].min(seg[1][1]), seg[0][1].max(seg[1][1])),
+    ]
+}
+
+// Chain unordered wall points into polylines by repeatedly walking to the nearest unused
+// point within max_gap, first from the seed forward and then from the seed backward.
+pub fn chain_points(points: &[[f64; 2]], max_gap: f64) -> Vec<Vec<[f64; 2]>> {
+    let bounds: Vec<[Interval; 2]> = points.iter().map(|p| [new_interval(p[0], p[0]), new_interval(p[1], p[1])]).collect();
+    let index = GridIndex::new(&bounds, max_gap);
+    let mut used = vec![false; points.len()];
+
+    let nearest_unused = |p: [f64; 2], used: &mut Vec<bool>| -> Option<usize> {
+        let mut best: Option<(usize, f64)> = None;
+        index.visit(new_interval(p[0] - max_gap, p[0] + max_gap), new_interval(p[1] - max_gap, p[1] + max_gap), |id| {
+            let d = distance_2d(&p, &points[id]);
+            if !used[id] && d <= max_gap && best.is_none_or(|(_, best_d)| d < best_d) {
+                best = Some((id, d));
+            }
+            true
+        });
+        let (id, _) = best?;
+        used[id] = true;
+        Some(id)
+    };
+
+    let mut polylines = Vec::new();
+    for seed in 0..points.len() {
+        if used[seed] {
+            continue;
+        }
+        used[seed] = true;
+
+        let mut forward = vec![points[seed]];
+        while let Some(id) = nearest_unused(*forward.last().unwrap(), &mut used) {
+            forward.push(points[id]);
+        }
+        let mut backward = vec![];
+        let mut tail = points[seed];
+        while let Some(id) = nearest_unused(tail, &mut used) {
+            backward.push(points[id]);
+            tail = points[id];
+        }
+
+        backward.reverse();
+        backward.extend(forward);
+        polylines.push(backward);
+    }
+
+    polylines
+}
+
+// Douglas-Peucker simplification, every dropped point is within tolerance of the result
+pub fn simplify_polyline(polyline: &[[f64; 2]], tolerance: f64) -> Vec<[f64; 2]> {
+    if polyline.len() < 3 {
+        return polyline.to_vec();
+    }
+
+    let first = polyline[0];
+    let last = polyline[polyline.len() - 1];
+    let mut max_dist = 0.0;
+    let mut max_idx = 0;
+    for (i, p) in polyline.iter().enumerate().take(polyline.len() - 1).skip(1) {
+        let d = point_segment_distance(p, &[first, last]);
+        if d > max_dist {
+            max_dist = d;
+            max_idx = i;
+        }
+    }
+
+    if max_dist <= tolerance {
+        return vec![first, last];
+    }
+    let mut rv = simplify_polyline(&polyline[..=max_idx], tolerance);
+    rv.pop();
+    rv.extend(simplify_polyline(&polyline[max_idx..], tolerance));
+    rv
+}
+
+// Convert a wall point cloud, e.g. porto_obstacles.txt, into segments. Isolated points
+// are kept as single point segments.
+pub fn wall_points_to_segments(points: &[[f64; 2]], max_gap: f64, tolerance: f64) -> WallSegments {
+    let mut segments = Vec::new();
+    for polyline in chain_points(points, max_gap) {
+        let simplified = simplify_polyline(&polyline, tolerance);
+        if simplified.len() == 1 {
+            segments.push([simplified[0], simplified[0]]);
+        }
+        for pair in simplified.windows(2) {
+            segments.push([pair[0], pair[1]]);
+        }
+    }
+    WallSegments { segments, tolerance }
+}
+
+// segment list with one "x1, y1, x2, y2, tolerance" row per segment, the tolerance column
+// is optional and 0 if missing
+pub fn load_wall_segments_from_csv(filename: &std::path::PathBuf) -> WallSegments {
+    let mut walls = WallSegments::default();
+    println!("Loading wall segments from file: {:?}", filename);
+    let mut reader = ReaderBuilder::new().has_headers(false).flexible(true).trim(csv::Trim::All).from_path(filename).unwrap();
+    for result in reader.records() {
+        let record = result.unwrap();
+        let v: Vec<f64> = (0..4).map(|i| record.get(i).unwrap().parse::<f64>().unwrap()).collect();
+        walls.segments.push([[v[0], v[1]], [v[2], v[3]]]);
+        if let Some(tolerance) = record.get(4) {
+            walls.tolerance = walls.tolerance.max(tolerance.parse::<f64>().unwrap());
+        }
+    }
+    walls
+}
+
+pub fn save_wall_segments_to_csv(filename: &str, walls: &WallSegments) {
+    let mut wtr = WriterBuilder::new().has_headers(false).from_path(filename).unwrap();
+    for s in &walls.segments {
+        let _ = wtr.write_record([s[0][0], s[0][1], s[1][0], s[1][1], walls.tolerance].map(|v| format!("{}", v)));
+    }
+    wtr.flush().unwrap();
+}
+
+pub fn point_segment_distance(p: &[f64; 2], seg: &Segment) -> f64 {
+    let [a, b] = *seg;
+    let ab = [b[0] - a[0], b[1] - a[1]];
+    let len_sq = ab[0] * ab[0] + ab[1] * ab[1];
+    if len_sq == 0.0 {
+        return distance_2d(p, &a);
+    }
+    let t = (((p[0] - a[0]) * ab[0] + (p[1] - a[1]) * ab[1]) / len_sq).clamp(0.0, 1.0);
+    distance_2d(p, &[a[0] + t * ab[0], a[1] + t * ab[1]])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_segment_intersects_box() {
+        // diagonal passing between two sampled points would still hit the box
+        let seg = [[0.0, 0.0], [1.0, 1.0]];
+        assert!(segment_intersects_box(&seg, new_interval(0.45, 0.55), new_interval(0.4, 0.6)));
+        assert!(!segment_intersects_box(&seg, new_interval(0.6, 0.9), new_interval(0.0, 0.4)));
+        assert!(!segment_intersects_box(&seg, new_interval(1.1, 2.0), new_interval(1.1, 2.0)));
+        // single point segment
+        assert!(segment_intersects_box(&[[0.5, 0.5], [0.5, 0.5]], new_interval(0.0, 1.0), new_interval(0.0, 1.0)));
+        assert!(!segment_intersects_box(&[[0.5, 1.5], [0.5, 1.5]], new_interval(0.0, 1.0), new_interval(0.0, 1.0)));
+    }
+
+    #[test]
+    fn test_segment_box_distance() {
+        let seg = [[2.0, -1.0], [2.0, 1.0]];
+        assert_eq!(segment_box_distance(&seg, new_interval(0.0, 1.5), new_interval(-0.5, 0.5)), 0.5);
+        assert_eq!(segment_box_distance(&seg, new_interval(0.0, 2.5), new_interval(-0.5, 0.5)), 0.0);
+        // closest to the end point
+        assert!((segment_box_distance(&seg, new_interval(3.0, 4.0), new_interval(2.0, 3.0)) - 2.0f64.sqrt()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_chain_points() {
+        // two walls sampled every 4 cm, given out of order
+        let mut points: Vec<[f64; 2]> = (0..25).map(|i| [i as f64 * 0.04, 0.0]).collect();
+        points.extend((0..25).map(|i| [i as f64 * 0.04, 2.0]));
+        points.swap(3, 30);
+        points.swap(10, 0);
+
+        let polylines = chain_points(&points, 0.1);
+        assert_eq!(polylines.len(), 2);
+        for poly in &polylines {
+            assert_eq!(poly.len(), 25);
+            assert!(poly.windows(2).all(|w| distance_2d(&w[0], &w[1]) < 0.05));
+        }
+    }
+
+    #[test]
+    fn test_wall_points_to_segments() {
+        // an L shaped wall simplifies to two segments
+        let mut points: Vec<[f64; 2]> = (0..=10).map(|i| [i as f64 * 0.05, 0.0]).collect();
+        points.extend((1..=10).map(|i| [0.5, i as f64 * 0.05]));
+        let walls = wall_points_to_segments(&points, 0.1, 0.01);
+        assert_eq!(walls.segments.len(), 2);
+        assert_eq!(walls.tolerance, 0.01);
+
+        // an isolated point is kept
+        let walls = wall_points_to_segments(&[[3.0, 3.0]], 0.1, 0.01);
+        assert_eq!(walls.segments, vec![[[3.0, 3.0], [3.0, 3.0]]]);
+
+        // the middle point of a slightly bent wall is dropped, a box touching it misses the
+        // segment unless grown by the tolerance
+        let walls = wall_points_to_segments(&[[0.0, 0.0], [0.05, 0.008], [0.1, 0.0]], 0.1, 0.01);
+        assert_eq!(walls.segments.len(), 1);
+        let (x, y) = (new_interval(0.05, 0.05), new_interval(0.008, 0.01));
+        assert!(!segment_intersects_box(&walls.segments[0], x, y));
+        let t = walls.tolerance;
+        assert!(segment_intersects_box(&walls.segments[0], new_interval(x.min - t, x.max + t), new_interval(y.min - t, y.max + t)));
+    }
+}